@@ -82,7 +82,7 @@ fn calc_curve_lengths(
     // last segment
     {
         let mut acc = 0.0;
-        for seg in indices.last().copied().unwrap_or(0)..curve.len() {
+        for seg in indices.last().copied().unwrap_or(0)..cubic.segment_count() {
             let seg_length = curve_length(&cubic.segment(seg).unwrap(), max_error);
             acc += seg_length;
         }
@@ -179,3 +179,47 @@ fn populate_with_interpolation(
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use monoxide_spiro::{SpiroCp, SpiroCpTy};
+
+    use super::*;
+
+    /// The lengths of the spiro segments of a polyline.
+    fn polyline_lengths(points: &[(f64, f64)], is_closed: bool) -> Vec<f64> {
+        let mut points = (points.iter())
+            .map(|&(x, y)| SpiroCp {
+                x,
+                y,
+                ty: SpiroCpTy::Corner,
+            })
+            .collect_vec();
+        if !is_closed {
+            points.first_mut().unwrap().ty = SpiroCpTy::Open;
+            points.last_mut().unwrap().ty = SpiroCpTy::EndOpen;
+        }
+        let curve = SpiroCurve::from_points(points, is_closed);
+
+        let (curves, indices) = crate::convert::spiro_to_cube_with_indices(&curve.points).unwrap();
+        let cubic = &curves[0];
+        let mut indices = indices.into_iter().map(|x| x.segment_index).collect_vec();
+        if !is_closed {
+            indices.push(cubic.segment_count());
+        }
+        calc_curve_lengths(&curve, cubic, &indices).unwrap()
+    }
+
+    /// The segments after the last control point run up to the end of the
+    /// cubic curve, which is not bounded by the number of spiro points: an
+    /// open polyline has one segment less than it has points.
+    #[test]
+    fn curve_lengths_end_at_last_cubic_segment() {
+        let lengths = polyline_lengths(&[(0., 0.), (1., 0.), (1., 3.)], false);
+        assert_eq!(lengths, [1., 3., 0.]);
+
+        // The closing segment of a closed curve counts towards the last point.
+        let lengths = polyline_lengths(&[(0., 0.), (2., 0.), (2., 1.), (0., 1.)], true);
+        assert_eq!(lengths, [2., 1., 2., 1.]);
+    }
+}
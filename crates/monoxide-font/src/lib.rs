@@ -22,9 +22,13 @@ impl InputContext {
 }
 
 pub fn make_font() -> FontContext {
-    let cx = InputContext {
-        settings: make_font_params(),
-    };
+    make_font_with(make_font_params())
+}
+
+/// Make the font with the given settings, e.g. those of a bold or italic
+/// member of the family.
+pub fn make_font_with(settings: FontParamSettings) -> FontContext {
+    let cx = InputContext { settings };
 
     let mut fcx = FontContext::new(cx.settings.clone());
     for &(ch, gl) in glyph::GLYPH_FNS {
@@ -34,6 +38,18 @@ pub fn make_font() -> FontContext {
     fcx
 }
 
+/// Make all members of the style-linked family, with their style names.
+pub fn make_family() -> [(&'static str, FontContext); 4] {
+    let regular = make_font_params();
+    [
+        ("Regular", regular.clone()),
+        ("Bold", regular.clone().bold()),
+        ("Italic", regular.clone().italic()),
+        ("Bold Italic", regular.bold().italic()),
+    ]
+    .map(|(style, settings)| (style, make_font_with(settings)))
+}
+
 pub const fn make_font_params() -> FontParamSettings {
     let width = 0.5;
     let cap_height = 0.7;
@@ -48,5 +64,7 @@ pub const fn make_font_params() -> FontParamSettings {
         side_bearing: 0.125 * width,
         overshoot: 1. / 40.,
         dot_size: 0.27 * width,
+        weight: 400,
+        slant: 0.,
     }
 }
@@ -44,8 +44,20 @@ pub struct FontParamSettings {
 
     /// Size of the dot in glyphs like 'i' and 'j'.
     pub dot_size: f64,
+
+    /// The `OS/2` weight class, e.g. 400 for regular and 700 for bold.
+    pub weight: u16,
+
+    /// Slant of the glyphs in degrees, positive values leaning to the right.
+    pub slant: f64,
 }
 
+/// Ratio between the stroke widths of the bold and the regular styles.
+const BOLD_STROKE_RATIO: f64 = 1.35;
+
+/// Slant of the italic styles in degrees.
+const ITALIC_SLANT: f64 = 10.;
+
 /// Snapshot of the original and derived font parameters used by glyph builders.
 #[derive(Clone, Copy, Debug)]
 pub struct FontParamSettingsView {
@@ -73,8 +85,34 @@ pub struct FontParamSettingsView {
 }
 
 impl FontParamSettings {
+    /// The settings of the bold style derived from these.
+    ///
+    /// Strokes are widened around their skeletons, so half of the extra width
+    /// eats into the counters on each side. The side bearings are narrowed by
+    /// the same amount to compensate, so that counters keep their sizes.
+    #[must_use]
+    pub const fn bold(self) -> Self {
+        let extra = (BOLD_STROKE_RATIO - 1.) * self.stroke_width;
+        Self {
+            stroke_width: self.stroke_width + extra,
+            side_bearing: self.side_bearing - extra / 2.,
+            dot_size: self.dot_size + extra,
+            weight: 700,
+            ..self
+        }
+    }
+
+    /// The settings of the italic style derived from these.
+    #[must_use]
+    pub const fn italic(self) -> Self {
+        Self {
+            slant: ITALIC_SLANT,
+            ..self
+        }
+    }
+
     #[must_use]
-    pub const fn view(&self) -> FontParamSettingsView {
+    pub fn view(&self) -> FontParamSettingsView {
         FontParamSettingsView {
             wth: self.wth(),
             xh: self.xh(),
@@ -148,8 +186,13 @@ impl FontParamSettings {
     }
 
     /// The horizontal overshoot.
-    pub const fn ovh(&self) -> f64 {
-        self.overshoot * (self.sbr() - self.sbl())
+    ///
+    /// Slanting keeps horizontal distances, which shrinks the distances across
+    /// slanted stems by the cosine of the slant. The overshoot is widened to
+    /// make up for it, so that round sides overshoot the stems as much as in
+    /// the upright style.
+    pub fn ovh(&self) -> f64 {
+        self.overshoot * (self.sbr() - self.sbl()) / self.slant.to_radians().cos()
     }
 
     /// The horizontal midline of a half-width character.
@@ -213,6 +256,14 @@ impl EvalSettings for FontParamSettings {
         self.width
     }
 
+    fn weight_class(&self) -> u16 {
+        self.weight
+    }
+
+    fn slant(&self) -> f64 {
+        self.slant
+    }
+
     fn cap_height(&self) -> f64 {
         self.cap_height
     }
@@ -224,4 +275,32 @@ impl EvalSettings for FontParamSettings {
     fn x_height(&self) -> f64 {
         self.x_height
     }
+
+    fn ascender(&self) -> f64 {
+        self.asc()
+    }
+
+    fn underline_position(&self) -> f64 {
+        self.dsc() / 2. + self.stw() / 2.
+    }
+
+    fn underline_thickness(&self) -> f64 {
+        self.stw()
+    }
+
+    fn strikeout_position(&self) -> f64 {
+        self.mih() + self.stw() / 2.
+    }
+
+    fn strikeout_size(&self) -> f64 {
+        self.stw()
+    }
+
+    fn stroke_width(&self) -> Option<f64> {
+        Some(self.stw())
+    }
+
+    fn overshoot(&self) -> f64 {
+        self.ovs()
+    }
 }
@@ -9,6 +9,7 @@ use dioxus_devtools::subsecond;
 use flate2::{Compression, write::GzEncoder};
 use futures_util::StreamExt;
 use monoxide_script::ast::FontContext;
+use monoxide_ttf::{hl::subset::subset, model::WriteOptions};
 use tokio::sync::watch;
 use tracing::{debug, info};
 
@@ -87,6 +88,11 @@ pub struct RenderCommand {
     #[clap(long, value_delimiter = ',', default_value = "gz", value_enum)]
     meta_compress: Vec<MetaCompressKind>,
 
+    /// Also write a subset of the font with only the characters of the given
+    /// text, e.g. for a specimen page.
+    #[clap(long)]
+    subset: Option<String>,
+
     /// The directory where generated files will be written.
     dir: PathBuf,
 }
@@ -104,7 +110,7 @@ impl RenderCommand {
             out_dir = std::env::current_dir()?.join(out_dir);
         };
 
-        let compiled = CompiledFont::new(make_font)?;
+        let (compiled, file) = CompiledFont::with_file(make_font)?;
         let ttf = compiled
             .ttf
             .map_err(|e| anyhow!("Font generation failed: {e}"))?;
@@ -117,6 +123,15 @@ impl RenderCommand {
 
         info!("Wrote {}", ttf_path.display());
 
+        // The file is present whenever the TTF above was generated.
+        if let (Some(text), Some(file)) = (&self.subset, file) {
+            let mut subset_ttf = vec![];
+            subset(file, text.chars())?.write(&WriteOptions::default(), &mut subset_ttf)?;
+            let subset_path = out_dir.join("monoxide.subset.ttf");
+            fs::write(&subset_path, &subset_ttf)?;
+            info!("Wrote {}", subset_path.display());
+        }
+
         if self.meta_compress.contains(&MetaCompressKind::None) {
             let metadata_path = out_dir.join("monoxide.ttf.meta");
             fs::write(&metadata_path, &metadata)?;
@@ -204,7 +204,7 @@ impl FontMetadata {
                 };
                 GlyphOverview {
                     id: i,
-                    name: None,
+                    name: glyph.name.clone(),
                     outline,
                     error,
                     advance: fcx.settings().mono_width(),
@@ -20,6 +20,7 @@ use monoxide_script::{
     ast::FontContext,
     eval::{AuxiliarySettings, eval, layout_glyphs},
 };
+use monoxide_ttf::model::{FontFile, WriteOptions};
 use tokio::sync::watch;
 use tower_http::services::{ServeDir, ServeFile};
 use tracing::info;
@@ -107,29 +108,35 @@ pub struct CompiledFont {
 
 impl CompiledFont {
     pub fn new(make_font: &mut impl FnMut() -> FontContext) -> Result<Self> {
+        let (compiled, _) = Self::with_file(make_font)?;
+        Ok(compiled)
+    }
+
+    /// Compile the font like [`Self::new`], and also return the evaluated font
+    /// file if it was generated, for further processing without evaluating the
+    /// font again.
+    pub fn with_file(
+        make_font: &mut impl FnMut() -> FontContext,
+    ) -> Result<(Self, Option<FontFile>)> {
         let fcx = make_font();
         let ser_fcx = layout_glyphs(&fcx)?;
         let metadata = FontMetadata::new(&fcx, ser_fcx);
 
-        let file = eval(
-            &fcx,
-            &AuxiliarySettings {
-                point_per_em: 2048,
-                font_name: "Monoxide".into(),
-            },
-        );
-        let ttf = file
-            .map(|f| {
+        let (ttf, file) = match eval(&fcx, &AuxiliarySettings::default()) {
+            Ok(f) => {
                 let mut out_ttf = BytesMut::new().writer();
-                f.write(&mut out_ttf).expect("Writing to memory can't fail");
-                out_ttf.into_inner().freeze()
-            })
-            .map_err(Into::into);
+                f.write(&WriteOptions::debug(), &mut out_ttf)
+                    .expect("Writing to memory can't fail");
+                (Ok(out_ttf.into_inner().freeze()), Some(f))
+            }
+            Err(e) => (Err(anyhow::Error::from(e)), None),
+        };
 
-        Ok(CompiledFont {
+        let compiled = CompiledFont {
             metadata: Box::new(metadata),
             ttf,
-        })
+        };
+        Ok((compiled, file))
     }
 }
 
@@ -57,7 +57,8 @@ pub(crate) fn serialized_glyph_to_detail(
     let advance = glyph.advance.unwrap_or(cx.settings().mono_width());
     match &glyph.kind {
         SerializedGlyphKind::Simple(simple_glyph) => {
-            Ok(simple_glyph_to_detail(id, cx, simple_glyph, advance))
+            let name = glyph.name.clone();
+            Ok(simple_glyph_to_detail(id, name, cx, simple_glyph, advance))
         }
         SerializedGlyphKind::Compound(_compound_glyph) => Err(GlyphDetailError::Unsupported {
             msg: "Compound glyphs are not supported yet".into(),
@@ -90,6 +91,7 @@ impl From<&GlyphDetailError> for Response {
 
 fn simple_glyph_to_detail(
     id: usize,
+    name: Option<String>,
     cx: &FontContext,
     outlines: &Vec<Arc<OutlineExpr>>,
     advance: f64,
@@ -112,7 +114,7 @@ fn simple_glyph_to_detail(
 
     let overview = GlyphOverview {
         id,
-        name: None,
+        name,
         outline: output_outline,
         error: None,
         advance: cx.settings().mono_width(),
@@ -148,6 +150,10 @@ fn make_guidelines(cx: &FontContext, advance: f64) -> Guidelines {
                 pos: settings.descender(),
                 label: Some("descender".to_string()),
             },
+            Guideline {
+                pos: settings.underline_position(),
+                label: Some("underline".to_string()),
+            },
         ],
         v: vec![
             Guideline {
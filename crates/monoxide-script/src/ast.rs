@@ -8,7 +8,7 @@ use crate::{
 mod compound;
 mod simple;
 
-pub use compound::GlyphComponent;
+pub use compound::{GlyphComponent, Overlap};
 pub use simple::OutlineExpr;
 
 #[derive(Debug)]
@@ -24,6 +24,9 @@ pub struct FontContext {
     /// The regular character mapping. This mapping is used when no other
     /// replacements override the characters.
     pub(crate) cmap: BTreeMap<char, Glyph>,
+    /// Glyphs for Unicode variation sequences, keyed by the base character
+    /// and the variation selector.
+    pub(crate) variations: BTreeMap<(char, char), Glyph>,
     pub(crate) settings: Box<dyn EvalSettings>,
 }
 
@@ -32,6 +35,7 @@ impl FontContext {
         Self {
             tofu: None,
             cmap: BTreeMap::new(),
+            variations: BTreeMap::new(),
             settings: Box::new(settings),
         }
     }
@@ -49,11 +53,36 @@ impl FontContext {
         self.cmap.insert(ch, glyph)
     }
 
+    /// Set the glyph of the variation sequence `ch` followed by `selector`.
+    /// Returns the previous glyph if it was set, otherwise `None`.
+    ///
+    /// If the glyph is the same as the one mapped to `ch`, the sequence is
+    /// recorded as using the default glyph, e.g. for standardized text
+    /// presentation sequences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `selector` is not a variation selector.
+    pub fn set_variation(&mut self, ch: char, selector: char, glyph: Glyph) -> Option<Glyph> {
+        assert!(
+            is_variation_selector(selector),
+            "U+{:04X} is not a variation selector",
+            selector as u32
+        );
+        self.variations.insert((ch, selector), glyph)
+    }
+
     pub fn settings(&self) -> &dyn EvalSettings {
         &*self.settings
     }
 }
 
+/// Whether the character is in the Variation Selectors (VS1–VS16) or the
+/// Variation Selectors Supplement (VS17–VS256) block.
+fn is_variation_selector(ch: char) -> bool {
+    matches!(ch, '\u{fe00}'..='\u{fe0f}' | '\u{e0100}'..='\u{e01ef}')
+}
+
 /// An opaque glyph type that cannot be modified once built.
 ///
 /// For building a [`Glyph`], see [`GlyphBuilder`], or [`Glyph::build()`].
@@ -83,10 +112,15 @@ impl Deref for Glyph {
     }
 }
 
+/// Derive a new glyph from an existing one. The name is not inherited, since
+/// glyph names must be unique within a font.
 impl From<Glyph> for GlyphBuilder {
     fn from(glyph: Glyph) -> Self {
         Self {
-            inner: (*glyph).clone(),
+            inner: GlyphInner {
+                name: None,
+                ..(*glyph).clone()
+            },
         }
     }
 }
@@ -104,6 +138,15 @@ pub struct GlyphInner {
     /// The advance width of the glyph. If unset, uses the default advance width
     /// of the font.
     pub advance: Option<f64>,
+
+    /// The PostScript name of the glyph. If unset, glyphs mapped from
+    /// characters are named after the lowest character mapped to them, and
+    /// other glyphs after their glyph ID.
+    pub name: Option<String>,
+
+    /// Whether to inline the components of this glyph as outlines, see
+    /// [`crate::eval::decompose_glyphs`].
+    pub decompose: bool,
 }
 
 /// The type to use for building a glyph.
@@ -149,6 +192,22 @@ impl GlyphBuilder {
         self
     }
 
+    /// Set the PostScript name of the glyph, which must be unique within the
+    /// font. Names are 1 to 63 characters from `A`–`Z`, `a`–`z`, `0`–`9`, `.`
+    /// and `_`; other names fail the evaluation of the font.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.inner.name = Some(name.into());
+        self
+    }
+
+    /// Inline the components of the glyph as transformed outlines instead of
+    /// referring to them, e.g. for a glyph whose components trip up some
+    /// renderers.
+    pub fn decompose(mut self) -> Self {
+        self.inner.decompose = true;
+        self
+    }
+
     pub fn build(self) -> Glyph {
         Glyph(Arc::new(self.inner))
     }
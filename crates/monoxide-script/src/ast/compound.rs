@@ -10,6 +10,27 @@ pub struct GlyphComponent {
     pub component: Glyph,
     /// The transformation applied to the component glyph.
     pub xform: Affine2D<Point2D>,
+    /// Whether the compound glyph takes its advance width and side bearings
+    /// from this component, even when rendered at small sizes where hinting
+    /// may change them. Only the first such component of a glyph is used.
+    pub use_my_metrics: bool,
+    /// Whether this component overlaps the other components of the glyph.
+    pub overlap: Overlap,
+}
+
+/// Whether a component overlaps the other components of its glyph. Renderers
+/// use this to avoid dropouts where the outlines overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overlap {
+    /// Overlapping if its bounding box intersects that of another component
+    /// not marked as [`Overlap::Disjoint`].
+    #[default]
+    Auto,
+    /// Always overlapping.
+    Overlapping,
+    /// Never overlapping, e.g. an accent inside the bounding box of its base
+    /// glyph without touching its outlines.
+    Disjoint,
 }
 
 impl GlyphComponent {
@@ -17,6 +38,8 @@ impl GlyphComponent {
         GlyphComponent {
             component: glyph,
             xform: Affine2D::id(),
+            use_my_metrics: false,
+            overlap: Overlap::Auto,
         }
     }
 
@@ -24,13 +47,20 @@ impl GlyphComponent {
         self.xform = xform;
         self
     }
+
+    pub fn with_my_metrics(mut self) -> Self {
+        self.use_my_metrics = true;
+        self
+    }
+
+    pub fn with_overlap(mut self, overlap: Overlap) -> Self {
+        self.overlap = overlap;
+        self
+    }
 }
 
 impl From<Glyph> for GlyphComponent {
     fn from(val: Glyph) -> Self {
-        GlyphComponent {
-            component: val,
-            xform: Affine2D::id(),
-        }
+        GlyphComponent::from_glyph(val)
     }
 }
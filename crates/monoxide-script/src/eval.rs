@@ -1,23 +1,34 @@
-use std::{collections::BTreeMap, sync::Arc, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use monoxide_curves::{point::Point2D, xform::Affine2D};
 use monoxide_ttf::{
-    hl,
-    model::{
-        FontFile, Outline, TrueTypeTables, cmap, fword, glyf, head, hhea, hmtx, name, os2, post,
-        ufword,
+    hl::{
+        self,
+        builder::{self, BuildError, FontBuilder, Metrics, MetricsReport, Style, WinMetrics},
+        name::FontNames,
     },
+    model::{Fixed, FontFile, fword, glyf, ufword},
 };
 use petgraph::prelude::DiGraphMap;
 
 use crate::{
-    ast::{FontContext, OutlineExpr},
+    WinMetricsPolicy,
+    ast::{FontContext, OutlineExpr, Overlap},
     eval::glyphs::FontEvalError,
 };
 
+mod decompose;
 mod glyphs;
+mod hint;
 mod layout;
 mod outline;
+#[cfg(test)]
+mod test;
+pub use decompose::decompose_glyphs;
 pub use layout::layout_glyphs;
 pub use outline::*; // fixme: use selective imports
 
@@ -25,12 +36,69 @@ pub struct AuxiliarySettings {
     /// Points per em when converting floating-point point data into
     /// fixed-point ones. A common value is 2048.
     pub point_per_em: u16,
-    pub font_name: String,
+    /// The family name shared by all styles of the font.
+    pub family_name: String,
+    /// The style within the family, such as `Regular` or `Bold Italic`.
+    /// The full, PostScript and typographic names are derived from the
+    /// family and style names, see [`FontNames`].
+    pub style_name: String,
+    /// The version number, such as `1.000`.
+    pub version: String,
+    pub copyright: Option<String>,
+    /// A description of the license.
+    pub license: Option<String>,
+    /// The name of the manufacturer.
+    pub vendor: Option<String>,
+    /// The 4-byte vendor ID of the manufacturer, as registered with
+    /// Microsoft. It's recorded in the `OS/2` table and the unique font
+    /// identifier. Defaults to `NONE` for unregistered vendors.
+    pub vendor_id: [u8; 4],
+    pub designer: Option<String>,
+    /// Whether to generate TrueType instructions for the glyphs. See
+    /// [`hint`] for how they are generated.
+    pub autohint: bool,
+    /// Pixel sizes to precompute the `hdmx` and `VDMX` device metrics at.
+    /// Leave empty to omit these tables.
+    pub device_ppems: Vec<u8>,
+    /// The creation and modification time recorded in the font. If unset,
+    /// `SOURCE_DATE_EPOCH` is used if present, otherwise the current time.
+    /// A `SOURCE_DATE_EPOCH` that isn't a number of seconds fails the
+    /// evaluation.
+    ///
+    /// Set this (or `SOURCE_DATE_EPOCH`) for reproducible builds.
+    pub timestamp: Option<SystemTime>,
+    /// Whether to decompose all compound glyphs into simple glyphs, for
+    /// renderers with poor support for components. Glyphs can also be
+    /// decomposed individually, see [`crate::ast::GlyphBuilder::decompose`].
+    pub decompose_components: bool,
+}
+
+impl Default for AuxiliarySettings {
+    fn default() -> Self {
+        Self {
+            point_per_em: 2048,
+            family_name: "Monoxide".into(),
+            style_name: "Regular".into(),
+            version: "0.100".into(),
+            copyright: None,
+            license: None,
+            vendor: Some("PangPang Studio".into()),
+            vendor_id: *b"NONE",
+            designer: None,
+            autohint: true,
+            device_ppems: (8..=48).collect(),
+            timestamp: None,
+            decompose_components: false,
+        }
+    }
 }
 
 pub struct SerializedComponent {
     pub index: usize,
     pub xform: Affine2D<Point2D>,
+    /// See [`crate::ast::GlyphComponent::use_my_metrics`].
+    pub use_my_metrics: bool,
+    pub overlap: Overlap,
 }
 
 pub enum SerializedGlyphKind {
@@ -46,6 +114,16 @@ pub enum SerializedGlyphKind {
 pub struct SerializedGlyph {
     pub kind: SerializedGlyphKind,
     pub advance: Option<f64>,
+    /// The PostScript name of the glyph. Every glyph is named after layout,
+    /// see [`crate::ast::GlyphInner::name`].
+    pub name: Option<String>,
+    /// Whether to inline the components of the glyph, see
+    /// [`decompose_glyphs`].
+    pub decompose: bool,
+    /// For a glyph decomposed by [`decompose_glyphs`], the number of outlines
+    /// inlined from each of its components, and the overlap setting of the
+    /// component. Empty for other glyphs.
+    pub inlined: Vec<(usize, Overlap)>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -55,6 +133,25 @@ pub enum HighEvalError {
 
     #[error("Failed to layout glyphs")]
     EvalError(#[from] FontEvalError),
+
+    #[error("Glyph name {0:?} is used by more than one glyph")]
+    DuplicateGlyphName(String),
+
+    #[error("Glyph name {0:?} is not a valid PostScript name")]
+    InvalidGlyphName(String),
+
+    #[error("Glyph of {ch:?} has advance {advance}, which breaks the monospace grid of {width}")]
+    OffGridAdvance {
+        ch: char,
+        advance: ufword,
+        width: ufword,
+    },
+
+    #[error("SOURCE_DATE_EPOCH {0:?} is not a number of seconds since the Unix epoch")]
+    InvalidSourceDateEpoch(String),
+
+    #[error("Failed to build the font: {0}")]
+    BuildError(#[from] BuildError),
 }
 
 /// A [`FontContext`] with only reachable glyphs and the layout determined.
@@ -63,19 +160,38 @@ pub enum HighEvalError {
 pub struct SerializedFontContext {
     pub glyph_list: Vec<SerializedGlyph>,
     pub cmap: BTreeMap<char, usize>,
+    /// Glyphs of Unicode variation sequences, keyed by the base character and
+    /// the variation selector.
+    pub variations: BTreeMap<(char, char), usize>,
 
     /// The reference relationship in the glyph list.
     pub glyph_map: DiGraphMap<usize, ()>,
 }
 
 pub fn eval(cx: &FontContext, aux: &AuxiliarySettings) -> Result<FontFile, HighEvalError> {
-    let scx = layout_glyphs(cx)?;
+    eval_with_report(cx, aux).map(|(font, _)| font)
+}
+
+/// Evaluate the font like [`eval`], and also report the glyphs that exceed
+/// the typographic box.
+pub fn eval_with_report(
+    cx: &FontContext,
+    aux: &AuxiliarySettings,
+) -> Result<(FontFile, MetricsReport), HighEvalError> {
+    let mut scx = layout_glyphs(cx)?;
+    // Components whose transforms can't be written are decomposed too.
+    let shear = glyphs::Shear::from_settings(cx.settings.as_ref());
+    decompose_glyphs(&mut scx, |g| {
+        aux.decompose_components || g.decompose || !glyphs::components_fit(shear, g)
+    });
     if scx.glyph_list.len() == 1 {
         panic!("Windows font reader disallow single-glyph fonts")
     }
-    let glyphs = glyphs::eval_glyphs(aux, &scx)?;
-    let res = create_tables(cx, &scx, aux, glyphs);
-    Ok(res)
+    let mut glyphs = glyphs::eval_glyphs(cx.settings.as_ref(), aux, &scx)?;
+    let hinting = aux
+        .autohint
+        .then(|| hint::autohint(cx.settings.as_ref(), aux, &mut glyphs));
+    create_tables(cx, &scx, aux, glyphs, hinting)
 }
 
 fn create_tables(
@@ -83,177 +199,150 @@ fn create_tables(
     scx: &SerializedFontContext,
     aux: &AuxiliarySettings,
     glyphs: Vec<glyf::Glyph>,
-) -> FontFile {
-    let glyf = glyf::Table { glyphs };
-    let loca = hl::loca::glyf_to_loca(&glyf);
-    let maxp = hl::maxp::glyf_to_maxp(&glyf);
-    let (x_min, y_min, x_max, y_max) = xy_minmax(&glyf);
+    hinting: Option<hint::HintTables>,
+) -> Result<(FontFile, MetricsReport), HighEvalError> {
+    let upem = aux.point_per_em as f64;
+    let to_units = |v: f64| (upem * v) as fword;
 
-    let mappings = scx
-        .cmap
+    let default_advance = cx.settings.mono_width();
+    let advances = scx
+        .glyph_list
         .iter()
-        .map(|(&k, &v)| hl::cmap::SeqMapping {
-            start_code: k as u32,
-            len: 1,
-            glyph_id: v as u32,
-        })
+        .map(|g| (upem * g.advance.unwrap_or(default_advance)) as ufword)
         .collect::<Vec<_>>();
-    let cmap = hl::cmap::Table {
-        subtables: vec![mappings],
-        mapping: vec![(hl::cmap::Encoding::Unicode, 0)],
-    };
-    let cmap = cmap::Table::from_raw(cmap);
+    if !cx.settings.proportional() {
+        check_monospace_grid(scx, &advances, (upem * default_advance) as ufword)?;
+    }
 
-    let outline = Outline::TrueType(TrueTypeTables { glyf, loca, maxp });
+    // Sequences resolving to the glyph of their base character use the
+    // default glyph.
+    let variations =
+        scx.variations
+            .iter()
+            .map(|(&(ch, selector), &v)| hl::cmap::VariationMapping {
+                code: ch as u32,
+                selector: selector as u32,
+                glyph_id: (scx.cmap.get(&ch) != Some(&v)).then_some(v as u32),
+            });
 
-    // hmtx for monospaced font
-    let width_in_font_units = aux.point_per_em as f64 * cx.settings.mono_width();
-    let hmtx = hmtx::Table {
-        metrics: vec![
-            hmtx::LongHorizontalMetric {
-                advance_width: width_in_font_units as ufword,
-                left_side_bearing: 0,
-            };
-            scx.glyph_list.len()
-        ],
-        left_side_bearings: vec![],
+    let settings = cx.settings.as_ref();
+    let script = |m: crate::ScriptMetrics| builder::ScriptMetrics {
+        x_size: to_units(m.x_size),
+        y_size: to_units(m.y_size),
+        x_offset: to_units(m.x_offset),
+        y_offset: to_units(m.y_offset),
     };
-
-    // Calculate other tables
-    let head = head::Table {
-        font_revision: 0,
-        checksum_adjustment: 0,
-        flags: head::HeaderFlags::empty(),
+    let ascender = to_units(settings.ascender());
+    let descender = to_units(settings.descender());
+    let to_ufword = |v: fword| v.max(0) as ufword;
+    let win_metrics = match settings.win_metrics() {
+        WinMetricsPolicy::GlyphBounds => WinMetrics::GlyphBounds,
+        WinMetricsPolicy::Typographic => WinMetrics::Fixed {
+            ascent: to_ufword(ascender),
+            descent: to_ufword(-descender),
+        },
+        WinMetricsPolicy::Fixed { ascent, descent } => WinMetrics::Fixed {
+            ascent: to_ufword(to_units(ascent)),
+            descent: to_ufword(to_units(descent)),
+        },
+    };
+    let metrics = Metrics {
         units_per_em: aux.point_per_em,
-        created: SystemTime::now(),
-        modified: SystemTime::now(),
-        x_min,
-        y_min,
-        x_max,
-        y_max,
-        mac_style: head::MacStyle::REGULAR,
         lowest_rec_ppem: 72,
+        ascender,
+        descender,
+        line_gap: to_units(settings.line_gap()),
+        win_metrics,
+        use_typo_metrics: settings.use_typo_metrics(),
+        x_height: to_units(settings.x_height()),
+        cap_height: to_units(settings.cap_height()),
+        underline_position: to_units(settings.underline_position()),
+        underline_thickness: to_units(settings.underline_thickness()),
+        strikeout_position: to_units(settings.strikeout_position()),
+        strikeout_size: to_units(settings.strikeout_size()),
+        subscript: script(settings.subscript()),
+        superscript: script(settings.superscript()),
     };
-
-    let hhea = hhea::Table {
-        ascender: (aux.point_per_em as f64 * cx.settings.cap_height()) as fword,
-        descender: (aux.point_per_em as f64 * cx.settings.descender()) as fword,
-        line_gap: 0,
-        advance_width_max: width_in_font_units as ufword,
-        min_left_side_bearing: 0,
-        min_right_side_bearing: 0,
-        x_max_extent: (width_in_font_units * 2.) as fword,
-        caret_slope_rise: 1,
-        caret_slope_run: 0,
-        caret_offset: 0,
-        metric_data_format: 0,
-        number_of_hmetrics: hmtx.metrics.len() as u16,
-    };
-
-    let name = name::Table {
-        records: [(
-            name::Lang::Microsoft(name::MSLangID::en_us),
-            name::NameRecords {
-                font_family_name: Some(aux.font_name.clone()),
-                ..Default::default()
-            },
-        )]
-        .into_iter()
-        .collect(),
+    // The italic angle is counter-clockwise, opposite to the slant. The caret
+    // is moved along with the sheared glyphs.
+    let shear = glyphs::Shear::from_settings(settings);
+    let style = Style {
+        weight_class: settings.weight_class(),
+        italic_angle: Fixed::from_num(-settings.slant()),
+        caret_offset: to_units(shear.baseline_offset()),
+        vendor_id: aux.vendor_id,
+        ..Default::default()
     };
 
-    let subscript_sz = (aux.point_per_em as f64 * cx.settings.x_height()) as fword;
-    let subscript_offset = (aux.point_per_em as f64 * cx.settings.x_height() / 4.) as fword;
-    let strikeout_sz = (0.05 * aux.point_per_em as f64) as fword;
-    let strikeout_pos = (aux.point_per_em as f64 * cx.settings.x_height() / 2.) as fword;
-    let ascender = (aux.point_per_em as f64 * cx.settings.cap_height()) as fword;
-    let descender = (aux.point_per_em as f64 * cx.settings.descender()) as fword;
-    let x_height = (aux.point_per_em as f64 * cx.settings.x_height()) as fword;
-    let os2 = os2::Table {
-        x_avg_char_width: width_in_font_units as fword,
-        us_weight_class: 400, // TODO: set weight
-        us_width_class: 5,
-        usage_permission: os2::UsagePermissionKind::EditableEmbedding,
-        fs_type: os2::FsTypeUpper::Nothing,
-        y_subscript_x_size: subscript_sz,
-        y_subscript_y_size: subscript_sz,
-        y_subscript_x_offset: 0,
-        y_subscript_y_offset: subscript_offset,
-        y_superscript_x_size: subscript_sz,
-        y_superscript_y_size: subscript_sz,
-        y_superscript_x_offset: 0,
-        y_superscript_y_offset: aux.point_per_em as fword - subscript_offset,
-        y_strikeout_size: strikeout_sz,
-        y_strikeout_position: strikeout_pos,
-        s_family_class: os2::SFamilyClass::NoClassification,
-        panose_classification: os2::PanroseClassification {
-            family_type: 2,
-            serif_style: 0,
-            weight: 5,
-            proportion: 3,
-            contrast: 0,
-            stroke_variation: 0,
-            arm_style: 0,
-            letterform: 0,
-            midline: 0,
-            x_height: 0,
-        },
-        unicode_range: os2::UnicodeRange::BasicLatin,
-        ach_vend_id: *b"TEST",
-        fs_selection: os2::FsSelectionKind::Regular,
-        first_char_index: 0,
-        last_char_index: 0xffff,
-        s_typo_ascender: ascender,
-        s_typo_descender: descender,
-        s_typo_line_gap: 0,
-        us_win_ascent: ascender as u16,
-        us_win_descent: (-descender) as u16,
-        code_page_range: os2::CodePageRange::Latin1,
-        sx_height: x_height,
-        s_cap_height: ascender,
-        us_default_char: 65,
-        us_break_char: 65,
-        us_max_context: 0,
+    let names = FontNames {
+        family: aux.family_name.clone(),
+        style: aux.style_name.clone(),
+        version: aux.version.clone(),
+        copyright: aux.copyright.clone(),
+        license: aux.license.clone(),
+        vendor: aux.vendor.clone(),
+        designer: aux.designer.clone(),
     };
 
-    let post = post::TableV3 {
-        italic_angle: monoxide_ttf::model::Fixed::from_num(0),
-        underline_position: 0,
-        underline_thickness: 0,
-        is_fixed_pitch: false,
-        min_mem_type42: 0,
-        max_mem_type42: 0,
-        min_mem_type1: 0,
-        max_mem_type1: 0,
-    };
+    let mut builder = FontBuilder::new(metrics)
+        .style(style)
+        .glyphs(glyphs.into_iter().zip(advances.iter().copied()))
+        .cmap(scx.cmap.iter().map(|(&ch, &v)| (ch, v as u32)))
+        .variations(variations)
+        .names(names)
+        .device_ppems(aux.device_ppems.clone())
+        .timestamp(build_timestamp(aux)?)
+        .glyph_names(post_glyph_names(&scx.glyph_list));
+    if let Some(h) = hinting {
+        builder = builder.prep(h.prep).cvt(h.cvt).gasp(h.gasp);
+    }
+    Ok(builder.build_with_report()?)
+}
 
-    FontFile {
-        head,
-        hhea,
-        hmtx,
-        cmap,
-        name,
-        os2,
-        post,
-        outline,
-        dsig: Some(Default::default()),
+/// Check that every glyph mapped from a character is either zero-width or
+/// exactly one monospace cell wide.
+fn check_monospace_grid(
+    scx: &SerializedFontContext,
+    advances: &[ufword],
+    width: ufword,
+) -> Result<(), HighEvalError> {
+    for (&ch, &ix) in &scx.cmap {
+        let advance = advances[ix];
+        if advance != 0 && advance != width {
+            return Err(HighEvalError::OffGridAdvance { ch, advance, width });
+        }
     }
+    Ok(())
 }
 
-/// Calculate `(xmin, ymin, xmax, ymax)` from glyf table
-fn xy_minmax(glyf: &glyf::Table) -> (fword, fword, fword, fword) {
-    let mut xmin = 0;
-    let mut ymin = 0;
-    let mut xmax = 0;
-    let mut ymax = 0;
+/// The timestamp to record in the font, see [`AuxiliarySettings::timestamp`].
+fn build_timestamp(aux: &AuxiliarySettings) -> Result<SystemTime, HighEvalError> {
+    let epoch = std::env::var("SOURCE_DATE_EPOCH").ok();
+    timestamp_from(aux, epoch.as_deref())
+}
 
-    for it in &glyf.glyphs {
-        let common = it.common();
-        xmin = xmin.min(common.x_min);
-        ymin = ymin.min(common.y_min);
-        xmax = xmax.max(common.x_max);
-        ymax = ymax.max(common.y_max);
+/// The timestamp to record in the font, given the value of
+/// `SOURCE_DATE_EPOCH` if set. A malformed value is an error rather than
+/// silently falling back to the current time, which would make the build
+/// irreproducible.
+fn timestamp_from(
+    aux: &AuxiliarySettings,
+    epoch: Option<&str>,
+) -> Result<SystemTime, HighEvalError> {
+    if let Some(timestamp) = aux.timestamp {
+        return Ok(timestamp);
     }
+    let Some(epoch) = epoch else {
+        return Ok(SystemTime::now());
+    };
+    let secs = (epoch.trim().parse::<u64>())
+        .map_err(|_| HighEvalError::InvalidSourceDateEpoch(epoch.to_owned()))?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
 
-    (xmin, ymin, xmax, ymax)
+/// Glyph names for the `post` table, in glyph ID order.
+fn post_glyph_names(glyph_list: &[SerializedGlyph]) -> Vec<String> {
+    (glyph_list.iter())
+        .map(|g| g.name.clone().expect("Glyphs should be named in layout"))
+        .collect()
 }
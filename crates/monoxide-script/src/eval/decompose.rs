@@ -0,0 +1,144 @@
+//! Decompose compound glyphs into simple glyphs.
+//!
+//! Some outputs can't use TrueType components: CFF2 outlines, subsets for
+//! renderers with broken support for them, and components whose transforms
+//! don't fit in F2Dot14. Decomposing a glyph inlines the outlines of its
+//! components, transformed, into the glyph itself.
+//!
+//! The overlap settings of the components are kept, so that the decomposed
+//! glyph is marked with `OVERLAP_SIMPLE` where the compound glyph would have
+//! been marked with `OVERLAP_COMPOUND`. Only the components of the decomposed
+//! glyph itself are considered: overlaps within nested compound components
+//! are not recorded.
+
+use std::sync::Arc;
+
+use monoxide_curves::point::Point2D;
+use petgraph::prelude::DiGraphMap;
+
+use crate::{
+    ast::{OutlineExpr, Overlap},
+    eval::{SerializedComponent, SerializedFontContext, SerializedGlyph, SerializedGlyphKind},
+};
+
+/// Decompose the compound glyphs selected by `filter` into simple glyphs.
+///
+/// Glyphs that were only used as components of decomposed glyphs are removed,
+/// and the remaining glyphs are renumbered in their original order. The
+/// `cmap`, the variation sequences and the `glyph_map` graph are updated to
+/// match.
+pub fn decompose_glyphs(
+    scx: &mut SerializedFontContext,
+    filter: impl Fn(&SerializedGlyph) -> bool,
+) {
+    let selected = (scx.glyph_list.iter().enumerate())
+        .filter(|(_, g)| matches!(g.kind, SerializedGlyphKind::Compound(_)) && filter(g))
+        .map(|(ix, _)| ix)
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        return;
+    }
+
+    let inlined = (selected.iter())
+        .map(|&ix| inline_components(&scx.glyph_list, ix))
+        .collect::<Vec<_>>();
+    for (ix, components) in selected.into_iter().zip(inlined) {
+        let glyph = &mut scx.glyph_list[ix];
+        glyph.inlined = (components.iter())
+            .map(|(outlines, overlap)| (outlines.len(), *overlap))
+            .collect();
+        let outlines = components.into_iter().flat_map(|(outlines, _)| outlines);
+        glyph.kind = SerializedGlyphKind::Simple(outlines.collect());
+    }
+    prune(scx);
+}
+
+/// The outlines inlined from each component of a compound glyph, with the
+/// overlap setting of the component.
+fn inline_components(
+    glyphs: &[SerializedGlyph],
+    ix: usize,
+) -> Vec<(Vec<Arc<OutlineExpr>>, Overlap)> {
+    let SerializedGlyphKind::Compound(components) = &glyphs[ix].kind else {
+        unreachable!("Only compound glyphs are decomposed");
+    };
+    (components.iter())
+        .map(|c| (inline_component(glyphs, c), c.overlap))
+        .collect()
+}
+
+/// The outlines of a component with all of its own components inlined.
+fn inline_component(glyphs: &[SerializedGlyph], c: &SerializedComponent) -> Vec<Arc<OutlineExpr>> {
+    let moved = !c.xform.scale_is_identity() || c.xform.translation() != Point2D::new(0., 0.);
+    let outlines = inline_outlines(glyphs, c.index).into_iter();
+    outlines
+        .map(|o| if moved { o.transformed(c.xform) } else { o })
+        .collect()
+}
+
+/// The outlines of a glyph with all of its components inlined.
+fn inline_outlines(glyphs: &[SerializedGlyph], ix: usize) -> Vec<Arc<OutlineExpr>> {
+    match &glyphs[ix].kind {
+        SerializedGlyphKind::Simple(outlines) => outlines.clone(),
+        SerializedGlyphKind::Compound(components) => components
+            .iter()
+            .flat_map(|c| inline_component(glyphs, c))
+            .collect(),
+    }
+}
+
+/// Remove the glyphs no longer reachable from `.notdef`, the `cmap` or the
+/// variation sequences, and rebuild the `glyph_map` graph.
+fn prune(scx: &mut SerializedFontContext) {
+    let mut reachable = vec![false; scx.glyph_list.len()];
+    let mut pending = [0]
+        .into_iter()
+        .chain(scx.cmap.values().copied())
+        .chain(scx.variations.values().copied())
+        .collect::<Vec<_>>();
+    while let Some(ix) = pending.pop() {
+        if std::mem::replace(&mut reachable[ix], true) {
+            continue;
+        }
+        if let SerializedGlyphKind::Compound(components) = &scx.glyph_list[ix].kind {
+            pending.extend(components.iter().map(|c| c.index));
+        }
+    }
+
+    let mut new_ids = vec![None; reachable.len()];
+    for (new, old) in (reachable.iter().enumerate())
+        .filter(|&(_, &r)| r)
+        .map(|(old, _)| old)
+        .enumerate()
+    {
+        new_ids[old] = Some(new);
+    }
+    let new_id = |ix: usize| new_ids[ix].expect("Reachable glyphs should be kept");
+
+    let glyph_list = std::mem::take(&mut scx.glyph_list);
+    scx.glyph_list = (glyph_list.into_iter().zip(&reachable))
+        .filter(|&(_, &r)| r)
+        .map(|(g, _)| g)
+        .collect();
+    for glyph in &mut scx.glyph_list {
+        if let SerializedGlyphKind::Compound(components) = &mut glyph.kind {
+            for c in components {
+                c.index = new_id(c.index);
+            }
+        }
+    }
+    for ix in scx.cmap.values_mut().chain(scx.variations.values_mut()) {
+        *ix = new_id(*ix);
+    }
+
+    let mut glyph_map = DiGraphMap::new();
+    for (ix, glyph) in scx.glyph_list.iter().enumerate() {
+        glyph_map.add_node(ix);
+        if let SerializedGlyphKind::Compound(components) = &glyph.kind {
+            for c in components {
+                glyph_map.add_edge(ix, c.index, ());
+            }
+        }
+    }
+    scx.glyph_map = glyph_map;
+}
@@ -2,21 +2,25 @@
 
 use std::sync::Arc;
 
-use monoxide_curves::{point::Point2D, xform::Affine2D};
+use monoxide_curves::{point::Point2D, quad::QuadBezier, xform::Affine2D};
 use monoxide_ttf::{
     hl::{self, glyf::ConvertError},
     model::{
         f2dot14, fword,
-        glyf::{self, GlyphCommon, compound::Scale},
+        glyf::{
+            self, GlyphCommon,
+            compound::{ComponentFlags, Scale},
+        },
     },
 };
 use petgraph::visit::DfsPostOrder;
 
 use crate::{
-    ast::OutlineExpr,
+    EvalSettings,
+    ast::{OutlineExpr, Overlap},
     eval::{
-        AuxiliarySettings, EvalError, SerializedComponent, SerializedFontContext,
-        SerializedGlyphKind, eval_outline,
+        AuxiliarySettings, EvalError, SerializedComponent, SerializedFontContext, SerializedGlyph,
+        SerializedGlyphKind, eval_outline_sheared,
     },
     trace::NoId,
 };
@@ -31,9 +35,11 @@ pub enum FontEvalError {
 }
 
 pub fn eval_glyphs(
+    settings: &dyn EvalSettings,
     aux: &AuxiliarySettings,
     scx: &SerializedFontContext,
 ) -> Result<Vec<glyf::Glyph>, FontEvalError> {
+    let shear = Shear::from_settings(settings);
     // Evaluate in post-order, so we calculate the dimensions of compound glyphs
     // after all of its components.
     let mut glyphs = Vec::new();
@@ -47,11 +53,11 @@ pub fn eval_glyphs(
         let glyph = &scx.glyph_list[ix];
         match &glyph.kind {
             SerializedGlyphKind::Simple(outlines) => {
-                let simple_glyph = eval_simple_glyph(aux, outlines, ix)?;
+                let simple_glyph = eval_simple_glyph(aux, shear, outlines, &glyph.inlined, ix)?;
                 glyphs[ix] = Some(glyf::Glyph::Simple(simple_glyph));
             }
             SerializedGlyphKind::Compound(comps) => {
-                let glyph = eval_compound_glyph(aux, &glyphs, comps);
+                let glyph = eval_compound_glyph(aux, shear, &glyphs, comps);
                 glyphs[ix] = Some(glyf::Glyph::Compound(glyph));
             }
         }
@@ -65,19 +71,94 @@ pub fn eval_glyphs(
     Ok(res)
 }
 
+/// The horizontal shear of slanted fonts.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Shear {
+    /// The offset in `x` per unit of `y`.
+    pub factor: f64,
+    /// The height that keeps its horizontal position, see
+    /// [`EvalSettings::slant_pivot`].
+    pub pivot: f64,
+}
+
+impl Shear {
+    pub fn from_settings(settings: &dyn EvalSettings) -> Self {
+        Self {
+            factor: settings.slant().to_radians().tan(),
+            pivot: settings.slant_pivot(),
+        }
+    }
+
+    /// The horizontal offset of the baseline after shearing.
+    pub fn baseline_offset(&self) -> f64 {
+        -self.factor * self.pivot
+    }
+
+    /// Rewrite the transform of a component referring to a sheared glyph, so
+    /// that the compound glyph is sheared too. That is,
+    /// `shear · xform · shear⁻¹`.
+    fn component(&self, xform: &Affine2D<Point2D>) -> Affine2D<Point2D> {
+        let k = self.factor;
+        // Rows of the linear part, see `Affine2D::apply`
+        let [r0, r1] = xform.matrix();
+        let top = Point2D::new(r0.x + k * r1.x, r0.y + k * r1.y);
+        let mat = [
+            Point2D::new(top.x, top.y - k * top.x),
+            Point2D::new(r1.x, r1.y - k * r1.x),
+        ];
+        // The sheared translation, plus the baseline offset that the
+        // component glyph has moved by, undone and redone.
+        let t = xform.translation();
+        let offset = self.baseline_offset();
+        let trans = Point2D::new(
+            t.x + k * t.y + offset - mat[0].x * offset,
+            t.y - mat[1].x * offset,
+        );
+        Affine2D::make(trans, mat)
+    }
+}
+
+/// Whether the transforms of all components of a glyph can be written in a
+/// compound glyph, i.e. fit in F2Dot14 once sheared.
+pub(super) fn components_fit(shear: Shear, glyph: &SerializedGlyph) -> bool {
+    let SerializedGlyphKind::Compound(comps) = &glyph.kind else {
+        return true;
+    };
+    comps.iter().all(|c| {
+        let [r0, r1] = shear.component(&c.xform).matrix();
+        [r0.x, r0.y, r1.x, r1.y]
+            .into_iter()
+            .all(|v| f2dot14::checked_from_num(v).is_some())
+    })
+}
+
 fn eval_simple_glyph(
     aux: &AuxiliarySettings,
+    shear: Shear,
     outlines: &[Arc<OutlineExpr>],
+    inlined: &[(usize, Overlap)],
     glyph_index: usize,
 ) -> Result<glyf::simple::SimpleGlyph, FontEvalError> {
     let mut res_outlines = vec![];
+    // The contours evaluated from outline `i` are at
+    // `outline_ends[i]..outline_ends[i + 1]`.
+    let mut outline_ends = vec![0];
     for it in outlines {
-        eval_outline(it, &mut res_outlines, &mut ())
+        eval_outline_sheared(it, shear.factor, &mut res_outlines, &mut ())
             .map_err(|e| FontEvalError::EvalError(e, glyph_index))?;
+        outline_ends.push(res_outlines.len());
     }
 
+    let offset = shear.baseline_offset();
     let quads = res_outlines
         .into_iter()
+        .map(|x| {
+            if offset == 0. {
+                x
+            } else {
+                x.xform(Affine2D::translated((offset, 0.)))
+            }
+        })
         .map(|x| monoxide_curves::convert::cube_to_quad(x, 0.00001))
         .map(|x| x.cast(|x| x * (aux.point_per_em as f64)))
         .map(|x| {
@@ -89,23 +170,52 @@ fn eval_simple_glyph(
         })
         .collect::<Vec<_>>();
 
-    hl::glyf::encode(&quads).map_err(|e| FontEvalError::GlyfEncodeError(e, glyph_index))
+    let mut glyph =
+        hl::glyf::encode(&quads).map_err(|e| FontEvalError::GlyfEncodeError(e, glyph_index))?;
+
+    // Decomposed glyphs keep the overlap settings of their components, see
+    // `decompose_glyphs`.
+    let mut bbs = vec![];
+    let mut first = 0;
+    for &(n_outlines, _) in inlined {
+        let contours = &quads[outline_ends[first]..outline_ends[first + n_outlines]];
+        bbs.push(quads_bb(contours));
+        first += n_outlines;
+    }
+    let overlaps = inlined
+        .iter()
+        .map(|&(_, overlap)| overlap)
+        .collect::<Vec<_>>();
+    if components_overlap(&overlaps, &bbs) {
+        glyph.set_overlap_simple();
+    }
+    Ok(glyph)
 }
 
 fn eval_compound_glyph(
     aux: &AuxiliarySettings,
+    shear: Shear,
     glyphs: &[Option<glyf::Glyph>],
     comps: &[SerializedComponent],
 ) -> glyf::compound::CompoundGlyph {
-    let bb = comps
+    let xforms = comps
         .iter()
-        .map(|comp| {
+        .map(|c| shear.component(&c.xform))
+        .collect::<Vec<_>>();
+    let bbs = comps
+        .iter()
+        .zip(&xforms)
+        .map(|(comp, xform)| {
             let bb = glyphs[comp.index]
                 .as_ref()
                 .expect("Children glyphs should be evaluated before compound glyph evaluate")
                 .common();
-            affine_bb(aux, &comp.xform, bb)
+            affine_bb(aux, xform, bb)
         })
+        .collect::<Vec<_>>();
+    let bb = bbs
+        .iter()
+        .cloned()
         .reduce(|bb1, bb2| GlyphCommon {
             x_min: bb1.x_min.min(bb2.x_min),
             y_min: bb1.y_min.min(bb2.y_min),
@@ -113,41 +223,61 @@ fn eval_compound_glyph(
             y_max: bb1.y_max.max(bb2.y_max),
         })
         .expect("Compound glyph should have at lease one component");
+    let overlaps = comps.iter().map(|c| c.overlap).collect::<Vec<_>>();
+    let overlapping = components_overlap(&overlaps, &bbs);
+    let metrics_component = comps.iter().position(|c| c.use_my_metrics);
 
-    let components = comps
-        .iter()
-        .map(|c| {
-            let trans = c.xform.translation();
-            let args = glyf::compound::Args::Offset {
-                x: (trans.x * aux.point_per_em as f64) as fword,
-                y: (trans.y * aux.point_per_em as f64) as fword,
-            };
-            let scale = if c.xform.scale_is_identity() {
-                Scale::One
-            } else if let Some(scale) = c.xform.scale_is_uniform() {
-                Scale::Simple(f2dot14::from_num(scale))
-            } else if let Some((x, y)) = c.xform.mat_is_only_scale() {
-                Scale::XY {
-                    x: f2dot14::from_num(x),
-                    y: f2dot14::from_num(y),
-                }
-            } else {
-                let mat = c.xform.matrix(); // column major
-                Scale::TwoByTwo {
-                    xx: f2dot14::from_num(mat[0].x),
-                    yx: f2dot14::from_num(mat[1].x),
-                    xy: f2dot14::from_num(mat[0].y),
-                    yy: f2dot14::from_num(mat[1].y),
-                }
-            };
-            glyf::compound::Component {
-                flags: glyf::compound::ComponentFlags::empty(),
-                glyph_index: c.index as u16,
-                args,
-                scale,
+    // F2Dot14 covers [-2, 2). Glyphs with larger transforms are decomposed
+    // beforehand, see `components_fit`.
+    let to_f2dot14 =
+        |v: f64| f2dot14::checked_from_num(v).expect("Component transforms should fit in F2Dot14");
+    let mut components = vec![];
+    for (ix, (c, xform)) in comps.iter().zip(&xforms).enumerate() {
+        let trans = xform.translation();
+        let args = glyf::compound::Args::Offset {
+            x: (trans.x * aux.point_per_em as f64).round() as fword,
+            y: (trans.y * aux.point_per_em as f64).round() as fword,
+        };
+        let scale = if xform.scale_is_identity() {
+            Scale::One
+        } else if let Some(scale) = xform.scale_is_uniform() {
+            Scale::Simple(to_f2dot14(scale))
+        } else if let Some((x, y)) = xform.mat_is_only_scale() {
+            Scale::XY {
+                x: to_f2dot14(x),
+                y: to_f2dot14(y),
             }
-        })
-        .collect();
+        } else {
+            // Rows of the linear part, see `Affine2D::apply`. TrueType maps
+            // (x, y) to (xx·x + yx·y, xy·x + yy·y).
+            let [r0, r1] = xform.matrix();
+            Scale::TwoByTwo {
+                xx: to_f2dot14(r0.x),
+                yx: to_f2dot14(r0.y),
+                xy: to_f2dot14(r1.x),
+                yy: to_f2dot14(r1.y),
+            }
+        };
+
+        let mut flags = ComponentFlags::ROUND_XY_TO_GRID;
+        // The offsets are in the coordinates of the compound glyph. State it,
+        // since rasterizers disagree on the default for scaled components.
+        if !matches!(scale, Scale::One) {
+            flags |= ComponentFlags::UNSCALED_COMPONENT_OFFSET;
+        }
+        if metrics_component == Some(ix) {
+            flags |= ComponentFlags::USE_MY_METRICS;
+        }
+        if ix == 0 && overlapping {
+            flags |= ComponentFlags::OVERLAP_COMPOUND;
+        }
+        components.push(glyf::compound::Component {
+            flags,
+            glyph_index: c.index as u16,
+            args,
+            scale,
+        });
+    }
 
     glyf::compound::CompoundGlyph {
         common: bb,
@@ -156,12 +286,32 @@ fn eval_compound_glyph(
     }
 }
 
+/// Whether any components overlap each other, see [`Overlap`]. `bbs` are the
+/// bounding boxes of the components.
+fn components_overlap(overlaps: &[Overlap], bbs: &[GlyphCommon]) -> bool {
+    if overlaps.contains(&Overlap::Overlapping) {
+        return true;
+    }
+    let auto = overlaps
+        .iter()
+        .zip(bbs)
+        .filter(|&(&overlap, _)| overlap == Overlap::Auto)
+        .map(|(_, bb)| bb)
+        .collect::<Vec<_>>();
+    let intersects = |a: &GlyphCommon, b: &GlyphCommon| {
+        a.x_min < b.x_max && b.x_min < a.x_max && a.y_min < b.y_max && b.y_min < a.y_max
+    };
+    (auto.iter().enumerate()).any(|(ix, a)| auto[ix + 1..].iter().any(|b| intersects(a, b)))
+}
+
 /// Affine transform a bounding box
 fn affine_bb(aux: &AuxiliarySettings, aff: &Affine2D<Point2D>, bb: &GlyphCommon) -> GlyphCommon {
     // One day we will have a bounding box on data before transform...
     // But now let's hack this through by translating the points back to f64 and
     // calculate the result bounding box
-    let aff = Affine2D::make(aff.translation() * aux.point_per_em as f64, aff.matrix());
+    // The offsets are rounded, see `eval_compound_glyph`.
+    let trans = aff.translation() * aux.point_per_em as f64;
+    let aff = Affine2D::make(Point2D::new(trans.x.round(), trans.y.round()), aff.matrix());
     let points = [
         cvt_pt(bb.x_min, bb.y_min),
         cvt_pt(bb.x_max, bb.y_min),
@@ -170,10 +320,26 @@ fn affine_bb(aux: &AuxiliarySettings, aff: &Affine2D<Point2D>, bb: &GlyphCommon)
     ];
     let xformed_points = points.map(|pt| aff.apply(&pt));
 
-    let x_max = xformed_points.iter().map(|pt| pt.x as fword).max().unwrap();
-    let x_min = xformed_points.iter().map(|pt| pt.x as fword).min().unwrap();
-    let y_max = xformed_points.iter().map(|pt| pt.y as fword).max().unwrap();
-    let y_min = xformed_points.iter().map(|pt| pt.y as fword).min().unwrap();
+    let x_max = xformed_points
+        .iter()
+        .map(|pt| pt.x.round() as fword)
+        .max()
+        .unwrap();
+    let x_min = xformed_points
+        .iter()
+        .map(|pt| pt.x.round() as fword)
+        .min()
+        .unwrap();
+    let y_max = xformed_points
+        .iter()
+        .map(|pt| pt.y.round() as fword)
+        .max()
+        .unwrap();
+    let y_min = xformed_points
+        .iter()
+        .map(|pt| pt.y.round() as fword)
+        .min()
+        .unwrap();
 
     GlyphCommon {
         x_min,
@@ -183,6 +349,23 @@ fn affine_bb(aux: &AuxiliarySettings, aff: &Affine2D<Point2D>, bb: &GlyphCommon)
     }
 }
 
+/// The bounding box of some contours. Empty boxes don't intersect anything.
+fn quads_bb(contours: &[QuadBezier<(fword, fword)>]) -> GlyphCommon {
+    let mut bb = GlyphCommon {
+        x_min: fword::MAX,
+        y_min: fword::MAX,
+        x_max: fword::MIN,
+        y_max: fword::MIN,
+    };
+    for (_, (x, y)) in contours.iter().flat_map(|c| c.iter()) {
+        bb.x_min = bb.x_min.min(x);
+        bb.y_min = bb.y_min.min(y);
+        bb.x_max = bb.x_max.max(x);
+        bb.y_max = bb.y_max.max(y);
+    }
+    bb
+}
+
 fn cvt_pt(x: fword, y: fword) -> Point2D {
     Point2D::new(x as f64, y as f64)
 }
@@ -0,0 +1,663 @@
+//! Parametric autohinting.
+//!
+//! Unlike a general-purpose autohinter, we don't need to guess the blue zones
+//! and the standard stem width from the outlines: they are design parameters
+//! given by [`EvalSettings`]. This stage only has to find which points of
+//! each glyph lie on those zones and stems, and emit the instructions that
+//! snap them to the corresponding CVT entries.
+
+use std::collections::HashSet;
+
+use monoxide_ttf::{
+    hl,
+    model::{
+        cvt, fword, gasp,
+        glyf::{self, simple::SimpleGlyph},
+        inst::{self, Axis, DistanceType, Instruction, MoveFlags},
+        prep,
+    },
+};
+
+use crate::{EvalSettings, eval::AuxiliarySettings};
+
+/// The tables generated by the autohinter.
+pub struct HintTables {
+    pub prep: prep::Table,
+    pub cvt: cvt::Table,
+    pub gasp: gasp::Table,
+}
+
+/// A blue zone, i.e. the height where flat glyph edges lie, and the height
+/// round glyph edges overshoot to.
+#[derive(Debug, Clone, Copy)]
+struct BlueZone {
+    flat: fword,
+    overshoot: fword,
+    flat_cvt: u16,
+    overshoot_cvt: u16,
+}
+
+impl BlueZone {
+    fn is_top(&self) -> bool {
+        self.overshoot > self.flat
+    }
+
+    /// Returns the CVT entry the coordinate should be snapped to, if it lies
+    /// within the zone.
+    fn cvt_for(&self, y: fword, tolerance: fword) -> Option<u16> {
+        let (lo, hi) = if self.is_top() {
+            (self.flat - tolerance, self.overshoot + tolerance)
+        } else {
+            (self.overshoot - tolerance, self.flat + tolerance)
+        };
+        if !(lo..=hi).contains(&y) {
+            return None;
+        }
+        if (y - self.flat).abs() <= (y - self.overshoot).abs() {
+            Some(self.flat_cvt)
+        } else {
+            Some(self.overshoot_cvt)
+        }
+    }
+}
+
+/// The hinting parameters in font units.
+struct Params {
+    upem: f64,
+    zones: Vec<BlueZone>,
+    /// The standard stem width and its CVT entry
+    stem: Option<(fword, u16)>,
+    zone_tolerance: fword,
+    min_edge_length: fword,
+}
+
+/// A point of a glyph outline.
+#[derive(Debug, Clone, Copy)]
+struct Pt {
+    index: u16,
+    on_curve: bool,
+    x: fword,
+    y: fword,
+}
+
+impl Pt {
+    /// The coordinate measured along the axis.
+    fn pos(&self, axis: Axis) -> fword {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+        }
+    }
+
+    /// The coordinate across the axis.
+    fn across(&self, axis: Axis) -> fword {
+        match axis {
+            Axis::X => self.y,
+            Axis::Y => self.x,
+        }
+    }
+}
+
+/// A set of points that share the same coordinate along an axis, and should
+/// be moved together.
+#[derive(Debug, Clone)]
+struct Edge {
+    pos: fword,
+    points: Vec<u16>,
+    /// The range covered across the axis
+    lo: fword,
+    hi: fword,
+    /// The direction of the outline across the axis, either -1 or 1.
+    dir: i32,
+}
+
+impl Edge {
+    fn overlaps(&self, other: &Edge, margin: fword) -> bool {
+        self.lo - margin <= other.hi && other.lo - margin <= self.hi
+    }
+}
+
+/// Hints all simple glyphs in place, and returns the font-wide tables.
+pub fn autohint(
+    settings: &dyn EvalSettings,
+    aux: &AuxiliarySettings,
+    glyphs: &mut [glyf::Glyph],
+) -> HintTables {
+    let upem = aux.point_per_em as f64;
+    let to_units = |v: f64| (v * upem).round() as fword;
+    let ovs = settings.overshoot().abs();
+
+    let mut cvt = vec![];
+    let mut push_cvt = |v: f64| {
+        cvt.push(to_units(v));
+        (cvt.len() - 1) as u16
+    };
+    let mut zones = vec![];
+    for (flat, overshoot) in [
+        (0.0, -ovs),
+        (settings.x_height(), settings.x_height() + ovs),
+        (settings.cap_height(), settings.cap_height() + ovs),
+        (settings.descender(), settings.descender() - ovs),
+    ] {
+        zones.push(BlueZone {
+            flat: to_units(flat),
+            overshoot: to_units(overshoot),
+            flat_cvt: push_cvt(flat),
+            overshoot_cvt: push_cvt(overshoot),
+        });
+    }
+    let stem = settings
+        .stroke_width()
+        .map(|stw| (to_units(stw), push_cvt(stw)));
+
+    let params = Params {
+        upem,
+        zone_tolerance: to_units(0.01).max(1),
+        min_edge_length: stem.map_or(to_units(0.02), |(stw, _)| stw / 2).max(1),
+        zones,
+        stem,
+    };
+
+    for glyph in glyphs.iter_mut() {
+        if let glyf::Glyph::Simple(glyph) = glyph {
+            glyph.instructions = inst::assemble(&hint_glyph(&params, glyph));
+        }
+    }
+
+    HintTables {
+        prep: prep::Table {
+            instructions: inst::assemble(&control_value_program(&params)),
+        },
+        cvt: cvt::Table { values: cvt },
+        gasp: gasp::Table {
+            ranges: vec![
+                gasp::Range {
+                    range_max_ppem: 8,
+                    behavior: gasp::Behavior::DOGRAY | gasp::Behavior::SYMMETRIC_SMOOTHING,
+                },
+                gasp::Range {
+                    range_max_ppem: 0xffff,
+                    behavior: gasp::Behavior::all(),
+                },
+            ],
+        },
+    }
+}
+
+/// The control value program.
+///
+/// Besides the usual scan conversion settings, it flattens the overshoots
+/// while they are smaller than half a pixel, so that round and flat glyphs
+/// have the same height at small sizes.
+fn control_value_program(params: &Params) -> Vec<Instruction> {
+    let mut code = vec![];
+    // Dropout control at all sizes, smart dropout control
+    code.extend(Instruction::push(&[0x01ff]));
+    code.push(Instruction::Scanctrl);
+    code.extend(Instruction::push(&[4]));
+    code.push(Instruction::Scantype);
+
+    let max_overshoot = params
+        .zones
+        .iter()
+        .map(|z| (z.overshoot - z.flat).abs())
+        .max()
+        .unwrap_or(0);
+    if max_overshoot > 0 {
+        // overshoot / upem * ppem < 0.5
+        let threshold = (0.5 * params.upem / max_overshoot as f64).ceil();
+        let threshold = threshold.min(i16::MAX as f64) as i16;
+
+        code.push(Instruction::Mppem);
+        code.extend(Instruction::push(&[threshold]));
+        code.push(Instruction::Lt);
+        code.push(Instruction::If);
+        for zone in &params.zones {
+            code.extend(Instruction::push(&[
+                zone.overshoot_cvt as i16,
+                zone.flat_cvt as i16,
+            ]));
+            code.push(Instruction::Rcvt);
+            code.push(Instruction::Wcvtp);
+        }
+        code.push(Instruction::Eif);
+    }
+    code
+}
+
+/// Generates the instructions of a single glyph.
+///
+/// The arguments of all instructions are pushed at once at the start of the
+/// program, in reverse order, so that each instruction finds its arguments
+/// at the top of the stack.
+fn hint_glyph(params: &Params, glyph: &SimpleGlyph) -> Vec<Instruction> {
+    let Ok(contours) = hl::glyf::decode(glyph) else {
+        return vec![];
+    };
+    let mut index = 0;
+    let contours = contours
+        .iter()
+        .map(|c| {
+            c.iter()
+                .map(|(on_curve, (x, y))| {
+                    index += 1;
+                    Pt {
+                        index: index - 1,
+                        on_curve,
+                        x,
+                        y,
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut prog = Program::default();
+    for axis in [Axis::Y, Axis::X] {
+        let edges = find_edges(params, &contours, axis);
+        let anchors = match axis {
+            Axis::Y => edges
+                .iter()
+                .enumerate()
+                .filter_map(|(ix, edge)| {
+                    params
+                        .zones
+                        .iter()
+                        .find_map(|z| z.cvt_for(edge.pos, params.zone_tolerance))
+                        .map(|cvt| (ix, cvt))
+                })
+                .collect(),
+            Axis::X => vec![],
+        };
+        let stems = find_stems(params, &edges);
+        if anchors.is_empty() && stems.is_empty() {
+            continue;
+        }
+
+        prog.inst(vec![], Instruction::Svtca(axis));
+        let mut touched = HashSet::new();
+        for &(ix, cvt) in &anchors {
+            let edge = &edges[ix];
+            prog.inst(
+                vec![edge.points[0] as i16, cvt as i16],
+                Instruction::Miap(true),
+            );
+            prog.align_rest(edge);
+            touched.insert(ix);
+        }
+        for (a, b) in stems {
+            let (base, other) = if touched.contains(&b) { (b, a) } else { (a, b) };
+            if touched.contains(&other) {
+                continue;
+            }
+            if touched.contains(&base) {
+                prog.inst(vec![edges[base].points[0] as i16], Instruction::Srp0);
+            } else {
+                prog.inst(vec![edges[base].points[0] as i16], Instruction::Mdap(true));
+                prog.align_rest(&edges[base]);
+                touched.insert(base);
+            }
+            let flags = MoveFlags {
+                set_rp0: true,
+                min_distance: true,
+                round: true,
+                distance_type: DistanceType::Gray,
+            };
+            match params.stem {
+                Some((_, cvt)) => prog.inst(
+                    vec![edges[other].points[0] as i16, cvt as i16],
+                    Instruction::Mirp(flags),
+                ),
+                None => prog.inst(
+                    vec![edges[other].points[0] as i16],
+                    Instruction::Mdrp(flags),
+                ),
+            }
+            prog.align_rest(&edges[other]);
+            touched.insert(other);
+        }
+        prog.inst(vec![], Instruction::Iup(axis));
+    }
+    prog.finish()
+}
+
+/// Finds the edges of the outline along the axis. These are either runs of
+/// consecutive points with the same coordinate, or on-curve points at a local
+/// extremum.
+fn find_edges(params: &Params, contours: &[Vec<Pt>], axis: Axis) -> Vec<Edge> {
+    let mut edges = vec![];
+    for contour in contours {
+        let n = contour.len();
+        if n < 3 {
+            continue;
+        }
+        let same = |a: &Pt, b: &Pt| a.pos(axis) == b.pos(axis);
+        // Start from a point that begins a run, so that runs don't wrap around
+        let Some(start) = (0..n).find(|&i| !same(&contour[(i + n - 1) % n], &contour[i])) else {
+            continue;
+        };
+
+        let mut in_run = vec![false; n];
+        let mut i = 0;
+        while i < n {
+            let first = contour[(start + i) % n];
+            let mut len = 1;
+            while len < n && same(&first, &contour[(start + i + len) % n]) {
+                len += 1;
+            }
+            if len > 1 {
+                let last = contour[(start + i + len - 1) % n];
+                let extent = last.across(axis) - first.across(axis);
+                if extent.abs() >= params.min_edge_length {
+                    let run = (i..i + len).map(|j| contour[(start + j) % n]);
+                    edges.push(Edge {
+                        pos: first.pos(axis),
+                        points: run.clone().map(|p| p.index).collect(),
+                        lo: run.clone().map(|p| p.across(axis)).min().unwrap(),
+                        hi: run.map(|p| p.across(axis)).max().unwrap(),
+                        dir: extent.signum() as i32,
+                    });
+                    for j in i..i + len {
+                        in_run[(start + j) % n] = true;
+                    }
+                }
+            }
+            i += len;
+        }
+
+        for (i, p) in contour.iter().enumerate() {
+            if in_run[i] || !p.on_curve {
+                continue;
+            }
+            let prev = contour[(i + n - 1) % n];
+            let next = contour[(i + 1) % n];
+            let is_max = prev.pos(axis) < p.pos(axis) && next.pos(axis) < p.pos(axis);
+            let is_min = prev.pos(axis) > p.pos(axis) && next.pos(axis) > p.pos(axis);
+            if is_max || is_min {
+                edges.push(Edge {
+                    pos: p.pos(axis),
+                    points: vec![p.index],
+                    lo: p.across(axis),
+                    hi: p.across(axis),
+                    dir: (next.across(axis) - prev.across(axis)).signum() as i32,
+                });
+            }
+        }
+    }
+    edges
+}
+
+/// Pairs edges into stems of about the standard stem width. Returns pairs of
+/// edge indices.
+fn find_stems(params: &Params, edges: &[Edge]) -> Vec<(usize, usize)> {
+    let Some((stw, _)) = params.stem else {
+        return vec![];
+    };
+    let (min_width, max_width) = (stw / 2, stw * 3 / 2);
+
+    let mut candidates = vec![];
+    for (i, a) in edges.iter().enumerate() {
+        for (j, b) in edges.iter().enumerate() {
+            let width = b.pos - a.pos;
+            if a.dir != 0
+                && a.dir == -b.dir
+                && (min_width..=max_width).contains(&width)
+                && a.overlaps(b, stw / 2)
+            {
+                candidates.push(((width - stw).abs(), i, j));
+            }
+        }
+    }
+    candidates.sort();
+
+    let mut used = HashSet::new();
+    let mut stems = vec![];
+    for (_, i, j) in candidates {
+        if !used.contains(&i) && !used.contains(&j) {
+            used.extend([i, j]);
+            stems.push((i, j));
+        }
+    }
+    stems
+}
+
+/// A glyph program under construction.
+#[derive(Default)]
+struct Program {
+    insts: Vec<(Vec<i16>, Instruction)>,
+}
+
+impl Program {
+    fn inst(&mut self, args: Vec<i16>, inst: Instruction) {
+        self.insts.push((args, inst));
+    }
+
+    /// Aligns the rest of the points of an edge to `rp0`, which should be set
+    /// to the first point of the edge.
+    fn align_rest(&mut self, edge: &Edge) {
+        let rest = &edge.points[1..];
+        if rest.is_empty() {
+            return;
+        }
+        self.inst(vec![rest.len() as i16], Instruction::Sloop);
+        self.inst(
+            rest.iter().map(|&p| p as i16).collect(),
+            Instruction::Alignrp,
+        );
+    }
+
+    fn finish(self) -> Vec<Instruction> {
+        if self.insts.is_empty() {
+            return vec![];
+        }
+        let args = self
+            .insts
+            .iter()
+            .rev()
+            .flat_map(|(args, _)| args.iter().copied())
+            .collect::<Vec<_>>();
+        let mut code = Instruction::push(&args);
+        code.extend(self.insts.into_iter().map(|(_, inst)| inst));
+        code
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct TestSettings;
+
+#[cfg(test)]
+impl EvalSettings for TestSettings {
+    fn mono_width(&self) -> f64 {
+        0.5
+    }
+
+    fn cap_height(&self) -> f64 {
+        0.7
+    }
+
+    fn descender(&self) -> f64 {
+        -0.2
+    }
+
+    fn x_height(&self) -> f64 {
+        0.5
+    }
+
+    fn stroke_width(&self) -> Option<f64> {
+        Some(0.08)
+    }
+
+    fn overshoot(&self) -> f64 {
+        0.01
+    }
+}
+
+#[cfg(test)]
+fn test_settings() -> (TestSettings, AuxiliarySettings) {
+    let aux = AuxiliarySettings {
+        point_per_em: 1000,
+        ..Default::default()
+    };
+    (TestSettings, aux)
+}
+
+/// Hints a glyph with a single closed polygon, and returns its program.
+#[cfg(test)]
+fn hint_polygon(points: &[(fword, fword)]) -> Vec<Instruction> {
+    let mut outline = monoxide_curves::QuadBezier::builder(points[0]);
+    for &p in points[1..].iter().chain(&points[..1]) {
+        outline.line_to(p);
+    }
+    outline.close();
+    let glyph = hl::glyf::encode(&[outline.build()]).unwrap();
+
+    let (settings, aux) = test_settings();
+    let mut glyphs = [glyf::Glyph::Simple(glyph)];
+    autohint(&settings, &aux, &mut glyphs);
+    let glyf::Glyph::Simple(glyph) = &glyphs[0] else {
+        unreachable!()
+    };
+    inst::disassemble(&glyph.instructions).unwrap()
+}
+
+#[test]
+fn test_hint_tables() {
+    let (settings, aux) = test_settings();
+    let tables = autohint(&settings, &aux, &mut []);
+
+    // Flat and overshoot heights of the baseline, x-height, cap height and
+    // descender, then the stem width.
+    assert_eq!(
+        tables.cvt.values,
+        [0, -10, 500, 510, 700, 710, -200, -210, 80]
+    );
+
+    // Overshoots of 10 units are flattened below 50 ppem, where they are
+    // smaller than half a pixel.
+    let mut prep = Instruction::push(&[0x01ff]);
+    prep.push(Instruction::Scanctrl);
+    prep.extend(Instruction::push(&[4]));
+    prep.push(Instruction::Scantype);
+    prep.push(Instruction::Mppem);
+    prep.extend(Instruction::push(&[50]));
+    prep.extend([Instruction::Lt, Instruction::If]);
+    for (overshoot, flat) in [(1, 0), (3, 2), (5, 4), (7, 6)] {
+        prep.extend(Instruction::push(&[overshoot, flat]));
+        prep.extend([Instruction::Rcvt, Instruction::Wcvtp]);
+    }
+    prep.push(Instruction::Eif);
+    assert_eq!(inst::disassemble(&tables.prep.instructions).unwrap(), prep);
+
+    let ranges = (tables.gasp.ranges.iter())
+        .map(|r| (r.range_max_ppem, r.behavior))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranges,
+        [
+            (
+                8,
+                gasp::Behavior::DOGRAY | gasp::Behavior::SYMMETRIC_SMOOTHING
+            ),
+            (0xffff, gasp::Behavior::all()),
+        ]
+    );
+}
+
+#[test]
+fn test_hint_stem() {
+    let flags = MoveFlags {
+        set_rp0: true,
+        min_distance: true,
+        round: true,
+        distance_type: DistanceType::Gray,
+    };
+
+    // A vertical stem of the standard width from the baseline to the cap
+    // height. The flat edges snap to their zones, and the right edge keeps
+    // the stem width from the left one.
+    let code = hint_polygon(&[(100, 0), (100, 700), (180, 700), (180, 0)]);
+    let mut expected = Instruction::push(&[3, 1, 2, 8, 1, 1, 0, 0, 1, 3, 0, 2, 1, 1, 4]);
+    expected.extend([
+        Instruction::Svtca(Axis::Y),
+        // Points 1 and 2 to the cap height, 3 and 0 to the baseline
+        Instruction::Miap(true),
+        Instruction::Sloop,
+        Instruction::Alignrp,
+        Instruction::Miap(true),
+        Instruction::Sloop,
+        Instruction::Alignrp,
+        Instruction::Iup(Axis::Y),
+        Instruction::Svtca(Axis::X),
+        // Points 0 and 1 are rounded, and 2 and 3 follow at the stem width
+        Instruction::Mdap(true),
+        Instruction::Sloop,
+        Instruction::Alignrp,
+        Instruction::Mirp(flags),
+        Instruction::Sloop,
+        Instruction::Alignrp,
+        Instruction::Iup(Axis::X),
+    ]);
+    assert_eq!(code, expected);
+
+    // The extrema of a diamond are round edges, which snap to the overshoot
+    // heights of the x-height and the baseline.
+    let code = hint_polygon(&[(100, 250), (140, 508), (180, 250), (140, -8)]);
+    let mut expected = Instruction::push(&[2, 8, 0, 3, 1, 1, 3]);
+    expected.extend([
+        Instruction::Svtca(Axis::Y),
+        Instruction::Miap(true),
+        Instruction::Miap(true),
+        Instruction::Iup(Axis::Y),
+        Instruction::Svtca(Axis::X),
+        Instruction::Mdap(true),
+        Instruction::Mirp(flags),
+        Instruction::Iup(Axis::X),
+    ]);
+    assert_eq!(code, expected);
+
+    // Glyphs without edges on zones or stems are left alone.
+    assert_eq!(hint_polygon(&[(100, 100), (300, 400), (400, 150)]), []);
+}
+
+#[test]
+fn test_hinted_font_analysis() {
+    use monoxide_ttf::hl::inst::Analyzer;
+
+    use super::test::{eval_tables, rect};
+    use crate::{ast::FontContext, prelude::*};
+
+    let mut cx = FontContext::new(TestSettings);
+    let tofu = Glyph::builder().outline(rect(0.05, 0., 0.45, 0.7)).build();
+    let stem = Glyph::builder().outline(rect(0.1, 0., 0.18, 0.7)).build();
+    cx.set_mapping('I', stem);
+    cx.set_mapping(char::REPLACEMENT_CHARACTER, tofu);
+    cx.set_tofu();
+    let tables = eval_tables(&cx, &Default::default());
+
+    // The programs pass the static analysis of `hl::inst` without errors, and
+    // fit in the resources declared in `maxp`.
+    let mut analyzer = Analyzer::new();
+    let prep = tables
+        .prep
+        .expect("hinted fonts have a control value program");
+    analyzer.analyze_bytecode(&prep.instructions).unwrap();
+    let mut hinted = 0;
+    for glyph in &tables.glyf.glyphs {
+        if let glyf::Glyph::Simple(glyph) = glyph
+            && !glyph.instructions.is_empty()
+        {
+            analyzer.analyze_bytecode(&glyph.instructions).unwrap();
+            hinted += 1;
+        }
+    }
+    assert!(hinted > 0);
+    let usage = analyzer.usage();
+    assert!(usage.max_stack_elements > 0);
+    assert_eq!(
+        usage.max_stack_elements,
+        tables.maxp.max_stack_elements as usize
+    );
+    assert_eq!(usage.max_function_defs, 0);
+    assert_eq!(usage.max_storage, 0);
+}
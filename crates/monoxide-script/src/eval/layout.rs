@@ -1,11 +1,15 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+};
 
 use by_address::ByAddress;
 use monoxide_curves::xform::Affine2D;
+use monoxide_ttf::hl::post::is_valid_glyph_name;
 use petgraph::prelude::DiGraphMap;
 
 use crate::{
-    ast::{FontContext, Glyph, GlyphInner},
+    ast::{FontContext, Glyph, GlyphInner, Overlap},
     eval::{
         HighEvalError, SerializedComponent, SerializedFontContext, SerializedGlyph,
         SerializedGlyphKind,
@@ -14,9 +18,9 @@ use crate::{
 
 /// Lays out all glyphs referenced within a [`FontContext`] into a linear list.
 pub fn layout_glyphs(cx: &FontContext) -> Result<SerializedFontContext, HighEvalError> {
-    // We perform two DFSes.
-    // 1. Split glyphs into their simple and compound parts
-    // 2. Serialize them into a vector of glyphs
+    // We perform two passes.
+    // 1. Split glyphs into their simple and compound parts (DFS)
+    // 2. Serialize them into a vector of glyphs with deterministic IDs (BFS)
     if cx.tofu.is_none() {
         return Err(HighEvalError::TofuUnset);
     }
@@ -25,12 +29,33 @@ pub fn layout_glyphs(cx: &FontContext) -> Result<SerializedFontContext, HighEval
     let mut ser = GlyphSerializer::new(cx, split_glyphs);
     ser.serialize();
 
-    Ok(ser.build())
+    let scx = ser.build();
+    check_glyph_names(&scx)?;
+    Ok(scx)
+}
+
+/// Glyph names must be valid PostScript names and unique within the font, see
+/// [`SerializedGlyph::name`].
+fn check_glyph_names(scx: &SerializedFontContext) -> Result<(), HighEvalError> {
+    let mut names = HashSet::new();
+    for name in scx.glyph_list.iter().filter_map(|g| g.name.as_deref()) {
+        if !is_valid_glyph_name(name) {
+            return Err(HighEvalError::InvalidGlyphName(name.to_owned()));
+        }
+        if !names.insert(name) {
+            return Err(HighEvalError::DuplicateGlyphName(name.to_owned()));
+        }
+    }
+    Ok(())
 }
 
 /// DFS into glyphs and split them into their simple and compound parts. Returns
 /// a map of glyphs to their simple parts, if any.
 ///
+/// The search continues into the components of each glyph, since a component
+/// may itself have both outlines and components, and needs to be split just
+/// like the glyphs mapped from characters.
+///
 /// After this function finishes, one will only need to check if the compound
 /// part is empty to determine if the glyph is simple or compound.
 fn split(cx: &FontContext) -> HashMap<ByAddress<Glyph>, Glyph> {
@@ -39,7 +64,7 @@ fn split(cx: &FontContext) -> HashMap<ByAddress<Glyph>, Glyph> {
     let mut stack = Vec::<&Glyph>::new();
 
     stack.push(cx.tofu.as_ref().expect("Should be checked"));
-    for glyph in cx.cmap.values() {
+    for glyph in cx.cmap.values().chain(cx.variations.values()) {
         stack.push(glyph);
     }
 
@@ -55,9 +80,14 @@ fn split(cx: &FontContext) -> HashMap<ByAddress<Glyph>, Glyph> {
                 outlines: glyph.outlines.clone(),
                 components: vec![],
                 advance: glyph.advance,
+                name: None,
+                decompose: false,
             });
             res.insert(glyph.clone().into(), new_glyph);
         }
+        for cmp in &glyph.components {
+            stack.push(&cmp.component);
+        }
     }
 
     res
@@ -71,11 +101,11 @@ struct GlyphSerializer<'a> {
     map: HashMap<ByAddress<Glyph>, usize>,
     /// Built `cmap` table
     cmap: BTreeMap<char, usize>,
+    /// Built variation sequences
+    variations: BTreeMap<(char, char), usize>,
 
     /// List of glyphs not yet transformed into [`SerializedGlyph`]
     glyphs: Vec<Glyph>,
-    /// DFS stack
-    stack: Vec<Glyph>,
 }
 
 impl<'a> GlyphSerializer<'a> {
@@ -86,7 +116,7 @@ impl<'a> GlyphSerializer<'a> {
             map: HashMap::new(),
             glyphs: Vec::new(),
             cmap: BTreeMap::new(),
-            stack: Vec::new(),
+            variations: BTreeMap::new(),
         }
     }
 
@@ -104,37 +134,82 @@ impl<'a> GlyphSerializer<'a> {
         // TOFU must be at index 0
         let tofu = self.cx.tofu.as_ref().expect("Should be checked");
         let tofu_id = self.assign_id(tofu);
-        self.stack.push(tofu.clone());
         assert_eq!(tofu_id, 0);
 
         // The rest of the root glyphs need to be laid out continuously after TOFU.
+        // They are assigned in codepoint order, so that consecutive characters
+        // form runs in the `cmap` table.
         for (&ch, glyph) in &self.cx.cmap {
             let id = self.assign_id(glyph);
             self.cmap.insert(ch, id);
-            self.stack.push(glyph.clone());
+        }
+        for (&seq, glyph) in &self.cx.variations {
+            let id = self.assign_id(glyph);
+            self.variations.insert(seq, id);
         }
 
-        // Now we can start the DFS.
-        while let Some(glyph) = self.stack.pop() {
-            self.assign_id(&glyph);
-            if glyph.inner().components.is_empty() {
-                // Simple glyph
-            } else {
-                if let Some(simple_glyph) = self.split_glyphs.get(ByAddress::from_ref(&glyph)) {
-                    self.stack.push(simple_glyph.clone());
-                }
-                for cmp in &glyph.inner().components {
-                    self.stack.push(cmp.component.clone());
+        // Discover the remaining glyphs breadth-first from the roots, in the
+        // order of their IDs and components.
+        let mut queue = self.glyphs.clone();
+        let mut discovered = self
+            .glyphs
+            .iter()
+            .cloned()
+            .map(ByAddress)
+            .collect::<HashSet<_>>();
+        let mut ix = 0;
+        while let Some(glyph) = queue.get(ix).cloned() {
+            ix += 1;
+            let simple_glyph = self.split_glyphs.get(ByAddress::from_ref(&glyph));
+            let components = glyph.inner().components.iter().map(|c| &c.component);
+            for child in simple_glyph.into_iter().chain(components) {
+                if discovered.insert(ByAddress(child.clone())) {
+                    queue.push(child.clone());
                 }
             }
         }
+
+        // Named glyphs come first in name order, followed by unnamed glyphs in
+        // the order they are discovered. This keeps their IDs stable when
+        // unrelated glyphs change.
+        let mut rest = queue.split_off(self.glyphs.len());
+        rest.sort_by(|a, b| match (&a.name, &b.name) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        for glyph in &rest {
+            self.assign_id(glyph);
+        }
     }
 
     fn build(&self) -> SerializedFontContext {
+        // Unnamed glyphs reachable from `cmap` are named after the lowest
+        // character mapped to them.
+        let mut auto_names = HashMap::new();
+        auto_names.insert(0, ".notdef".to_owned());
+        for (&ch, &idx) in &self.cmap {
+            auto_names
+                .entry(idx)
+                .or_insert_with(|| monoxide_ttf::hl::post::glyph_name(ch));
+        }
+        // Glyph variants are suffixed with their selector, e.g. `zero.uniFE00`.
+        for (&(ch, selector), &idx) in &self.variations {
+            auto_names.entry(idx).or_insert_with(|| {
+                let base = monoxide_ttf::hl::post::glyph_name(ch);
+                let selector = monoxide_ttf::hl::post::glyph_name(selector);
+                format!("{base}.{selector}")
+            });
+        }
+
         let mut glyph_list = Vec::new();
         let mut glyph_map = DiGraphMap::new();
         for (idx, glyph) in self.glyphs.iter().enumerate() {
-            let serialized = self.convert_to_serialized_glyph(glyph);
+            let mut serialized = self.convert_to_serialized_glyph(glyph);
+            if serialized.name.is_none() {
+                serialized.name = auto_names.remove(&idx);
+            }
 
             glyph_map.add_node(idx);
             match &serialized.kind {
@@ -148,16 +223,38 @@ impl<'a> GlyphSerializer<'a> {
             glyph_list.push(serialized);
         }
 
+        // The remaining glyphs, such as components, are named after their
+        // glyph ID. Names taken by other glyphs are skipped, so that a glyph
+        // named e.g. `glyph5` by the user doesn't clash.
+        let taken = (glyph_list.iter())
+            .filter_map(|g| g.name.clone())
+            .collect::<HashSet<_>>();
+        for (idx, glyph) in glyph_list.iter_mut().enumerate() {
+            if glyph.name.is_none() {
+                let name = std::iter::once(format!("glyph{idx}"))
+                    .chain((1..).map(|n| format!("glyph{idx}.{n}")))
+                    .find(|name| !taken.contains(name))
+                    .expect("Should find an unused name");
+                glyph.name = Some(name);
+            }
+        }
+
         SerializedFontContext {
             glyph_list,
             cmap: self.cmap.clone(),
+            variations: self.variations.clone(),
             glyph_map,
         }
     }
 
     fn convert_to_serialized_glyph(&self, glyph: &Glyph) -> SerializedGlyph {
         let inner = glyph.inner();
-        let advance = inner.advance;
+        // The advance of a glyph using the metrics of a component follows that
+        // component, unless set explicitly.
+        let metrics_component = inner.components.iter().find(|c| c.use_my_metrics);
+        let advance = inner
+            .advance
+            .or_else(|| metrics_component.and_then(|c| c.component.advance));
         let kind = if inner.components.is_empty() {
             SerializedGlyphKind::Simple(inner.outlines.clone())
         } else {
@@ -168,8 +265,12 @@ impl<'a> GlyphSerializer<'a> {
                     .map
                     .get(ByAddress::from_ref(simple_glyph))
                     .expect("Should be assigned");
-                let xform = Affine2D::id();
-                components.push(SerializedComponent { index, xform });
+                components.push(SerializedComponent {
+                    index,
+                    xform: Affine2D::id(),
+                    use_my_metrics: false,
+                    overlap: Overlap::Auto,
+                });
             }
 
             components.extend(inner.components.iter().map(|c| {
@@ -177,11 +278,21 @@ impl<'a> GlyphSerializer<'a> {
                     .map
                     .get(ByAddress::from_ref(&c.component))
                     .expect("Should be assigned");
-                let xform = c.xform;
-                SerializedComponent { index, xform }
+                SerializedComponent {
+                    index,
+                    xform: c.xform,
+                    use_my_metrics: c.use_my_metrics,
+                    overlap: c.overlap,
+                }
             }));
             SerializedGlyphKind::Compound(components)
         };
-        SerializedGlyph { kind, advance }
+        SerializedGlyph {
+            kind,
+            advance,
+            name: inner.name.clone(),
+            decompose: inner.decompose,
+            inlined: vec![],
+        }
     }
 }
@@ -1,5 +1,9 @@
 use itertools::{Itertools, chain};
-use monoxide_curves::{CubicBezier, SpiroCurve, point::Point2D, stroke::StrokedSpiroCurve};
+use monoxide_curves::{
+    CubicBezier, CubicSegment, SpiroCurve, point::Point2D, stroke::StrokedSpiroCurve,
+    xform::Affine2D,
+};
+use monoxide_spiro::SpiroCpTy;
 
 use crate::{ast::OutlineExpr, trace::EvalTracer};
 
@@ -11,7 +15,22 @@ pub fn eval_outline<E: EvalTracer>(
     out: &mut Vec<CubicBezier<Point2D>>,
     dbg: &mut E,
 ) -> EvalResult<E, E::Id> {
-    let evaled = eval_outline_internal(expr, dbg)?;
+    eval_outline_sheared(expr, 0., out, dbg)
+}
+
+/// Evaluate an outline like [`eval_outline`], sheared horizontally around the
+/// baseline by `shear`, i.e. the offset in `x` per unit of `y`.
+///
+/// Spiro skeletons are sheared before they are stroked, so that strokes keep
+/// their widths across the slanted skeleton, and curves are solved anew
+/// through the sheared control points instead of being distorted.
+pub fn eval_outline_sheared<E: EvalTracer>(
+    expr: &OutlineExpr,
+    shear: f64,
+    out: &mut Vec<CubicBezier<Point2D>>,
+    dbg: &mut E,
+) -> EvalResult<E, E::Id> {
+    let evaled = eval_outline_internal(expr, shear, dbg)?;
     let id = match evaled.kind {
         EvalValueKind::Beziers(beziers) => {
             out.extend(beziers);
@@ -93,13 +112,112 @@ pub enum EvalError<Id> {
     CurveError(Id, #[source] monoxide_curves::error::Error),
 }
 
-fn eval_outline_internal<E: EvalTracer>(expr: &OutlineExpr, dbg: &mut E) -> EvalResult<E> {
+/// The transform shearing `x` by `shear` per unit of `y`.
+pub fn shear_xform(shear: f64) -> Affine2D<Point2D> {
+    Affine2D::make(Point2D::new(0., 0.), [(1., shear), (0., 1.)])
+}
+
+/// Shear the control points of a spiro curve.
+///
+/// Overridden tangents set the direction of the stroke's cross section, so
+/// they are transformed like normals, keeping the cross sections at the same
+/// angle as before shearing. The open ends get such tangents too, so that
+/// e.g. stems stay cut flat at the baseline.
+fn shear_spiro(spiro: &SpiroCurve, shear: f64) -> SpiroCurve {
+    let mut spiro = spiro.clone();
+    if spiro
+        .points
+        .first()
+        .is_some_and(|cp| cp.ty == SpiroCpTy::Open)
+    {
+        let last = spiro.len() - 1;
+        for (idx, tangent) in [0, last].into_iter().zip(open_end_tangents(&spiro)) {
+            if let Some(tangent) = tangent {
+                spiro.tangents.entry(idx).or_insert(tangent);
+            }
+        }
+    }
+    for cp in &mut spiro.points {
+        cp.x += shear * cp.y;
+    }
+    // Stroking expects unit tangents.
+    for tangent in spiro.tangents.values_mut() {
+        *tangent = Point2D::new(tangent.x, tangent.y - shear * tangent.x).normalize();
+    }
+    spiro
+}
+
+/// The tangents at the start and the end of an open spiro curve, if it can be
+/// solved.
+fn open_end_tangents(spiro: &SpiroCurve) -> [Option<Point2D>; 2] {
+    let Ok(beziers) = monoxide_curves::convert::spiro_to_cube(&spiro.points) else {
+        return [None, None];
+    };
+    let non_zero = |v: Point2D| (v.x != 0. || v.y != 0.).then_some(v);
+    let start = beziers.first().and_then(|b| {
+        let first = b.segments.first()?;
+        let towards = match first {
+            CubicSegment::Line(p) | CubicSegment::Curve(p, _, _) => *p,
+        };
+        non_zero(towards - b.start)
+    });
+    let end = beziers.last().and_then(|b| {
+        let n = b.segment_count();
+        let last = b.segment(n.checked_sub(1)?)?;
+        let from = match last.rest {
+            CubicSegment::Line(_) => last.start,
+            CubicSegment::Curve(_, c2, _) => c2,
+        };
+        non_zero(last.rest.last_point() - from)
+    });
+    [start, end]
+}
+
+/// Move the shear applied after `xform` to before it where possible, so that
+/// the transformed outline is sheared at the spiro level too.
+///
+/// Returns the shear to apply to the inner outline, and the transform to apply
+/// after it.
+fn push_shear(xform: &Affine2D<Point2D>, shear: f64) -> (f64, Affine2D<Point2D>) {
+    let outer = shear_xform(shear);
+    let trans = outer.apply(&xform.translation());
+    if let Some((sx, sy)) = xform.mat_is_only_scale() {
+        // shear · scale = scale · shear', with the shear scaled by sy / sx.
+        (shear * sy / sx, Affine2D::make(trans, xform.matrix()))
+    } else {
+        // Otherwise, shear the inner outline as is, and conjugate the
+        // transform with the shear: shear · xform · shear⁻¹.
+        let [r0, r1] = xform.matrix();
+        let top = Point2D::new(r0.x + shear * r1.x, r0.y + shear * r1.y);
+        let mat = [
+            Point2D::new(top.x, top.y - shear * top.x),
+            Point2D::new(r1.x, r1.y - shear * r1.x),
+        ];
+        (shear, Affine2D::make(trans, mat))
+    }
+}
+
+fn eval_outline_internal<E: EvalTracer>(
+    expr: &OutlineExpr,
+    shear: f64,
+    dbg: &mut E,
+) -> EvalResult<E> {
     match expr {
         OutlineExpr::Bezier(cubic_bezier) => {
-            let id = dbg.constructed_bezier(cubic_bezier);
-            Ok(EvalValue::bezier(cubic_bezier.clone(), id))
+            let bezier = if shear == 0. {
+                cubic_bezier.clone()
+            } else {
+                cubic_bezier.xform(shear_xform(shear))
+            };
+            let id = dbg.constructed_bezier(&bezier);
+            Ok(EvalValue::bezier(bezier, id))
         }
         OutlineExpr::Spiro(spiro) => {
+            let spiro = if shear == 0. {
+                spiro.clone()
+            } else {
+                shear_spiro(spiro, shear)
+            };
             let id = dbg.constructed_spiro(&spiro.points);
 
             if E::needs_evaluate_intermediate() {
@@ -109,10 +227,10 @@ fn eval_outline_internal<E: EvalTracer>(expr: &OutlineExpr, dbg: &mut E) -> Eval
                 dbg.intermediate_output(id, &bez);
             }
 
-            Ok(EvalValue::spiro(spiro.clone(), id))
+            Ok(EvalValue::spiro(spiro, id))
         }
         OutlineExpr::Stroked(outline_expr, width) => {
-            let evaled = eval_outline_internal(outline_expr, dbg)?;
+            let evaled = eval_outline_internal(outline_expr, shear, dbg)?;
             match evaled.kind {
                 EvalValueKind::Beziers(_) => Err(EvalError::StrokingABezier(evaled.id)),
                 EvalValueKind::Spiros(eval_spiros) => {
@@ -121,18 +239,23 @@ fn eval_outline_internal<E: EvalTracer>(expr: &OutlineExpr, dbg: &mut E) -> Eval
             }
         }
         OutlineExpr::Transformed(expr, xform) => {
-            let evaled = eval_outline_internal(expr, dbg)?;
+            let (inner_shear, xform) = if shear == 0. {
+                (0., *xform)
+            } else {
+                push_shear(xform, shear)
+            };
+            let evaled = eval_outline_internal(expr, inner_shear, dbg)?;
             let (id, bezier) = evaled.force_bezier(dbg)?;
             let flips = xform.flips_direction();
             let xformed = bezier
                 .iter()
                 .map(|x| {
-                    let xformed = x.xform(*xform);
+                    let xformed = x.xform(xform);
                     if flips { xformed.reversed() } else { xformed }
                 })
                 .collect_vec();
 
-            let id = dbg.transformed(id, xform, &xformed);
+            let id = dbg.transformed(id, &xform, &xformed);
             dbg.intermediate_output(id, &xformed);
             Ok(EvalValue {
                 id,
@@ -0,0 +1,496 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use monoxide_ttf::model::{
+    ITable, Outline, TrueTypeTables, WriteOptions,
+    glyf::simple::OutlineFlag,
+    name::{Lang, MSLangID},
+};
+
+use super::{
+    AuxiliarySettings, HighEvalError, SerializedGlyphKind, decompose_glyphs, eval, layout_glyphs,
+    timestamp_from,
+};
+use crate::{
+    EvalSettings,
+    ast::{FontContext, Overlap},
+    dsl::BezierBuilder,
+    line,
+    prelude::*,
+};
+
+#[derive(Debug, Default)]
+struct TestSettings {
+    proportional: bool,
+    bold: bool,
+    slant: f64,
+}
+
+impl EvalSettings for TestSettings {
+    fn mono_width(&self) -> f64 {
+        0.5
+    }
+
+    fn proportional(&self) -> bool {
+        self.proportional
+    }
+
+    fn weight_class(&self) -> u16 {
+        if self.bold { 700 } else { 400 }
+    }
+
+    fn slant(&self) -> f64 {
+        self.slant
+    }
+
+    fn cap_height(&self) -> f64 {
+        0.7
+    }
+
+    fn descender(&self) -> f64 {
+        -0.2
+    }
+
+    fn x_height(&self) -> f64 {
+        0.5
+    }
+}
+
+pub(super) fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> BezierBuilder {
+    BezierBuilder::closed((x0, y0)).insts([
+        line!(x1, y0),
+        line!(x1, y1),
+        line!(x0, y1),
+        line!(x0, y0),
+    ])
+}
+
+/// A font where `A` has both outlines and components, and the unnamed `dot`
+/// is shared between `A` and `B`.
+fn make_font() -> FontContext {
+    make_font_with(TestSettings::default())
+}
+
+fn make_font_with(settings: TestSettings) -> FontContext {
+    let mut cx = FontContext::new(settings);
+    let tofu = Glyph::builder().outline(rect(0.05, 0., 0.45, 0.7)).build();
+    let bar = Glyph::builder()
+        .name("bar")
+        .outline(rect(0.1, 0.3, 0.4, 0.4))
+        .build();
+    let dot = Glyph::builder().outline(rect(0.2, 0., 0.3, 0.1)).build();
+    let a = Glyph::builder()
+        .outline(rect(0.1, 0., 0.2, 0.7))
+        .component(dot.clone())
+        .component(bar)
+        .build();
+    let b = Glyph::builder().component(dot).build();
+
+    cx.set_mapping('B', b);
+    cx.set_mapping('A', a);
+    cx.set_mapping(char::REPLACEMENT_CHARACTER, tofu);
+    cx.set_tofu();
+    cx
+}
+
+/// The TrueType tables of the font evaluated from `cx`.
+pub(super) fn eval_tables(cx: &FontContext, aux: &AuxiliarySettings) -> TrueTypeTables {
+    let font = eval(cx, aux).unwrap();
+    let Outline::TrueType(tables) = font.outline else {
+        panic!("expected TrueType outlines");
+    };
+    tables
+}
+
+#[test]
+fn test_glyph_id_order() {
+    let scx = layout_glyphs(&make_font()).unwrap();
+    let names = scx
+        .glyph_list
+        .iter()
+        .map(|g| g.name.as_deref())
+        .collect::<Vec<_>>();
+    // .notdef, cmap roots, named components, then unnamed ones in
+    // breadth-first order: the outline part of `A`, and `dot`.
+    assert_eq!(
+        names,
+        [
+            Some(".notdef"),
+            Some("A"),
+            Some("B"),
+            Some("bar"),
+            Some("glyph4"),
+            Some("glyph5")
+        ]
+    );
+    assert_eq!(scx.cmap[&'A'], 1);
+    assert_eq!(scx.cmap[&'B'], 2);
+    assert_eq!(scx.cmap[&char::REPLACEMENT_CHARACTER], 0);
+}
+
+#[test]
+fn test_nested_split() {
+    // A component with both outlines and components of its own is split too.
+    let mut cx = make_font();
+    let dot = Glyph::builder().outline(rect(0.2, 0., 0.3, 0.1)).build();
+    let inner = Glyph::builder()
+        .name("inner")
+        .outline(rect(0.1, 0.2, 0.4, 0.3))
+        .component(dot)
+        .build();
+    let c = Glyph::builder().component(inner).build();
+    cx.set_mapping('C', c);
+
+    let scx = layout_glyphs(&cx).unwrap();
+    let inner = (scx.glyph_list.iter())
+        .find(|g| g.name.as_deref() == Some("inner"))
+        .unwrap();
+    let SerializedGlyphKind::Compound(components) = &inner.kind else {
+        panic!("the nested glyph should be compound");
+    };
+    let outline_counts = (components.iter())
+        .map(|c| match &scx.glyph_list[c.index].kind {
+            SerializedGlyphKind::Simple(outlines) => outlines.len(),
+            SerializedGlyphKind::Compound(_) => panic!("components should be simple"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(outline_counts, [1, 1]);
+    eval(&cx, &AuxiliarySettings::default()).unwrap();
+}
+
+#[test]
+fn test_glyph_names() {
+    // Unnamed glyphs are named after their ID, skipping names already taken.
+    let mut cx = make_font();
+    let c = Glyph::builder()
+        .name("glyph5")
+        .outline(rect(0.1, 0., 0.4, 0.7));
+    cx.set_mapping('C', c.build());
+    let scx = layout_glyphs(&cx).unwrap();
+    let names = (scx.glyph_list.iter())
+        .map(|g| g.name.as_deref().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [".notdef", "A", "B", "glyph5", "bar", "glyph5.1", "glyph6"]
+    );
+
+    let mut cx = make_font();
+    let c = Glyph::builder()
+        .name("a-b")
+        .outline(rect(0.1, 0., 0.4, 0.7));
+    cx.set_mapping('C', c.build());
+    assert!(matches!(
+        layout_glyphs(&cx),
+        Err(HighEvalError::InvalidGlyphName(name)) if name == "a-b"
+    ));
+}
+
+#[test]
+fn test_deterministic_output() {
+    let glyf_bytes = || {
+        let tables = eval_tables(&make_font(), &AuxiliarySettings::default());
+        let mut buf = vec![];
+        tables.glyf.write(&mut buf);
+        buf
+    };
+    let first = glyf_bytes();
+    for _ in 0..4 {
+        assert_eq!(glyf_bytes(), first);
+    }
+}
+
+#[test]
+fn test_reproducible_build() {
+    let aux = AuxiliarySettings {
+        timestamp: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        ..Default::default()
+    };
+    let build = || {
+        let mut buf = vec![];
+        eval(&make_font(), &aux)
+            .unwrap()
+            .write(&WriteOptions::default(), &mut buf)
+            .unwrap();
+        buf
+    };
+    let first = build();
+    for _ in 0..4 {
+        assert_eq!(build(), first);
+    }
+
+    // The timestamp set explicitly takes precedence over SOURCE_DATE_EPOCH.
+    assert_eq!(
+        timestamp_from(&aux, Some("1")).unwrap(),
+        aux.timestamp.unwrap()
+    );
+    let aux = AuxiliarySettings::default();
+    assert_eq!(
+        timestamp_from(&aux, Some("1700000000")).unwrap(),
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+    assert_eq!(
+        timestamp_from(&aux, Some(" 42\n")).unwrap(),
+        UNIX_EPOCH + Duration::from_secs(42)
+    );
+    for malformed in ["", "-1", "1.5", "yesterday"] {
+        assert!(matches!(
+            timestamp_from(&aux, Some(malformed)),
+            Err(HighEvalError::InvalidSourceDateEpoch(value)) if value == malformed
+        ));
+    }
+}
+
+#[test]
+fn test_monospace_flags() {
+    let font = eval(&make_font(), &AuxiliarySettings::default()).unwrap();
+    assert!(font.post.is_fixed_pitch);
+    assert_eq!(font.os2.panose_classification.proportion, 9);
+    assert_eq!(font.os2.x_avg_char_width, 1024);
+}
+
+#[test]
+fn test_off_grid_advance() {
+    let wide = Glyph::builder()
+        .outline(rect(0.1, 0., 0.6, 0.7))
+        .advance(0.7)
+        .build();
+
+    let mut cx = make_font();
+    cx.set_mapping('W', wide.clone());
+    let res = eval(&cx, &AuxiliarySettings::default());
+    assert!(matches!(
+        res,
+        Err(HighEvalError::OffGridAdvance { ch: 'W', .. })
+    ));
+
+    let mut cx = make_font_with(TestSettings {
+        proportional: true,
+        ..Default::default()
+    });
+    cx.set_mapping('W', wide);
+    let font = eval(&cx, &AuxiliarySettings::default()).unwrap();
+    assert!(!font.post.is_fixed_pitch);
+    assert_eq!(font.os2.panose_classification.proportion, 3);
+}
+
+#[test]
+fn test_decoration_metrics() {
+    let font = eval(&make_font(), &AuxiliarySettings::default()).unwrap();
+    assert_eq!(font.hhea.ascender, 1638);
+    assert_eq!(font.hhea.descender, -409);
+    assert_eq!(font.post.underline_thickness, 102);
+    assert_eq!(font.post.underline_position, -153);
+    assert_eq!(font.os2.y_strikeout_position, 563);
+    assert!(font.os2.y_subscript_y_offset > 0);
+}
+
+#[test]
+fn test_style_linked_instance() {
+    let cx = make_font_with(TestSettings {
+        bold: true,
+        slant: 10.,
+        ..Default::default()
+    });
+    let aux = AuxiliarySettings {
+        style_name: "Bold Italic".into(),
+        vendor_id: *b"PPST",
+        ..Default::default()
+    };
+    let font = eval(&cx, &aux).unwrap();
+    assert_eq!(font.os2.us_weight_class, 700);
+    assert_eq!(&font.os2.ach_vend_id, b"PPST");
+    let records = &font.name.records[&Lang::Microsoft(MSLangID::en_us)];
+    assert_eq!(
+        records.unique_font_identifier.as_deref(),
+        Some("0.100;PPST;Monoxide-BoldItalic")
+    );
+    assert!(font.os2.fs_selection.contains(
+        monoxide_ttf::model::os2::FsSelectionKind::Bold
+            | monoxide_ttf::model::os2::FsSelectionKind::Italic
+    ));
+    assert_eq!(font.head.mac_style.bits(), 0b11);
+    assert_eq!(font.post.italic_angle, -10);
+    assert_eq!(
+        (font.hhea.caret_slope_rise, font.hhea.caret_slope_run),
+        (2048, 361)
+    );
+    // Slanting pivots around half the x-height, moving the baseline left by
+    // 0.25 * tan(10°), and the top of the tofu right by 0.45 * tan(10°).
+    assert_eq!(font.hhea.caret_offset, -90);
+    assert_eq!(font.head.x_max, 1084);
+    assert_eq!(font.head.x_min, 12);
+}
+
+#[test]
+fn test_stroke_aware_slant() {
+    let mut cx = make_font_with(TestSettings {
+        slant: 45.,
+        ..Default::default()
+    });
+    let stem = Glyph::builder()
+        .outline(
+            SpiroBuilder::open()
+                .insts([corner!(0.25, 0.), corner!(0.25, 0.5)])
+                .stroked(0.1),
+        )
+        .build();
+    cx.set_mapping('I', stem);
+    let ix = layout_glyphs(&cx).unwrap().cmap[&'I'];
+    let tables = eval_tables(&cx, &AuxiliarySettings::default());
+    let bb = tables.glyf.glyphs[ix].common();
+    // The skeleton runs from (0, 0) to (0.5, 0.5). The stroke keeps its width
+    // of 0.1 across the skeleton, so it is 0.1 / cos(45°) wide horizontally,
+    // and both ends are still cut flat.
+    assert_eq!((bb.x_min, bb.x_max), (-144, 1168));
+    assert_eq!((bb.y_min, bb.y_max), (0, 1024));
+}
+
+#[test]
+fn test_component_flags() {
+    use monoxide_ttf::model::glyf::{
+        self,
+        compound::{Args, ComponentFlags, Scale},
+    };
+
+    let dot = Glyph::builder().outline(rect(0.2, 0., 0.3, 0.1)).build();
+    let mark = Glyph::builder()
+        .outline(rect(0.2, 0.6, 0.3, 0.7))
+        .advance(0.)
+        .build();
+    let mut cx = make_font();
+    cx.set_mapping(
+        'E',
+        Glyph::builder()
+            .component(GlyphComponent::from(mark).with_my_metrics())
+            .component(
+                GlyphComponent::from(dot.clone()).with_xform(Affine2D::translated((0., 0.0003))),
+            )
+            .component(
+                GlyphComponent::from(dot.clone()).with_xform(
+                    Affine2D::rotated(std::f64::consts::FRAC_PI_2).translate((0.3, 0.45)),
+                ),
+            )
+            .build(),
+    );
+    let scx = layout_glyphs(&cx).unwrap();
+    let (ix_a, ix_b, ix_e) = (scx.cmap[&'A'], scx.cmap[&'B'], scx.cmap[&'E']);
+    assert_eq!(scx.glyph_list[ix_e].advance, Some(0.));
+
+    let tables = eval_tables(&cx, &AuxiliarySettings::default());
+    let components = |ix: usize| match &tables.glyf.glyphs[ix] {
+        glyf::Glyph::Compound(glyph) => &glyph.components,
+        glyf::Glyph::Simple(_) => panic!("expected a compound glyph"),
+    };
+    // The outline part of `A` is crossed by `bar`.
+    assert!(
+        components(ix_a)[0]
+            .flags
+            .contains(ComponentFlags::OVERLAP_COMPOUND)
+    );
+    assert!(
+        !components(ix_b)[0]
+            .flags
+            .contains(ComponentFlags::OVERLAP_COMPOUND)
+    );
+
+    let [mark, shifted, rotated] = &components(ix_e)[..] else {
+        panic!("expected three components");
+    };
+    assert!(mark.flags.contains(ComponentFlags::USE_MY_METRICS));
+    assert!(!shifted.flags.contains(ComponentFlags::USE_MY_METRICS));
+    // The rotated dot overlaps the mark, but not the shifted dot.
+    assert!(mark.flags.contains(ComponentFlags::OVERLAP_COMPOUND));
+    assert_eq!(shifted.args, Args::Offset { x: 0, y: 1 });
+    let Scale::TwoByTwo { xx, yx, xy, yy } = rotated.scale else {
+        panic!("expected a 2x2 transform");
+    };
+    assert_eq!(
+        [xx, yx, xy, yy].map(|v| v.to_num::<f64>()),
+        [0., -1., 1., 0.]
+    );
+    assert!(
+        rotated
+            .flags
+            .contains(ComponentFlags::UNSCALED_COMPONENT_OFFSET)
+    );
+
+    // Transforms that don't fit in F2Dot14 are decomposed instead.
+    let mut cx = make_font();
+    cx.set_mapping(
+        'S',
+        Glyph::builder()
+            .component(GlyphComponent::from(dot).with_xform(Affine2D::scaled(3.)))
+            .build(),
+    );
+    let ix = layout_glyphs(&cx).unwrap().cmap[&'S'];
+    let tables = eval_tables(&cx, &AuxiliarySettings::default());
+    assert!(matches!(tables.glyf.glyphs[ix], glyf::Glyph::Simple(_)));
+}
+
+#[test]
+fn test_decompose() {
+    let glyphs = |aux: &AuxiliarySettings| eval_tables(&make_font(), aux).glyf.glyphs;
+    let compound = glyphs(&AuxiliarySettings::default());
+    let decomposed = glyphs(&AuxiliarySettings {
+        decompose_components: true,
+        ..Default::default()
+    });
+    // Only .notdef, `A` and `B` are left, with the same bounding boxes.
+    assert_eq!(decomposed.len(), 3);
+    for (compound, decomposed) in compound.iter().zip(&decomposed) {
+        assert!(matches!(
+            decomposed,
+            monoxide_ttf::model::glyf::Glyph::Simple(_)
+        ));
+        let (c, d) = (compound.common(), decomposed.common());
+        assert_eq!(
+            (c.x_min, c.y_min, c.x_max, c.y_max),
+            (d.x_min, d.y_min, d.x_max, d.y_max)
+        );
+    }
+    // The overlap of the outline part of `A` and `bar` is kept.
+    let overlapping = |glyph: &monoxide_ttf::model::glyf::Glyph| match glyph {
+        monoxide_ttf::model::glyf::Glyph::Simple(glyph) => (glyph.flags.first())
+            .is_some_and(|f| f.get_flag().contains(OutlineFlag::OVERLAP_SIMPLE)),
+        monoxide_ttf::model::glyf::Glyph::Compound(_) => panic!("expected a simple glyph"),
+    };
+    assert_eq!(
+        decomposed.iter().map(overlapping).collect::<Vec<_>>(),
+        [false, true, false]
+    );
+
+    // Only `B` is decomposed, and `dot` is still used by `A`.
+    let mut cx = make_font();
+    let b = Glyph::builder()
+        .outline(rect(0.2, 0., 0.3, 0.1))
+        .component(GlyphComponent::from(cx.cmap[&'A'].clone()))
+        .decompose()
+        .build();
+    cx.set_mapping('B', b);
+    let mut scx = layout_glyphs(&cx).unwrap();
+    decompose_glyphs(&mut scx, |g| g.decompose);
+    let names = (scx.glyph_list.iter())
+        .map(|g| g.name.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            Some(".notdef"),
+            Some("A"),
+            Some("B"),
+            Some("bar"),
+            Some("glyph4"),
+            Some("glyph5")
+        ]
+    );
+    assert!(matches!(
+        scx.glyph_list[2].kind,
+        SerializedGlyphKind::Simple(ref outlines) if outlines.len() == 4
+    ));
+    assert_eq!(
+        scx.glyph_list[2].inlined,
+        [(1, Overlap::Auto), (3, Overlap::Auto)]
+    );
+    assert_eq!(scx.glyph_map.edge_count(), 3);
+    assert!(scx.glyph_map.contains_edge(1, 3));
+}
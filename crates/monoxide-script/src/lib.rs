@@ -13,6 +13,36 @@ pub trait EvalSettings: std::fmt::Debug + Send + Sync {
     /// The full width of a half-width monospace character.
     fn mono_width(&self) -> f64;
 
+    /// Whether the font is proportional. Otherwise, the font fails to build
+    /// if any glyph mapped from a character has an advance other than zero
+    /// or [`Self::mono_width`].
+    fn proportional(&self) -> bool {
+        false
+    }
+
+    /// The `OS/2` weight class, from 100 (thin) through 400 (regular) and
+    /// 700 (bold) to 900 (black).
+    fn weight_class(&self) -> u16 {
+        400
+    }
+
+    /// The slant of the glyphs in degrees from the vertical, where positive
+    /// values lean to the right. The outlines are sheared by this angle, and
+    /// the italic angle of the font is set accordingly.
+    ///
+    /// Spiro skeletons are sheared before stroking, so strokes keep their
+    /// widths, see [`eval::eval_outline_sheared`].
+    fn slant(&self) -> f64 {
+        0.0
+    }
+
+    /// The height that keeps its horizontal position when slanting the
+    /// glyphs. Defaults to half the x-height, so that lowercase letters stay
+    /// centered on their advances. The caret is offset to match.
+    fn slant_pivot(&self) -> f64 {
+        self.x_height() / 2.
+    }
+
     /// The cap height (height of capital letters).
     fn cap_height(&self) -> f64;
 
@@ -21,4 +51,104 @@ pub trait EvalSettings: std::fmt::Debug + Send + Sync {
 
     /// The x-height (height of lowercase letters like 'x').
     fn x_height(&self) -> f64;
+
+    /// The typographic ascender. Defaults to one em above the descender.
+    fn ascender(&self) -> f64 {
+        1.0 + self.descender()
+    }
+
+    /// The extra space between lines, in addition to the ascender and
+    /// descender.
+    fn line_gap(&self) -> f64 {
+        0.0
+    }
+
+    /// The top of the underline stroke (usually a negative value).
+    fn underline_position(&self) -> f64 {
+        (self.descender() + self.underline_thickness()) / 2.
+    }
+
+    /// The thickness of the underline stroke.
+    fn underline_thickness(&self) -> f64 {
+        self.stroke_width().unwrap_or(0.05)
+    }
+
+    /// The top of the strikeout stroke.
+    fn strikeout_position(&self) -> f64 {
+        (self.x_height() + self.strikeout_size()) / 2.
+    }
+
+    /// The thickness of the strikeout stroke.
+    fn strikeout_size(&self) -> f64 {
+        self.underline_thickness()
+    }
+
+    /// The size and offset of subscript glyphs synthesized by applications.
+    fn subscript(&self) -> ScriptMetrics {
+        ScriptMetrics {
+            x_size: 0.65,
+            y_size: 0.65,
+            x_offset: 0.0,
+            y_offset: 0.075,
+        }
+    }
+
+    /// The size and offset of superscript glyphs synthesized by
+    /// applications.
+    fn superscript(&self) -> ScriptMetrics {
+        ScriptMetrics {
+            x_size: 0.65,
+            y_size: 0.65,
+            x_offset: 0.0,
+            y_offset: 0.35,
+        }
+    }
+
+    /// How to compute the Windows ascent and descent, which are also the
+    /// clipping box on Windows.
+    fn win_metrics(&self) -> WinMetricsPolicy {
+        WinMetricsPolicy::GlyphBounds
+    }
+
+    /// Whether applications should use the typographic ascender, descender
+    /// and line gap for line spacing, instead of the Windows and `hhea`
+    /// metrics that cover the tallest glyphs.
+    fn use_typo_metrics(&self) -> bool {
+        false
+    }
+
+    /// The primary stroke width, used as the standard stem width when
+    /// hinting. If unset, stems keep their own widths.
+    fn stroke_width(&self) -> Option<f64> {
+        None
+    }
+
+    /// The vertical overshoot of round glyphs beyond the baseline, x-height,
+    /// cap height and descender, used to derive the blue zones when hinting.
+    fn overshoot(&self) -> f64 {
+        0.0
+    }
+}
+
+/// The size and offset of subscript or superscript glyphs, in em units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScriptMetrics {
+    pub x_size: f64,
+    pub y_size: f64,
+    pub x_offset: f64,
+    /// The vertical offset from the baseline. For subscripts, positive values
+    /// move the glyph downwards.
+    pub y_offset: f64,
+}
+
+/// How to compute the Windows ascent and descent (`usWinAscent` and
+/// `usWinDescent` in the `OS/2` table).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WinMetricsPolicy {
+    /// Cover the bounding boxes of all glyphs, so that none is clipped.
+    GlyphBounds,
+    /// Use the typographic ascender and descender. Taller glyphs are clipped.
+    Typographic,
+    /// Use the given ascent and descent in em units. Both are positive.
+    Fixed { ascent: f64, descent: f64 },
 }
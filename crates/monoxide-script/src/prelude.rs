@@ -1,7 +1,7 @@
 pub use monoxide_curves::{point::Point2D, xform::Affine2D};
 
 pub use crate::{
-    ast::{Glyph, GlyphBuilder, OutlineExpr},
+    ast::{Glyph, GlyphBuilder, GlyphComponent, OutlineExpr, Overlap},
     corner, curl,
     dsl::{
         BezierBuilder, IntoOutline, IntoOutlineExt, IntoOutlines, IntoOutlinesExt, SpiroBuilder,
@@ -0,0 +1,373 @@
+//! Textual dumps of font files, so that two builds can be compared with
+//! ordinary text tools.
+//!
+//! The dump is an XML document modelled after the TTX format of fontTools:
+//! one element per table, glyphs referred to by their names, outlines as
+//! absolute points and instructions disassembled. Like [`crate::validate`],
+//! the dump is made from the binary form, so it shows exactly what is
+//! written.
+//!
+//! The table directory and `loca` are left out, as they follow from the other
+//! tables and would only add noise to a diff. Tables without a decoder, or
+//! that fail to decode, are dumped as hexadecimal data.
+
+mod cmap;
+mod glyf;
+mod tables;
+
+use std::{borrow::Cow, collections::HashMap, fmt, fmt::Write as _, io};
+
+use crate::{
+    model::{FontFile, WriteOptions, post::STANDARD_MAC_NAMES},
+    read::{self, font::Malformed, u8_at, u16_at, u32_at},
+};
+
+/// Dump a font as it would be written with the default options.
+pub fn font_file(font: &FontFile) -> io::Result<String> {
+    let mut buf = vec![];
+    font.write(&WriteOptions::default(), &mut buf)?;
+    font_data(&buf)
+}
+
+/// Dump the binary data of a font file.
+///
+/// Only fails if the table directory cannot be read. Malformed tables are
+/// dumped as hexadecimal data instead.
+pub fn font_data(data: &[u8]) -> io::Result<String> {
+    let font = Font::parse(data).map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))?;
+    let mut xml = Xml::default();
+    xml.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.open("ttFont", &[("sfntVersion", &Hex32(font.file.version))]);
+
+    xml.blank();
+    xml.open("GlyphOrder", &[]);
+    for (gid, name) in font.glyph_names.iter().enumerate() {
+        xml.empty("GlyphID", &[("id", &gid), ("name", name)]);
+    }
+    xml.close("GlyphOrder");
+
+    for record in &font.file.records {
+        xml.blank();
+        dump_table(&font, &record.tag, font.file.table_data(record), &mut xml);
+    }
+    xml.close("ttFont");
+    Ok(xml.out)
+}
+
+/// Decodes a table into the given writer, or returns `None` if the table is
+/// malformed.
+type Dumper = fn(&Font<'_>, &[u8], &mut Xml) -> Option<()>;
+
+fn dumper(tag: &[u8; 4]) -> Option<Dumper> {
+    Some(match tag {
+        b"head" => tables::head,
+        b"hhea" => tables::hhea,
+        b"maxp" => tables::maxp,
+        b"OS/2" => tables::os2,
+        b"post" => tables::post,
+        b"hmtx" => tables::hmtx,
+        b"name" => tables::name,
+        b"gasp" => tables::gasp,
+        b"hdmx" => tables::hdmx,
+        b"VDMX" => tables::vdmx,
+        b"DSIG" => tables::dsig,
+        b"cvt " => tables::cvt,
+        b"fpgm" | b"prep" => tables::program,
+        b"loca" => tables::loca,
+        b"glyf" => glyf::dump,
+        b"cmap" => cmap::dump,
+        _ => return None,
+    })
+}
+
+fn dump_table(font: &Font<'_>, tag: &[u8; 4], data: Option<&[u8]>, xml: &mut Xml) {
+    let name = element_name(tag);
+    xml.open(&name, &[]);
+    let Some(data) = data else {
+        xml.comment("The table is out of bounds");
+        xml.close(&name);
+        return;
+    };
+    // Decode into a separate buffer, so nothing is left of a table that turns
+    // out to be malformed halfway through
+    let mut decoded = Xml {
+        out: String::new(),
+        depth: xml.depth,
+    };
+    match dumper(tag).map(|dump| dump(font, data, &mut decoded)) {
+        Some(Some(())) => xml.out.push_str(&decoded.out),
+        Some(None) => {
+            xml.comment("The table is malformed");
+            xml.hexdata(data);
+        }
+        None => xml.hexdata(data),
+    }
+    xml.close(&name);
+}
+
+/// The element name of a table, as in TTX: `OS/2` becomes `OS_2` and `cvt `
+/// becomes `cvt`.
+fn element_name(tag: &[u8; 4]) -> String {
+    let tag = String::from_utf8_lossy(tag);
+    let mut name = (tag.trim_end().chars())
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// A font file with its glyph names.
+struct Font<'a> {
+    file: read::font::Font<'a>,
+    /// The names of the glyphs, made unique.
+    glyph_names: Vec<String>,
+}
+
+impl<'a> Font<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, &'static str> {
+        let file = read::font::Font::parse(data).map_err(|err| match err {
+            Malformed::Truncated => "the table directory ends too early",
+            Malformed::Collection => "font collections are not supported",
+        })?;
+        let mut font = Font {
+            file,
+            glyph_names: vec![],
+        };
+        font.glyph_names = font.read_glyph_names();
+        Ok(font)
+    }
+
+    /// The name of a glyph, which needn't be in the font.
+    fn glyph_name(&self, gid: u32) -> Cow<'_, str> {
+        match self.glyph_names.get(gid as usize) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(default_glyph_name(gid)),
+        }
+    }
+
+    /// Read the glyph names from `post`, falling back to generated names.
+    /// Duplicates are made unique by appending `#1`, `#2` and so on.
+    fn read_glyph_names(&self) -> Vec<String> {
+        let n_glyphs = (self.file.table(b"maxp"))
+            .and_then(|maxp| u16_at(maxp, 4))
+            .unwrap_or(0);
+        let post_names = self.file.table(b"post").and_then(read_post_names);
+        let mut seen = HashMap::<String, usize>::new();
+        (0..n_glyphs as u32)
+            .map(|gid| {
+                let name = (post_names.as_ref())
+                    .and_then(|names| names.get(gid as usize))
+                    .filter(|name| !name.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| default_glyph_name(gid));
+                let count = seen.entry(name.clone()).or_default();
+                *count += 1;
+                match *count {
+                    1 => name,
+                    n => format!("{name}#{}", n - 1),
+                }
+            })
+            .collect()
+    }
+}
+
+fn default_glyph_name(gid: u32) -> String {
+    match gid {
+        0 => ".notdef".into(),
+        _ => format!("glyph{gid:05}"),
+    }
+}
+
+/// Read the glyph names of a version 2.0 `post` table.
+fn read_post_names(post: &[u8]) -> Option<Vec<String>> {
+    if u32_at(post, 0)? != 0x0002_0000 {
+        return None;
+    }
+    let n_glyphs = u16_at(post, 32)? as usize;
+    let indices = (0..n_glyphs)
+        .map(|ix| u16_at(post, 34 + 2 * ix))
+        .collect::<Option<Vec<_>>>()?;
+    let mut pool = vec![];
+    let mut at = 34 + 2 * n_glyphs;
+    while let Some(len) = u8_at(post, at) {
+        let name = post.get(at + 1..at + 1 + len as usize)?;
+        pool.push(String::from_utf8_lossy(name).into_owned());
+        at += 1 + len as usize;
+    }
+    let names = indices.into_iter().map(|ix| match ix as usize {
+        ix if ix < STANDARD_MAC_NAMES.len() => Some(STANDARD_MAC_NAMES[ix].to_owned()),
+        ix => pool.get(ix - STANDARD_MAC_NAMES.len()).cloned(),
+    });
+    // Names missing from the pool are generated instead
+    Some(names.map(Option::unwrap_or_default).collect())
+}
+
+/// An indented XML writer.
+#[derive(Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+type Attrs<'a> = &'a [(&'a str, &'a dyn fmt::Display)];
+
+impl Xml {
+    fn line(&mut self, line: impl fmt::Display) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        writeln!(self.out, "{line}").unwrap();
+    }
+
+    fn tag(&mut self, name: &str, attrs: Attrs<'_>, end: &str) {
+        let mut tag = format!("<{name}");
+        for (key, value) in attrs {
+            write!(tag, r#" {key}="{}""#, escape(&value.to_string())).unwrap();
+        }
+        tag.push_str(end);
+        self.line(tag);
+    }
+
+    fn open(&mut self, name: &str, attrs: Attrs<'_>) {
+        self.tag(name, attrs, ">");
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.line(format_args!("</{name}>"));
+    }
+
+    fn empty(&mut self, name: &str, attrs: Attrs<'_>) {
+        self.tag(name, attrs, "/>");
+    }
+
+    /// A field with a single value.
+    fn value(&mut self, name: &str, value: &dyn fmt::Display) {
+        self.empty(name, &[("value", value)]);
+    }
+
+    fn text(&mut self, text: &str) {
+        self.line(escape(text));
+    }
+
+    fn comment(&mut self, text: &str) {
+        self.line(format_args!("<!-- {} -->", text.replace("--", "- -")));
+    }
+
+    fn blank(&mut self) {
+        self.out.push('\n');
+    }
+
+    /// Data in hexadecimal, in groups of 4 bytes.
+    fn hexdata(&mut self, data: &[u8]) {
+        self.open("hexdata", &[]);
+        for line in data.chunks(32) {
+            let groups = line.chunks(4).map(|group| {
+                (group.iter())
+                    .map(|b| format!("{b:02x}"))
+                    .collect::<String>()
+            });
+            self.line(groups.collect::<Vec<_>>().join(" "));
+        }
+        self.close("hexdata");
+    }
+}
+
+/// Escape text for use in XML, including non-printable characters.
+fn escape(text: &str) -> Cow<'_, str> {
+    let needs_escape = |c: char| matches!(c, '&' | '<' | '>' | '"') || c.is_control();
+    if !text.contains(needs_escape) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c if c.is_control() => write!(escaped, "&#x{:x};", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Formats a `u16` in hexadecimal.
+struct Hex16(u16);
+
+impl fmt::Display for Hex16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+/// Formats a `u32` in hexadecimal.
+struct Hex32(u32);
+
+impl fmt::Display for Hex32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}", self.0)
+    }
+}
+
+#[cfg(test)]
+fn test_dump() -> String {
+    font_file(&crate::validate::test_font()).unwrap()
+}
+
+#[test]
+fn test_dump_font() {
+    let dump = test_dump();
+    assert!(dump.starts_with("<?xml"));
+    assert!(dump.ends_with("</ttFont>\n"));
+    for element in ["<head>", "<OS_2>", "<cmap>", "<glyf>", "<hmtx>", "<name>"] {
+        assert!(dump.contains(element), "{element} is missing");
+    }
+    for line in [
+        r#"<GlyphID id="0" name=".notdef"/>"#,
+        r#"<pt x="50" y="700" on="1"/>"#,
+        r#"<component glyphName="glyph00001" x="0" y="100" flags="0x0000"/>"#,
+        r#"<map code="0x41" name="glyph00001"/>"#,
+        r#"<mtx name="glyph00003" width="500" lsb="0"/>"#,
+        r#"<created value="1970-01-01T00:00:00Z"/>"#,
+    ] {
+        assert!(dump.contains(line), "{line} is missing");
+    }
+    // Dumps are deterministic, so they can be diffed
+    assert_eq!(dump, test_dump());
+}
+
+#[test]
+fn test_dump_malformed() {
+    use crate::read::table_offset;
+
+    let mut data = vec![];
+    (crate::validate::test_font().write(&WriteOptions::default(), &mut data)).unwrap();
+    // Claim more name records than there is room for
+    let name = table_offset(&data, b"name");
+    data[name + 2..name + 4].copy_from_slice(&u16::MAX.to_be_bytes());
+    let dump = font_data(&data).unwrap();
+    assert!(dump.contains("<!-- The table is malformed -->"));
+    // Cut the file off in the middle of `name`
+    let mut truncated = data.clone();
+    truncated.truncate(name + 4);
+    let dump = font_data(&truncated).unwrap();
+    assert!(dump.contains("<!-- The table is out of bounds -->"));
+
+    let mut collection = data.clone();
+    collection[..4].copy_from_slice(b"ttcf");
+    assert!(font_data(&collection).is_err());
+    data.truncate(8);
+    assert!(font_data(&data).is_err());
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(escape("plain"), "plain");
+    assert_eq!(escape(r#"<"a" & b>"#), "&lt;&quot;a&quot; &amp; b&gt;");
+    assert_eq!(escape("\u{1}"), "&#x1;");
+}
@@ -0,0 +1,92 @@
+//! Dump of the `cmap` subtables.
+
+use super::{Font, Xml};
+use crate::read::{
+    cmap::{Malformed, Mappings, read_format4, read_format12, read_format14},
+    u16_at, u32_at,
+};
+
+pub(super) fn dump(font: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let (version, n_records) = (u16_at(data, 0)?, u16_at(data, 2)?);
+    xml.empty("tableVersion", &[("version", &version)]);
+    for ix in 0..n_records as usize {
+        let record = 4 + 8 * ix;
+        let (platform_id, encoding_id) = (u16_at(data, record)?, u16_at(data, record + 2)?);
+        let subtable = data.get(u32_at(data, record + 4)? as usize..)?;
+        let format = u16_at(subtable, 0)?;
+        let element = format!("cmap_format_{format}");
+        let language = match format {
+            4 => u16_at(subtable, 4).map(u32::from),
+            12 => u32_at(subtable, 8),
+            _ => None,
+        };
+        match language {
+            Some(language) => xml.open(
+                &element,
+                &[
+                    ("platformID", &platform_id),
+                    ("platEncID", &encoding_id),
+                    ("language", &language),
+                ],
+            ),
+            None => xml.open(
+                &element,
+                &[("platformID", &platform_id), ("platEncID", &encoding_id)],
+            ),
+        }
+
+        let decoded = match format {
+            4 => read_format4(subtable).map(|mappings| dump_mappings(font, mappings, xml)),
+            12 => read_format12(subtable).map(|mappings| dump_mappings(font, mappings, xml)),
+            14 => read_format14(subtable).map(|mut selectors| {
+                selectors.sort_by_key(|s| s.selector);
+                for s in selectors {
+                    for code in s.default {
+                        xml.empty("map", &[("uv", &Hex(code)), ("uvs", &Hex(s.selector))]);
+                    }
+                    for (code, glyph) in s.non_default {
+                        let name = font.glyph_name(glyph.into());
+                        xml.empty(
+                            "map",
+                            &[
+                                ("uv", &Hex(code)),
+                                ("uvs", &Hex(s.selector)),
+                                ("name", &name),
+                            ],
+                        );
+                    }
+                }
+            }),
+            _ => {
+                xml.comment("The subtable format is not supported");
+                Ok(())
+            }
+        };
+        if let Err(malformed) = decoded {
+            let reason = match malformed {
+                Malformed::Truncated => "the subtable ends too early",
+                Malformed::OutOfBounds => "the subtable refers to data out of bounds",
+                Malformed::UnsortedRanges => "the ranges are not sorted",
+            };
+            xml.comment(&format!("The subtable is malformed: {reason}"));
+        }
+        xml.close(&element);
+    }
+    Some(())
+}
+
+fn dump_mappings(font: &Font<'_>, mappings: Mappings, xml: &mut Xml) {
+    for (code, glyph) in mappings {
+        let name = font.glyph_name(glyph);
+        xml.empty("map", &[("code", &Hex(code)), ("name", &name)]);
+    }
+}
+
+/// Formats a code point in hexadecimal, as short as possible.
+struct Hex(u32);
+
+impl std::fmt::Display for Hex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
@@ -0,0 +1,140 @@
+//! Dump of the glyph outlines in `glyf`.
+
+use super::{Font, Hex16, Xml, tables::assembly};
+use crate::read::{
+    glyf::{
+        ARG_1_AND_2_ARE_WORDS, ARGS_ARE_XY_VALUES, Component, MORE_COMPONENTS, OVERLAP_SIMPLE,
+        Outline, Placement, WE_HAVE_A_SCALE, WE_HAVE_A_TWO_BY_TWO, WE_HAVE_AN_X_AND_Y_SCALE,
+        WE_HAVE_INSTRUCTIONS, loca_offsets, parse_glyph,
+    },
+    i16_at,
+};
+
+/// Component flags that only concern the encoding, and follow from the other
+/// attributes of the component.
+const ENCODING_FLAGS: u16 = ARG_1_AND_2_ARE_WORDS
+    | ARGS_ARE_XY_VALUES
+    | WE_HAVE_A_SCALE
+    | MORE_COMPONENTS
+    | WE_HAVE_AN_X_AND_Y_SCALE
+    | WE_HAVE_A_TWO_BY_TWO
+    | WE_HAVE_INSTRUCTIONS;
+
+pub(super) fn dump(font: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let loca_format = i16_at(font.file.table(b"head")?, 50)?;
+    let offsets = loca_offsets(font.file.table(b"loca")?, loca_format)?;
+    if offsets.len() != font.glyph_names.len() + 1 {
+        return None;
+    }
+    for (gid, range) in offsets.windows(2).enumerate() {
+        let name = font.glyph_name(gid as u32);
+        let Some(glyph_data) = data.get(range[0] as usize..range[1] as usize) else {
+            xml.empty("TTGlyph", &[("name", &name)]);
+            xml.comment("The glyph is out of bounds");
+            continue;
+        };
+        if glyph_data.is_empty() {
+            xml.empty("TTGlyph", &[("name", &name)]);
+            continue;
+        }
+        let glyph = match parse_glyph(glyph_data) {
+            Ok(glyph) => glyph,
+            Err(reason) => {
+                xml.open("TTGlyph", &[("name", &name)]);
+                xml.comment(&format!("The glyph is malformed: {reason}"));
+                xml.hexdata(glyph_data);
+                xml.close("TTGlyph");
+                continue;
+            }
+        };
+
+        let [x_min, y_min, x_max, y_max] = glyph.bbox;
+        xml.open(
+            "TTGlyph",
+            &[
+                ("name", &name),
+                ("xMin", &x_min),
+                ("yMin", &y_min),
+                ("xMax", &x_max),
+                ("yMax", &y_max),
+            ],
+        );
+        match &glyph.outline {
+            Outline::Simple { points, end_points } => {
+                let mut start = 0;
+                for &end in end_points {
+                    xml.open("contour", &[]);
+                    for point in &points[start..=end as usize] {
+                        let on = u8::from(point.on_curve());
+                        if point.flags & OVERLAP_SIMPLE != 0 {
+                            xml.empty(
+                                "pt",
+                                &[
+                                    ("x", &point.x),
+                                    ("y", &point.y),
+                                    ("on", &on),
+                                    ("overlap", &1),
+                                ],
+                            );
+                        } else {
+                            xml.empty("pt", &[("x", &point.x), ("y", &point.y), ("on", &on)]);
+                        }
+                    }
+                    xml.close("contour");
+                    start = end as usize + 1;
+                }
+            }
+            Outline::Compound(components) => {
+                for component in components {
+                    dump_component(font, component, xml);
+                }
+            }
+        }
+        if !glyph.instructions.is_empty() {
+            xml.open("instructions", &[]);
+            if assembly(xml, glyph.instructions).is_none() {
+                xml.comment("The instructions are malformed");
+                xml.hexdata(glyph.instructions);
+            }
+            xml.close("instructions");
+        }
+        xml.close("TTGlyph");
+    }
+    Some(())
+}
+
+fn dump_component(font: &Font<'_>, component: &Component, xml: &mut Xml) {
+    let name = font.glyph_name(component.glyph.into());
+    let mut attrs = vec![("glyphName", name.to_string())];
+    match component.placement {
+        Placement::Offset(x, y) => {
+            attrs.push(("x", x.to_string()));
+            attrs.push(("y", y.to_string()));
+        }
+        Placement::Align(parent, child) => {
+            attrs.push(("firstPt", parent.to_string()));
+            attrs.push(("secondPt", child.to_string()));
+        }
+    }
+    let [xx, xy, yx, yy] = component.matrix;
+    if component.flags & WE_HAVE_A_SCALE != 0 {
+        attrs.push(("scale", xx.to_string()));
+    } else if component.flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+        attrs.push(("scalex", xx.to_string()));
+        attrs.push(("scaley", yy.to_string()));
+    } else if component.flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+        attrs.push(("scalex", xx.to_string()));
+        attrs.push(("scale01", xy.to_string()));
+        attrs.push(("scale10", yx.to_string()));
+        attrs.push(("scaley", yy.to_string()));
+    }
+    attrs.push((
+        "flags",
+        Hex16(component.flags & !ENCODING_FLAGS).to_string(),
+    ));
+
+    let attrs = (attrs.iter())
+        .map(|(key, value)| (*key, value as &dyn std::fmt::Display))
+        .collect::<Vec<_>>();
+    xml.empty("component", &attrs);
+}
@@ -0,0 +1,465 @@
+//! Dumps of the tables with a fixed layout, the metrics, the names and the
+//! programs.
+
+use super::{Font, Hex16, Hex32, Xml};
+use crate::{
+    model::{Fixed, encoding::decode_mac_roman, inst::disassemble},
+    read::{i16_at, i32_at, u8_at, u16_at, u32_at, u64_at},
+};
+
+/// How to decode and format a field.
+#[derive(Clone, Copy)]
+enum Field {
+    U16,
+    I16,
+    U32,
+    /// A 16.16 fixed-point number.
+    F16Dot16,
+    /// A number in hexadecimal.
+    X32,
+    /// Bit flags in binary, most significant bit first.
+    B16,
+    B32,
+    /// A 4-byte tag.
+    Tag,
+    /// The 10-byte PANOSE classification.
+    Panose,
+    /// Seconds since 1904-01-01T00:00:00Z.
+    Date,
+}
+
+use Field::*;
+
+impl Field {
+    fn size(self) -> usize {
+        match self {
+            U16 | I16 | B16 => 2,
+            U32 | F16Dot16 | X32 | B32 | Tag => 4,
+            Date => 8,
+            Panose => 10,
+        }
+    }
+
+    /// Format the field, given exactly its bytes.
+    fn format(self, bytes: &[u8]) -> String {
+        match self {
+            U16 => u16_at(bytes, 0).unwrap().to_string(),
+            I16 => i16_at(bytes, 0).unwrap().to_string(),
+            U32 => u32_at(bytes, 0).unwrap().to_string(),
+            F16Dot16 => Fixed::from_bits(i32_at(bytes, 0).unwrap()).to_string(),
+            X32 => Hex32(u32_at(bytes, 0).unwrap()).to_string(),
+            B16 | B32 => (bytes.iter())
+                .map(|b| format!("{b:08b}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Tag => String::from_utf8_lossy(bytes).into_owned(),
+            Panose => (bytes.iter())
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            Date => format_date(u64_at(bytes, 0).unwrap() as i64),
+        }
+    }
+}
+
+const HEAD: &[(&str, Field)] = &[
+    ("majorVersion", U16),
+    ("minorVersion", U16),
+    ("fontRevision", F16Dot16),
+    ("checkSumAdjustment", X32),
+    ("magicNumber", X32),
+    ("flags", B16),
+    ("unitsPerEm", U16),
+    ("created", Date),
+    ("modified", Date),
+    ("xMin", I16),
+    ("yMin", I16),
+    ("xMax", I16),
+    ("yMax", I16),
+    ("macStyle", B16),
+    ("lowestRecPPEM", U16),
+    ("fontDirectionHint", I16),
+    ("indexToLocFormat", I16),
+    ("glyphDataFormat", I16),
+];
+
+const HHEA: &[(&str, Field)] = &[
+    ("majorVersion", U16),
+    ("minorVersion", U16),
+    ("ascender", I16),
+    ("descender", I16),
+    ("lineGap", I16),
+    ("advanceWidthMax", U16),
+    ("minLeftSideBearing", I16),
+    ("minRightSideBearing", I16),
+    ("xMaxExtent", I16),
+    ("caretSlopeRise", I16),
+    ("caretSlopeRun", I16),
+    ("caretOffset", I16),
+    ("reserved0", I16),
+    ("reserved1", I16),
+    ("reserved2", I16),
+    ("reserved3", I16),
+    ("metricDataFormat", I16),
+    ("numberOfHMetrics", U16),
+];
+
+/// The fields of all versions, of which version 0.5 only has the first two.
+const MAXP: &[(&str, Field)] = &[
+    ("version", X32),
+    ("numGlyphs", U16),
+    ("maxPoints", U16),
+    ("maxContours", U16),
+    ("maxCompositePoints", U16),
+    ("maxCompositeContours", U16),
+    ("maxZones", U16),
+    ("maxTwilightPoints", U16),
+    ("maxStorage", U16),
+    ("maxFunctionDefs", U16),
+    ("maxInstructionDefs", U16),
+    ("maxStackElements", U16),
+    ("maxSizeOfInstructions", U16),
+    ("maxComponentElements", U16),
+    ("maxComponentDepth", U16),
+];
+
+/// The fields of all versions, each version adding to the previous one.
+const OS2: &[(&str, Field)] = &[
+    ("version", U16),
+    ("xAvgCharWidth", I16),
+    ("usWeightClass", U16),
+    ("usWidthClass", U16),
+    ("fsType", B16),
+    ("ySubscriptXSize", I16),
+    ("ySubscriptYSize", I16),
+    ("ySubscriptXOffset", I16),
+    ("ySubscriptYOffset", I16),
+    ("ySuperscriptXSize", I16),
+    ("ySuperscriptYSize", I16),
+    ("ySuperscriptXOffset", I16),
+    ("ySuperscriptYOffset", I16),
+    ("yStrikeoutSize", I16),
+    ("yStrikeoutPosition", I16),
+    ("sFamilyClass", I16),
+    ("panose", Panose),
+    ("ulUnicodeRange1", B32),
+    ("ulUnicodeRange2", B32),
+    ("ulUnicodeRange3", B32),
+    ("ulUnicodeRange4", B32),
+    ("achVendID", Tag),
+    ("fsSelection", B16),
+    ("usFirstCharIndex", U16),
+    ("usLastCharIndex", U16),
+    ("sTypoAscender", I16),
+    ("sTypoDescender", I16),
+    ("sTypoLineGap", I16),
+    ("usWinAscent", U16),
+    ("usWinDescent", U16),
+    ("ulCodePageRange1", B32),
+    ("ulCodePageRange2", B32),
+    ("sxHeight", I16),
+    ("sCapHeight", I16),
+    ("usDefaultChar", U16),
+    ("usBreakChar", U16),
+    ("usMaxContext", U16),
+    ("usLowerOpticalPointSize", U16),
+    ("usUpperOpticalPointSize", U16),
+];
+
+const POST: &[(&str, Field)] = &[
+    ("formatType", X32),
+    ("italicAngle", F16Dot16),
+    ("underlinePosition", I16),
+    ("underlineThickness", I16),
+    ("isFixedPitch", U32),
+    ("minMemType42", U32),
+    ("maxMemType42", U32),
+    ("minMemType1", U32),
+    ("maxMemType1", U32),
+];
+
+const GASP: &[(&str, Field)] = &[("version", U16), ("numRanges", U16)];
+
+const DSIG: &[(&str, Field)] = &[("version", U32), ("numSignatures", U16), ("flags", B16)];
+
+/// Dump consecutive fields starting at `at`, returning where they end.
+///
+/// The dump stops early at the end of the table, for versions of the table
+/// with fewer fields.
+fn fields(xml: &mut Xml, data: &[u8], mut at: usize, fields: &[(&str, Field)]) -> Option<usize> {
+    for &(name, field) in fields {
+        if at == data.len() {
+            break;
+        }
+        let bytes = data.get(at..at + field.size())?;
+        xml.value(name, &field.format(bytes));
+        at += field.size();
+    }
+    Some(at)
+}
+
+/// Dump the data after the decoded fields, if any.
+fn trailing(xml: &mut Xml, data: &[u8], at: usize) {
+    if let Some(rest) = data.get(at..)
+        && !rest.is_empty()
+    {
+        xml.comment("Data after the known fields");
+        xml.hexdata(rest);
+    }
+}
+
+/// Define dumpers for tables made of fields only.
+macro_rules! fields_only {
+    ($($name:ident: $fields:ident),* $(,)?) => {
+        $(
+            pub(super) fn $name(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+                let at = fields(xml, data, 0, $fields)?;
+                trailing(xml, data, at);
+                Some(())
+            }
+        )*
+    };
+}
+
+fields_only! {
+    head: HEAD,
+    hhea: HHEA,
+    maxp: MAXP,
+    os2: OS2,
+    dsig: DSIG,
+}
+
+pub(super) fn post(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let at = fields(xml, data, 0, POST)?;
+    if u32_at(data, 0)? == 0x0002_0000 {
+        xml.comment("The glyph names are listed in GlyphOrder");
+    } else {
+        trailing(xml, data, at);
+    }
+    Some(())
+}
+
+pub(super) fn hmtx(font: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let n_metrics = u16_at(font.file.table(b"hhea")?, 34)? as usize;
+    let n_glyphs = font.glyph_names.len();
+    if n_metrics == 0
+        || n_metrics > n_glyphs
+        || data.len() != 4 * n_metrics + 2 * (n_glyphs - n_metrics)
+    {
+        return None;
+    }
+    for gid in 0..n_glyphs {
+        let (advance, lsb) = if gid < n_metrics {
+            (u16_at(data, 4 * gid)?, i16_at(data, 4 * gid + 2)?)
+        } else {
+            let lsb_at = 4 * n_metrics + 2 * (gid - n_metrics);
+            (u16_at(data, 4 * (n_metrics - 1))?, i16_at(data, lsb_at)?)
+        };
+        let name = font.glyph_name(gid as u32);
+        xml.empty(
+            "mtx",
+            &[("name", &name), ("width", &advance), ("lsb", &lsb)],
+        );
+    }
+    Some(())
+}
+
+pub(super) fn loca(_: &Font<'_>, _: &[u8], xml: &mut Xml) -> Option<()> {
+    xml.comment("The offsets follow from the glyph data in 'glyf'");
+    Some(())
+}
+
+pub(super) fn name(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let (count, storage_offset) = (u16_at(data, 2)?, u16_at(data, 4)? as usize);
+    for ix in 0..count as usize {
+        let [
+            Some(platform_id),
+            Some(encoding_id),
+            Some(language_id),
+            Some(name_id),
+            Some(length),
+            Some(offset),
+        ] = [0, 2, 4, 6, 8, 10].map(|at| u16_at(data, 6 + 12 * ix + at))
+        else {
+            return None;
+        };
+        let start = storage_offset + offset as usize;
+        let bytes = data.get(start..start + length as usize)?;
+        let text = match platform_id {
+            0 | 3 => {
+                let units = (bytes.chunks_exact(2)).map(|ch| u16::from_be_bytes([ch[0], ch[1]]));
+                String::from_utf16_lossy(&units.collect::<Vec<_>>())
+            }
+            1 => decode_mac_roman(bytes),
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        };
+        xml.open(
+            "namerecord",
+            &[
+                ("nameID", &name_id),
+                ("platformID", &platform_id),
+                ("platEncID", &encoding_id),
+                ("langID", &Hex16(language_id)),
+            ],
+        );
+        xml.text(&text);
+        xml.close("namerecord");
+    }
+    Some(())
+}
+
+pub(super) fn gasp(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let mut at = fields(xml, data, 0, GASP)?;
+    for _ in 0..u16_at(data, 2)? {
+        let (max_ppem, behavior) = (u16_at(data, at)?, u16_at(data, at + 2)?);
+        xml.empty(
+            "gaspRange",
+            &[
+                ("rangeMaxPPEM", &max_ppem),
+                ("rangeGaspBehavior", &Hex16(behavior)),
+            ],
+        );
+        at += 4;
+    }
+    trailing(xml, data, at);
+    Some(())
+}
+
+pub(super) fn hdmx(font: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let (version, n_records) = (u16_at(data, 0)?, i16_at(data, 2)?);
+    let record_size = usize::try_from(i32_at(data, 4)?).ok()?;
+    xml.value("version", &version);
+    for ix in 0..n_records.max(0) as usize {
+        let record = data.get(8 + ix * record_size..)?;
+        let (ppem, max_width) = (u8_at(record, 0)?, u8_at(record, 1)?);
+        let widths = record.get(2..2 + font.glyph_names.len())?;
+        xml.open("hdmxData", &[("ppem", &ppem), ("maxWidth", &max_width)]);
+        for row in widths.chunks(16) {
+            let row = row.iter().map(u8::to_string).collect::<Vec<_>>();
+            xml.line(row.join(" "));
+        }
+        xml.close("hdmxData");
+    }
+    Some(())
+}
+
+pub(super) fn vdmx(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    let (version, n_ratios) = (u16_at(data, 0)?, u16_at(data, 4)? as usize);
+    xml.value("version", &version);
+    let offsets = (0..n_ratios)
+        .map(|ix| u16_at(data, 6 + 4 * n_ratios + 2 * ix))
+        .collect::<Option<Vec<_>>>()?;
+    // Ratios may share a group, so groups are numbered by their offset
+    let mut groups = offsets.clone();
+    groups.sort_unstable();
+    groups.dedup();
+
+    for (ix, offset) in offsets.iter().enumerate() {
+        let ratio: [u8; 4] = data.get(6 + 4 * ix..10 + 4 * ix)?.try_into().unwrap();
+        let [char_set, x_ratio, y_start_ratio, y_end_ratio] = ratio;
+        let group = groups.binary_search(offset).unwrap();
+        xml.empty(
+            "ratRange",
+            &[
+                ("bCharSet", &char_set),
+                ("xRatio", &x_ratio),
+                ("yStartRatio", &y_start_ratio),
+                ("yEndRatio", &y_end_ratio),
+                ("group", &group),
+            ],
+        );
+    }
+    for (ix, &offset) in groups.iter().enumerate() {
+        let at = offset as usize;
+        let (n_records, start, end) = (
+            u16_at(data, at)?,
+            u8_at(data, at + 2)?,
+            u8_at(data, at + 3)?,
+        );
+        xml.open(
+            "group",
+            &[("index", &ix), ("startsz", &start), ("endsz", &end)],
+        );
+        for record in (0..n_records as usize).map(|r| at + 4 + 6 * r) {
+            let (y_pel_height, y_max, y_min) = (
+                u16_at(data, record)?,
+                i16_at(data, record + 2)?,
+                i16_at(data, record + 4)?,
+            );
+            xml.empty(
+                "record",
+                &[
+                    ("yPelHeight", &y_pel_height),
+                    ("yMax", &y_max),
+                    ("yMin", &y_min),
+                ],
+            );
+        }
+        xml.close("group");
+    }
+    Some(())
+}
+
+pub(super) fn cvt(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+    for (ix, value) in data.chunks_exact(2).enumerate() {
+        let value = i16::from_be_bytes([value[0], value[1]]);
+        xml.empty("cv", &[("index", &ix), ("value", &value)]);
+    }
+    Some(())
+}
+
+/// Dump `fpgm` or `prep`.
+pub(super) fn program(_: &Font<'_>, data: &[u8], xml: &mut Xml) -> Option<()> {
+    assembly(xml, data)
+}
+
+/// Dump instructions disassembled, one per line.
+pub(super) fn assembly(xml: &mut Xml, code: &[u8]) -> Option<()> {
+    let insts = disassemble(code).ok()?;
+    xml.open("assembly", &[]);
+    for inst in insts {
+        xml.line(inst);
+    }
+    xml.close("assembly");
+    Some(())
+}
+
+/// Format seconds since 1904-01-01T00:00:00Z as an ISO 8601 date and time.
+fn format_date(secs: i64) -> String {
+    // Days from 1904-01-01 to 1970-01-01
+    const UNIX_EPOCH_DAYS: i64 = 24107;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Convert days since 0000-03-01 to a date, so leap days come last
+    let days = days - UNIX_EPOCH_DAYS + 719468;
+    let (era, day_of_era) = (days.div_euclid(146097), days.rem_euclid(146097));
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[test]
+fn test_format_date() {
+    assert_eq!(format_date(0), "1904-01-01T00:00:00Z");
+    assert_eq!(format_date(2082844800), "1970-01-01T00:00:00Z");
+    assert_eq!(
+        format_date(2082844800 + 951782400 + 3661),
+        "2000-02-29T01:01:01Z"
+    );
+}
@@ -0,0 +1,930 @@
+//! Build a complete font file from glyphs, character mappings, metrics and
+//! naming.
+//!
+//! Many fields in a TrueType font are not independent: the bounding box in
+//! `head`, the side bearing statistics in `hhea`, the averages and character
+//! ranges in `OS/2`, and the whole `maxp` and `loca` tables can all be
+//! computed from the glyph data. [`FontBuilder`] derives them so that they
+//! can never disagree with the glyphs actually written.
+
+use std::{collections::BTreeMap, time::SystemTime};
+
+use thiserror::Error;
+
+use super::{
+    cmap::{self as hl_cmap, VariationMapping},
+    device,
+    inst::AnalysisError,
+    loca::glyf_to_loca,
+    maxp::glyf_to_maxp,
+    name::FontNames,
+    os2 as hl_os2,
+};
+use crate::model::{
+    Fixed, FontFile, Outline, RawTable, TrueTypeTables, cmap, cvt, fpgm, fword, gasp, glyf, head,
+    hhea, hmtx, os2, post, prep, ufword,
+};
+
+/// Font-wide vertical metrics and decoration positions, in font units.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub units_per_em: u16,
+    /// Smallest readable size in pixels.
+    pub lowest_rec_ppem: u16,
+    /// Typographic ascender, positive above the baseline.
+    pub ascender: fword,
+    /// Typographic descender, negative below the baseline.
+    pub descender: fword,
+    pub line_gap: fword,
+    /// How to compute the Windows ascent and descent, outside of which glyphs
+    /// are clipped on Windows.
+    pub win_metrics: WinMetrics,
+    /// Whether applications should use the typographic metrics for line
+    /// spacing (`USE_TYPO_METRICS`). The `hhea` metrics then follow the
+    /// typographic ones too, keeping line spacing stable regardless of tall
+    /// glyphs.
+    pub use_typo_metrics: bool,
+    pub x_height: fword,
+    pub cap_height: fword,
+    /// Position of the top of the underline.
+    pub underline_position: fword,
+    pub underline_thickness: fword,
+    /// Position of the top of the strikeout stroke.
+    pub strikeout_position: fword,
+    pub strikeout_size: fword,
+    pub subscript: ScriptMetrics,
+    pub superscript: ScriptMetrics,
+}
+
+/// How to compute `usWinAscent` and `usWinDescent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WinMetrics {
+    /// Cover the typographic box and the bounding boxes of all glyphs, so
+    /// that no glyph is clipped.
+    #[default]
+    GlyphBounds,
+    /// Use the given values. Glyphs outside of them are clipped.
+    Fixed { ascent: ufword, descent: ufword },
+}
+
+/// Size and offset of subscript or superscript glyphs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScriptMetrics {
+    pub x_size: fword,
+    pub y_size: fword,
+    pub x_offset: fword,
+    pub y_offset: fword,
+}
+
+/// PANOSE family kind of Latin text fonts.
+const PANOSE_LATIN_TEXT: u8 = 2;
+/// PANOSE proportion of monospaced Latin text fonts.
+const PANOSE_MONOSPACED: u8 = 9;
+
+/// Subfamily names of the bold and italic members of a style-linked family.
+const BOLD_SUBFAMILIES: &[&str] = &["Bold", "Bold Italic"];
+const ITALIC_SUBFAMILIES: &[&str] = &["Italic", "Bold Italic"];
+
+/// Classification and style fields that cannot be derived from the glyphs.
+///
+/// The regular, bold and italic bits of `fs_selection` and `mac_style` are
+/// overwritten to match the legacy subfamily name (see
+/// [`FontNames::legacy_names`]), so that style linking agrees across tables.
+/// The caret slope follows the italic angle.
+#[derive(Clone)]
+pub struct Style {
+    pub weight_class: u16,
+    pub width_class: u16,
+    pub usage_permission: os2::UsagePermissionKind,
+    pub fs_type: os2::FsTypeUpper,
+    pub family_class: os2::SFamilyClass,
+    pub panose: os2::PanroseClassification,
+    pub vendor_id: [u8; 4],
+    pub fs_selection: os2::FsSelectionKind,
+    pub mac_style: head::MacStyle,
+    /// Italic angle in counter-clockwise degrees from the vertical.
+    pub italic_angle: Fixed,
+    /// Horizontal shift of the caret of slanted fonts, so that it is centered
+    /// on the glyphs.
+    pub caret_offset: i16,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            weight_class: os2::WeightClass::Regular as u16,
+            width_class: os2::WidthClass::Normal as u16,
+            usage_permission: os2::UsagePermissionKind::EditableEmbedding,
+            fs_type: os2::FsTypeUpper::Nothing,
+            family_class: os2::SFamilyClass::NoClassification,
+            panose: os2::PanroseClassification {
+                family_type: 2,
+                serif_style: 0,
+                weight: 5,
+                proportion: 3,
+                contrast: 0,
+                stroke_variation: 0,
+                arm_style: 0,
+                letterform: 0,
+                midline: 0,
+                x_height: 0,
+            },
+            vendor_id: *b"    ",
+            fs_selection: os2::FsSelectionKind::Regular,
+            mac_style: head::MacStyle::REGULAR,
+            italic_angle: Fixed::from_num(0),
+            caret_offset: 0,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("The font contains no glyphs")]
+    NoGlyphs,
+
+    #[error("The font contains {0} glyphs, more than the 65535 allowed")]
+    TooManyGlyphs(usize),
+
+    #[error("Character U+{code:04X} maps to glyph {glyph_id}, which does not exist")]
+    MissingGlyph { code: u32, glyph_id: u32 },
+
+    #[error("{names} glyph names are given for {glyphs} glyphs")]
+    GlyphNameCount { names: usize, glyphs: usize },
+
+    #[error("{0}")]
+    GlyphVerifyError(#[from] glyf::GlyphVerifyError),
+
+    #[error("Failed to analyze TrueType instructions: {0}")]
+    InstructionError(#[from] AnalysisError),
+}
+
+/// A glyph whose bounding box exceeds the typographic ascender or descender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExceedingGlyph {
+    pub glyph_id: u32,
+    pub y_min: fword,
+    pub y_max: fword,
+}
+
+/// Findings about the vertical metrics while building the font.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsReport {
+    /// Glyphs extending beyond the typographic box. These may collide with
+    /// adjacent lines when the typographic metrics are used.
+    pub exceeding_typo_box: Vec<ExceedingGlyph>,
+}
+
+/// Builder for a TrueType [`FontFile`].
+///
+/// Only the glyphs, their advances, the character mappings, the font-wide
+/// [`Metrics`], the [`Style`] and the naming are given. Everything else is
+/// derived in [`FontBuilder::build`].
+pub struct FontBuilder {
+    metrics: Metrics,
+    style: Style,
+    glyphs: Vec<glyf::Glyph>,
+    advances: Vec<ufword>,
+    cmap: BTreeMap<char, u32>,
+    variations: Vec<VariationMapping>,
+    names: FontNames,
+    glyph_names: Option<Vec<String>>,
+    fpgm: Option<fpgm::Table>,
+    prep: Option<prep::Table>,
+    cvt: Option<cvt::Table>,
+    gasp: Option<gasp::Table>,
+    device_ppems: Vec<u8>,
+    timestamp: Option<SystemTime>,
+    extra_tables: Vec<RawTable>,
+}
+
+impl FontBuilder {
+    pub fn new(metrics: Metrics) -> Self {
+        Self {
+            metrics,
+            style: Style::default(),
+            glyphs: vec![],
+            advances: vec![],
+            cmap: BTreeMap::new(),
+            variations: vec![],
+            names: FontNames::new("Untitled", "Regular"),
+            glyph_names: None,
+            fpgm: None,
+            prep: None,
+            cvt: None,
+            gasp: None,
+            device_ppems: vec![],
+            timestamp: None,
+            extra_tables: vec![],
+        }
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Append a glyph with the given advance width. Glyph IDs are assigned in
+    /// the order glyphs are added, starting from 0 (`.notdef`).
+    pub fn glyph(mut self, glyph: glyf::Glyph, advance: ufword) -> Self {
+        self.glyphs.push(glyph);
+        self.advances.push(advance);
+        self
+    }
+
+    /// Append glyphs with their advance widths, see [`Self::glyph`].
+    pub fn glyphs(mut self, glyphs: impl IntoIterator<Item = (glyf::Glyph, ufword)>) -> Self {
+        for (glyph, advance) in glyphs {
+            self = self.glyph(glyph, advance);
+        }
+        self
+    }
+
+    /// Map a character to a glyph ID.
+    pub fn map(mut self, ch: char, glyph_id: u32) -> Self {
+        self.cmap.insert(ch, glyph_id);
+        self
+    }
+
+    /// Map characters to glyph IDs, see [`Self::map`].
+    pub fn cmap(mut self, mappings: impl IntoIterator<Item = (char, u32)>) -> Self {
+        self.cmap.extend(mappings);
+        self
+    }
+
+    /// Set the Unicode variation sequences to write in the `cmap` table.
+    pub fn variations(mut self, variations: impl IntoIterator<Item = VariationMapping>) -> Self {
+        self.variations = variations.into_iter().collect();
+        self
+    }
+
+    /// Set the names of the font, from which the `name` table and the font
+    /// revision are derived.
+    pub fn names(mut self, names: FontNames) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Set the names of all glyphs in glyph ID order, to be written in the
+    /// `post` table.
+    pub fn glyph_names(mut self, names: Vec<String>) -> Self {
+        self.glyph_names = Some(names);
+        self
+    }
+
+    pub fn fpgm(mut self, fpgm: fpgm::Table) -> Self {
+        self.fpgm = Some(fpgm);
+        self
+    }
+
+    pub fn prep(mut self, prep: prep::Table) -> Self {
+        self.prep = Some(prep);
+        self
+    }
+
+    pub fn cvt(mut self, cvt: cvt::Table) -> Self {
+        self.cvt = Some(cvt);
+        self
+    }
+
+    pub fn gasp(mut self, gasp: gasp::Table) -> Self {
+        self.gasp = Some(gasp);
+        self
+    }
+
+    /// Attach a table that is written as is, e.g. a `meta` table or a table
+    /// produced by an external tool. Its tag must not collide with any table
+    /// generated by the builder, or writing the font will fail.
+    pub fn table(mut self, table: RawTable) -> Self {
+        self.extra_tables.push(table);
+        self
+    }
+
+    /// Pixel sizes to precompute the `hdmx` and `VDMX` device metrics at.
+    /// If empty (the default), these tables are omitted.
+    pub fn device_ppems(mut self, ppems: Vec<u8>) -> Self {
+        self.device_ppems = ppems;
+        self
+    }
+
+    /// The creation and modification time recorded in the font. Defaults to
+    /// the current time.
+    pub fn timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn build(self) -> Result<FontFile, BuildError> {
+        self.build_with_report().map(|(font, _)| font)
+    }
+
+    /// Build the font, and report the glyphs exceeding the typographic box.
+    pub fn build_with_report(self) -> Result<(FontFile, MetricsReport), BuildError> {
+        let n_glyphs = self.glyphs.len();
+        if n_glyphs == 0 {
+            return Err(BuildError::NoGlyphs);
+        }
+        if n_glyphs > u16::MAX as usize {
+            return Err(BuildError::TooManyGlyphs(n_glyphs));
+        }
+        let mapped = self.cmap.iter().map(|(&ch, &gid)| (ch as u32, gid));
+        let variants = (self.variations.iter()).filter_map(|v| Some((v.code, v.glyph_id?)));
+        if let Some((code, glyph_id)) = mapped
+            .chain(variants)
+            .find(|&(_, gid)| gid as usize >= n_glyphs)
+        {
+            return Err(BuildError::MissingGlyph { code, glyph_id });
+        }
+        if let Some(names) = &self.glyph_names
+            && names.len() != n_glyphs
+        {
+            return Err(BuildError::GlyphNameCount {
+                names: names.len(),
+                glyphs: n_glyphs,
+            });
+        }
+
+        let glyf = glyf::Table {
+            glyphs: self.glyphs,
+        };
+        glyf.verify()?;
+        let loca = glyf_to_loca(&glyf);
+        let maxp = glyf_to_maxp(&glyf, self.fpgm.as_ref(), self.prep.as_ref())?;
+
+        let stats = GlyphStats::new(&glyf, &self.advances);
+        let hmtx = hmtx_table(&glyf, &self.advances);
+        let hdmx = (!self.device_ppems.is_empty()).then(|| {
+            device::hdmx(
+                &hmtx,
+                n_glyphs,
+                self.metrics.units_per_em,
+                &self.device_ppems,
+            )
+        });
+        let vdmx = (!self.device_ppems.is_empty())
+            .then(|| device::vdmx(&glyf, self.metrics.units_per_em, &self.device_ppems));
+
+        let metrics = &self.metrics;
+        let mut style = self.style;
+        link_style(&mut style, &self.names);
+        let (caret_slope_rise, caret_slope_run) =
+            caret_slope(style.italic_angle, metrics.units_per_em);
+
+        let report = MetricsReport {
+            exceeding_typo_box: (glyf.glyphs.iter().enumerate())
+                .filter(|(_, g)| g.n_points(&glyf.glyphs) > 0)
+                .map(|(ix, g)| (ix, g.common()))
+                .filter(|(_, c)| c.y_max > metrics.ascender || c.y_min < metrics.descender)
+                .map(|(ix, c)| ExceedingGlyph {
+                    glyph_id: ix as u32,
+                    y_min: c.y_min,
+                    y_max: c.y_max,
+                })
+                .collect(),
+        };
+
+        let (win_ascent, win_descent) = match metrics.win_metrics {
+            WinMetrics::GlyphBounds => (
+                metrics.ascender.max(stats.bbox.y_max).max(0) as ufword,
+                (-metrics.descender).max(-stats.bbox.y_min).max(0) as ufword,
+            ),
+            WinMetrics::Fixed { ascent, descent } => (ascent, descent),
+        };
+        let (hhea_ascender, hhea_descender, hhea_line_gap) = if metrics.use_typo_metrics {
+            style.fs_selection |= os2::FsSelectionKind::UseTypoMetrics;
+            (metrics.ascender, metrics.descender, metrics.line_gap)
+        } else {
+            // Match the line spacing on Windows, which uses the win metrics
+            // plus any leading left from the typographic line height.
+            let typo_height =
+                metrics.ascender as i32 - metrics.descender as i32 + metrics.line_gap as i32;
+            let win_height = win_ascent as i32 + win_descent as i32;
+            (
+                win_ascent.min(fword::MAX as ufword) as fword,
+                -(win_descent.min(fword::MAX as ufword) as fword),
+                (typo_height - win_height).clamp(0, fword::MAX as i32) as fword,
+            )
+        };
+
+        // Every left side bearing is taken from the glyph's xMin.
+        let mut flags = head::HeaderFlags::BASELINE_Y0 | head::HeaderFlags::LEFT_SIDEBEARING_X0;
+        if self.prep.is_some() || self.fpgm.is_some() {
+            flags |= head::HeaderFlags::INSTRUCTIONS_DEPEND_ON_POINT_SIZE
+                | head::HeaderFlags::FORCE_PPEM_TO_INT;
+        }
+        let timestamp = self.timestamp.unwrap_or_else(SystemTime::now);
+        let head = head::Table {
+            font_revision: self.names.font_revision().to_bits() as u32,
+            checksum_adjustment: 0,
+            flags,
+            units_per_em: metrics.units_per_em,
+            created: timestamp,
+            modified: timestamp,
+            x_min: stats.bbox.x_min,
+            y_min: stats.bbox.y_min,
+            x_max: stats.bbox.x_max,
+            y_max: stats.bbox.y_max,
+            mac_style: style.mac_style,
+            lowest_rec_ppem: metrics.lowest_rec_ppem,
+            index_to_loc_format: loca.format,
+        };
+
+        let hhea = hhea::Table {
+            ascender: hhea_ascender,
+            descender: hhea_descender,
+            line_gap: hhea_line_gap,
+            advance_width_max: stats.advance_width_max,
+            min_left_side_bearing: stats.min_left_side_bearing,
+            min_right_side_bearing: stats.min_right_side_bearing,
+            x_max_extent: stats.x_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset: style.caret_offset,
+            metric_data_format: 0,
+            number_of_hmetrics: hmtx.metrics.len() as u16,
+        };
+
+        // PANOSE weight and proportion are only defined this way for Latin
+        // text fonts.
+        let mut panose = style.panose;
+        if panose.family_type == PANOSE_LATIN_TEXT {
+            panose.weight = panose_weight(style.weight_class);
+            if stats.is_fixed_pitch {
+                panose.proportion = PANOSE_MONOSPACED;
+            } else if panose.proportion == PANOSE_MONOSPACED {
+                panose.proportion = 0;
+            }
+        }
+        let first_char = self.cmap.keys().next().map_or(0, |&ch| ch as u32);
+        let last_char = self.cmap.keys().next_back().map_or(0, |&ch| ch as u32);
+        let os2 = os2::Table {
+            x_avg_char_width: stats.x_avg_char_width,
+            us_weight_class: style.weight_class,
+            us_width_class: style.width_class,
+            usage_permission: style.usage_permission,
+            fs_type: style.fs_type,
+            y_subscript_x_size: metrics.subscript.x_size,
+            y_subscript_y_size: metrics.subscript.y_size,
+            y_subscript_x_offset: metrics.subscript.x_offset,
+            y_subscript_y_offset: metrics.subscript.y_offset,
+            y_superscript_x_size: metrics.superscript.x_size,
+            y_superscript_y_size: metrics.superscript.y_size,
+            y_superscript_x_offset: metrics.superscript.x_offset,
+            y_superscript_y_offset: metrics.superscript.y_offset,
+            y_strikeout_size: metrics.strikeout_size,
+            y_strikeout_position: metrics.strikeout_position,
+            s_family_class: style.family_class,
+            panose_classification: panose,
+            unicode_range: hl_os2::unicode_range(self.cmap.keys().copied()),
+            ach_vend_id: style.vendor_id,
+            fs_selection: style.fs_selection,
+            first_char_index: first_char.min(0xffff) as u16,
+            last_char_index: last_char.min(0xffff) as u16,
+            s_typo_ascender: metrics.ascender,
+            s_typo_descender: metrics.descender,
+            s_typo_line_gap: metrics.line_gap,
+            us_win_ascent: win_ascent,
+            us_win_descent: win_descent,
+            code_page_range: hl_os2::code_page_range(|ch| self.cmap.contains_key(&ch)),
+            sx_height: metrics.x_height,
+            s_cap_height: metrics.cap_height,
+            us_default_char: 0,
+            us_break_char: 0x20,
+            us_max_context: 0,
+        };
+
+        let post = post::Table {
+            italic_angle: style.italic_angle,
+            underline_position: metrics.underline_position,
+            underline_thickness: metrics.underline_thickness,
+            is_fixed_pitch: stats.is_fixed_pitch,
+            min_mem_type42: 0,
+            max_mem_type42: 0,
+            min_mem_type1: 0,
+            max_mem_type1: 0,
+            glyph_names: self.glyph_names,
+        };
+
+        let cmap = cmap::Table::from_raw(hl_cmap::Table {
+            subtables: vec![hl_cmap::subtable_from_mappings(
+                self.cmap.iter().map(|(&ch, &gid)| (ch as u32, gid)),
+            )],
+            mapping: vec![(hl_cmap::Encoding::Unicode, 0)],
+            variations: self.variations,
+        });
+
+        let font = FontFile {
+            head,
+            hhea,
+            hmtx,
+            cmap,
+            name: self.names.to_table(style.vendor_id),
+            os2,
+            post,
+            outline: Outline::TrueType(TrueTypeTables {
+                glyf,
+                loca,
+                maxp,
+                fpgm: self.fpgm,
+                prep: self.prep,
+                cvt: self.cvt,
+            }),
+            gasp: self.gasp,
+            hdmx,
+            vdmx,
+            dsig: Some(Default::default()),
+            extra_tables: self.extra_tables,
+        };
+        Ok((font, report))
+    }
+}
+
+/// Set the regular, bold and italic bits of `fs_selection` and `mac_style`
+/// from the legacy subfamily name, which is what applications use to link the
+/// four styles of a family.
+fn link_style(style: &mut Style, names: &FontNames) {
+    let (_, subfamily) = names.legacy_names();
+    let bold = BOLD_SUBFAMILIES.contains(&subfamily.as_str());
+    let italic = ITALIC_SUBFAMILIES.contains(&subfamily.as_str());
+
+    let fs_selection = &mut style.fs_selection;
+    fs_selection.remove(
+        os2::FsSelectionKind::Regular | os2::FsSelectionKind::Bold | os2::FsSelectionKind::Italic,
+    );
+    fs_selection.set(os2::FsSelectionKind::Bold, bold);
+    fs_selection.set(os2::FsSelectionKind::Italic, italic);
+    fs_selection.set(os2::FsSelectionKind::Regular, !bold && !italic);
+
+    style.mac_style.set(head::MacStyle::BOLD, bold);
+    style.mac_style.set(head::MacStyle::ITALIC, italic);
+}
+
+/// The caret slope for the italic angle, as rise over run. Upright fonts get
+/// a vertical caret.
+fn caret_slope(italic_angle: Fixed, units_per_em: u16) -> (i16, i16) {
+    if italic_angle == Fixed::ZERO {
+        return (1, 0);
+    }
+    let run = units_per_em as f64 * (-italic_angle.to_num::<f64>()).to_radians().tan();
+    (units_per_em as i16, run.round() as i16)
+}
+
+/// The PANOSE weight (2 for very light to 11 for extra black) closest to the
+/// `OS/2` weight class.
+fn panose_weight(weight_class: u16) -> u8 {
+    (weight_class / 100 + 1).clamp(2, 11) as u8
+}
+
+/// Statistics over the glyph outlines and advances.
+pub(super) struct GlyphStats {
+    pub bbox: glyf::GlyphCommon,
+    pub advance_width_max: ufword,
+    pub min_left_side_bearing: fword,
+    pub min_right_side_bearing: fword,
+    pub x_max_extent: fword,
+    pub x_avg_char_width: fword,
+    /// Whether all glyphs with a non-zero advance share the same advance.
+    pub is_fixed_pitch: bool,
+}
+
+impl GlyphStats {
+    pub(super) fn new(glyf: &glyf::Table, advances: &[ufword]) -> Self {
+        let mut bbox: Option<glyf::GlyphCommon> = None;
+        let mut min_lsb = fword::MAX;
+        let mut min_rsb = fword::MAX;
+        let mut x_max_extent = fword::MIN;
+
+        // Glyphs without outlines don't count towards the extents.
+        let outlined = glyf
+            .glyphs
+            .iter()
+            .zip(advances)
+            .filter(|(g, _)| g.n_points(&glyf.glyphs) > 0);
+        for (glyph, &advance) in outlined {
+            let c = glyph.common();
+            let b = bbox.get_or_insert_with(|| c.clone());
+            b.x_min = b.x_min.min(c.x_min);
+            b.y_min = b.y_min.min(c.y_min);
+            b.x_max = b.x_max.max(c.x_max);
+            b.y_max = b.y_max.max(c.y_max);
+
+            min_lsb = min_lsb.min(c.x_min);
+            min_rsb = min_rsb.min((advance as i32 - c.x_max as i32) as fword);
+            x_max_extent = x_max_extent.max(c.x_max);
+        }
+
+        let (sum, count) = advances
+            .iter()
+            .filter(|&&a| a != 0)
+            .fold((0u64, 0u64), |(sum, count), &a| (sum + a as u64, count + 1));
+        let x_avg_char_width = (sum + count / 2).checked_div(count).unwrap_or(0) as fword;
+
+        let mut widths = advances.iter().filter(|&&a| a != 0);
+        let is_fixed_pitch = widths
+            .next()
+            .is_some_and(|&first| widths.all(|&a| a == first));
+
+        let has_outlines = bbox.is_some();
+        Self {
+            bbox: bbox.unwrap_or_default(),
+            advance_width_max: advances.iter().copied().max().unwrap_or(0),
+            min_left_side_bearing: if has_outlines { min_lsb } else { 0 },
+            min_right_side_bearing: if has_outlines { min_rsb } else { 0 },
+            x_max_extent: if has_outlines { x_max_extent } else { 0 },
+            x_avg_char_width,
+            is_fixed_pitch,
+        }
+    }
+}
+
+/// Generate the `hmtx` table, omitting advances repeated at the end.
+pub(super) fn hmtx_table(glyf: &glyf::Table, advances: &[ufword]) -> hmtx::Table {
+    let last = *advances.last().expect("at least one glyph");
+    let n_metrics = advances
+        .iter()
+        .rposition(|&a| a != last)
+        .map_or(1, |ix| ix + 2);
+
+    let lsb = |g: &glyf::Glyph| {
+        if g.n_points(&glyf.glyphs) > 0 {
+            g.common().x_min
+        } else {
+            0
+        }
+    };
+    let metrics = glyf.glyphs[..n_metrics]
+        .iter()
+        .zip(advances)
+        .map(|(g, &advance_width)| hmtx::LongHorizontalMetric {
+            advance_width,
+            left_side_bearing: lsb(g),
+        })
+        .collect();
+    let left_side_bearings = glyf.glyphs[n_metrics..].iter().map(lsb).collect();
+    hmtx::Table {
+        metrics,
+        left_side_bearings,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_rect(x0: fword, y0: fword, x1: fword, y1: fword) -> glyf::Glyph {
+    let mut outline = monoxide_curves::QuadBezier::builder((x0, y0));
+    outline
+        .line_to((x0, y1))
+        .line_to((x1, y1))
+        .line_to((x1, y0))
+        .close();
+    glyf::Glyph::Simple(super::glyf::encode(&[outline.build()]).unwrap())
+}
+
+#[test]
+fn test_derived_fields() {
+    use crate::model::glyf::simple::SimpleGlyph;
+
+    let font = FontBuilder::new(test_metrics())
+        .glyph(test_rect(50, 0, 450, 700), 500)
+        .glyph(test_rect(100, -100, 550, 600), 600)
+        .glyph(glyf::Glyph::Simple(SimpleGlyph::default()), 600)
+        .map('A', 1)
+        .map(' ', 2)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        (
+            font.head.x_min,
+            font.head.y_min,
+            font.head.x_max,
+            font.head.y_max
+        ),
+        (50, -100, 550, 700)
+    );
+    assert_eq!(font.hhea.advance_width_max, 600);
+    assert_eq!(font.hhea.min_left_side_bearing, 50);
+    assert_eq!(font.hhea.min_right_side_bearing, 50);
+    assert_eq!(font.hhea.x_max_extent, 550);
+    assert_eq!(font.hhea.number_of_hmetrics, 2);
+    assert_eq!(font.hmtx.left_side_bearings, vec![0]);
+    assert_eq!(font.os2.x_avg_char_width, 567);
+    assert!(!font.post.is_fixed_pitch);
+    assert_eq!(font.os2.panose_classification.proportion, 3);
+    assert_eq!(
+        (font.os2.first_char_index, font.os2.last_char_index),
+        (0x20, 0x41)
+    );
+
+    let err = FontBuilder::new(test_metrics()).map('A', 0).build();
+    assert!(matches!(err, Err(BuildError::NoGlyphs)));
+}
+
+#[cfg(test)]
+pub(crate) fn test_metrics() -> Metrics {
+    Metrics {
+        units_per_em: 1000,
+        lowest_rec_ppem: 8,
+        ascender: 800,
+        descender: -200,
+        line_gap: 0,
+        win_metrics: WinMetrics::GlyphBounds,
+        use_typo_metrics: false,
+        x_height: 500,
+        cap_height: 700,
+        underline_position: -100,
+        underline_thickness: 50,
+        strikeout_position: 250,
+        strikeout_size: 50,
+        subscript: Default::default(),
+        superscript: Default::default(),
+    }
+}
+
+#[test]
+fn test_win_metrics() {
+    let build = |use_typo_metrics| {
+        FontBuilder::new(Metrics {
+            use_typo_metrics,
+            ..test_metrics()
+        })
+        .glyph(test_rect(50, 0, 450, 700), 500)
+        .glyph(test_rect(50, -300, 450, 900), 500)
+        .build_with_report()
+        .unwrap()
+    };
+
+    let (font, report) = build(false);
+    assert_eq!(
+        (font.os2.us_win_ascent, font.os2.us_win_descent),
+        (900, 300)
+    );
+    assert_eq!((font.hhea.ascender, font.hhea.descender), (900, -300));
+    assert_eq!(font.hhea.line_gap, 0);
+    assert!(
+        !font
+            .os2
+            .fs_selection
+            .contains(os2::FsSelectionKind::UseTypoMetrics)
+    );
+    assert_eq!(
+        report.exceeding_typo_box,
+        [ExceedingGlyph {
+            glyph_id: 1,
+            y_min: -300,
+            y_max: 900
+        }]
+    );
+
+    let (font, _) = build(true);
+    assert_eq!(
+        (font.os2.us_win_ascent, font.os2.us_win_descent),
+        (900, 300)
+    );
+    assert_eq!((font.hhea.ascender, font.hhea.descender), (800, -200));
+    assert!(
+        font.os2
+            .fs_selection
+            .contains(os2::FsSelectionKind::UseTypoMetrics)
+    );
+}
+
+#[test]
+fn test_style_linking() {
+    let build = |style_name: &str, weight_class, italic_angle: f64| {
+        FontBuilder::new(test_metrics())
+            .style(Style {
+                weight_class,
+                italic_angle: Fixed::from_num(italic_angle),
+                fs_selection: os2::FsSelectionKind::Regular | os2::FsSelectionKind::WWS,
+                ..Default::default()
+            })
+            .names(FontNames::new("Test", style_name))
+            .glyph(test_rect(50, 0, 450, 700), 500)
+            .build()
+            .unwrap()
+    };
+
+    let font = build("Regular", 400, 0.);
+    assert_eq!(
+        font.os2.fs_selection.bits(),
+        (os2::FsSelectionKind::Regular | os2::FsSelectionKind::WWS).bits()
+    );
+    assert_eq!(font.head.mac_style.bits(), 0);
+    assert_eq!(
+        (font.hhea.caret_slope_rise, font.hhea.caret_slope_run),
+        (1, 0)
+    );
+    assert_eq!(font.os2.panose_classification.weight, 5);
+
+    let font = build("Bold Italic", 700, -10.);
+    assert_eq!(
+        font.os2.fs_selection.bits(),
+        (os2::FsSelectionKind::Bold | os2::FsSelectionKind::Italic | os2::FsSelectionKind::WWS)
+            .bits()
+    );
+    assert_eq!(
+        font.head.mac_style.bits(),
+        (head::MacStyle::BOLD | head::MacStyle::ITALIC).bits()
+    );
+    assert_eq!(font.post.italic_angle, Fixed::from_num(-10.));
+    assert_eq!(
+        (font.hhea.caret_slope_rise, font.hhea.caret_slope_run),
+        (1000, 176)
+    );
+    assert_eq!(font.os2.panose_classification.weight, 8);
+
+    // Light Italic is linked as the italic of the Light family.
+    let font = build("Light Italic", 300, -10.);
+    assert_eq!(
+        font.os2.fs_selection.bits(),
+        (os2::FsSelectionKind::Italic | os2::FsSelectionKind::WWS).bits()
+    );
+    assert_eq!(font.head.mac_style.bits(), head::MacStyle::ITALIC.bits());
+}
+
+#[test]
+fn test_extra_tables() {
+    use crate::{model::WriteOptions, validate::DiagnosticKind};
+
+    let build = |tables: &[RawTable]| {
+        let builder = FontBuilder::new(test_metrics()).glyph(test_rect(50, 0, 450, 700), 500);
+        let font = (tables.iter().cloned()).fold(builder, FontBuilder::table);
+        let mut buf = vec![];
+        font.build()?.write(&WriteOptions::default(), &mut buf)?;
+        Ok::<_, Box<dyn std::error::Error>>(buf)
+    };
+
+    let meta = RawTable::new(*b"meta", &b"\0\0\0\x01\0\0\0\0\0\0\0\x1c\0\0\0\0\x01"[..]);
+    let buf = build(std::slice::from_ref(&meta)).unwrap();
+    let font = crate::read::font::Font::parse(&buf).unwrap();
+    assert_eq!(font.table(&meta.tag), Some(&meta.data[..]));
+    // The checksum of the table is recorded correctly
+    let diagnostics = crate::validate::font_data(&buf);
+    assert!(!(diagnostics.iter()).any(|d| matches!(d.kind, DiagnosticKind::Checksum { .. })));
+
+    let invalid = [
+        vec![RawTable::new(*b"name", vec![])],
+        vec![meta.clone(), meta],
+        vec![RawTable::new(*b" abc", vec![])],
+    ];
+    for tables in invalid {
+        let err = build(&tables).unwrap_err();
+        let err = err.downcast::<std::io::Error>().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_table_count() {
+    use crate::model::WriteOptions;
+
+    let write = |n_extra: usize| {
+        let tags = (0..n_extra).map(|ix| format!("x{ix:03x}").into_bytes().try_into().unwrap());
+        let builder = FontBuilder::new(test_metrics()).glyph(test_rect(50, 0, 450, 700), 500);
+        let font = tags.fold(builder, |b, tag| b.table(RawTable::new(tag, vec![])));
+        let mut buf = vec![];
+        font.build()
+            .unwrap()
+            .write(&WriteOptions::default(), &mut buf)?;
+        Ok::<_, std::io::Error>(buf)
+    };
+
+    let buf = write(0).unwrap();
+    let n_builtin = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+    // 4095 tables is the most where `numTables * 16` fits in u16.
+    let buf = write(4095 - n_builtin).unwrap();
+    let u16_at = |at: usize| u16::from_be_bytes([buf[at], buf[at + 1]]);
+    assert_eq!(
+        [4, 6, 8, 10].map(u16_at),
+        [4095, 2048 * 16, 11, 4095 * 16 - 2048 * 16]
+    );
+    let err = write(4096 - n_builtin).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_post_glyph_names() {
+    use crate::model::{WriteOptions, post};
+
+    let font = FontBuilder::new(test_metrics())
+        .glyph(test_rect(50, 0, 450, 700), 500)
+        .glyph_names(vec![".notdef".into()])
+        .build()
+        .unwrap();
+    let post_version = |options: &WriteOptions| {
+        let mut buf = vec![];
+        font.write(options, &mut buf).unwrap();
+        let offset = crate::read::table_offset(&buf, b"post");
+        crate::read::u32_at(&buf, offset).unwrap()
+    };
+
+    assert_eq!(
+        post_version(&WriteOptions::default()),
+        post::Table::VERSION_2
+    );
+    let options = WriteOptions {
+        glyph_names: false,
+        ..Default::default()
+    };
+    assert_eq!(post_version(&options), post::Table::VERSION_3);
+    assert!(font.post.glyph_names.is_some());
+}
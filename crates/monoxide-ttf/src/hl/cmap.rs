@@ -20,6 +20,41 @@ pub struct SeqMapping {
 /// character mappings.
 pub type SubTable = Vec<SeqMapping>;
 
+/// Build a subtable from `(code, glyph_id)` pairs sorted by code, collapsing
+/// consecutive codes mapped to consecutive glyph IDs into a single run.
+pub fn subtable_from_mappings(mappings: impl IntoIterator<Item = (u32, u32)>) -> SubTable {
+    let mut subtable = SubTable::new();
+    for (code, glyph_id) in mappings {
+        if let Some(last) = subtable.last_mut()
+            && last.start_code + last.len == code
+            && last.glyph_id + last.len == glyph_id
+        {
+            last.len += 1;
+        } else {
+            subtable.push(SeqMapping {
+                start_code: code,
+                len: 1,
+                glyph_id,
+            });
+        }
+    }
+    subtable
+}
+
+/// A Unicode variation sequence, i.e. a base character followed by a
+/// variation selector, mapped to a glyph.
+pub struct VariationMapping {
+    pub code: u32,
+    pub selector: u32,
+    /// The glyph to display, or `None` if the sequence should be displayed
+    /// with the default glyph of `code` in the Unicode subtable.
+    pub glyph_id: Option<u32>,
+}
+
+/// The variation sequences of a font. They are written as a single
+/// [`Encoding::UnicodeVariations`] subtable.
+pub type VariationSubTable = Vec<VariationMapping>;
+
 /// A high-level representation of the encoding of a subtable.
 /// This encoding will be mapped in a one-to-many relationship with the
 /// actual OpenType platform and encoding IDs.
@@ -31,7 +66,8 @@ pub enum Encoding {
     /// Microsoft platforms.
     Unicode,
 
-    /// Unicode variation sequences. Unsupported for now.
+    /// Unicode variation sequences. These are stored separately in
+    /// [`Table::variations`] instead of in the subtable list.
     UnicodeVariations,
 
     /// Windows Symbol encoding.
@@ -91,6 +127,8 @@ impl Encoding {
         }
     }
 
+    /// The encoding record for this encoding, that should be used in the
+    /// format 12 subtable.
     pub fn to_encoding_records_fmt12(&self) -> &'static [EncodingRecord] {
         match self {
             Encoding::Unicode => &[
@@ -112,6 +150,18 @@ impl Encoding {
             Encoding::Johab => &[],
         }
     }
+
+    /// The encoding record for this encoding, that should be used in the
+    /// format 14 subtable.
+    pub fn to_encoding_records_fmt14(&self) -> &'static [EncodingRecord] {
+        match self {
+            Encoding::UnicodeVariations => &[EncodingRecord {
+                platform_id: PlatformId::Unicode,
+                encoding_id: UnicodePlatformEncoding::VariationSequences as u16,
+            }],
+            _ => &[],
+        }
+    }
 }
 
 /// A full-fledged `cmap` table is a list of subtables, each for a specific
@@ -126,4 +176,17 @@ pub struct Table {
     /// subtable, e.g. Microsoft and Unicode both have UCS-2 tables and they
     /// can be shared.
     pub mapping: Vec<(Encoding, usize)>,
+
+    /// The Unicode variation sequences, if any.
+    pub variations: VariationSubTable,
+}
+
+#[test]
+fn test_subtable_from_mappings() {
+    let subtable = subtable_from_mappings([(0x30, 1), (0x31, 2), (0x32, 3), (0x34, 4), (0x35, 4)]);
+    let runs = subtable
+        .iter()
+        .map(|m| (m.start_code, m.len, m.glyph_id))
+        .collect::<Vec<_>>();
+    assert_eq!(runs, [(0x30, 3, 1), (0x34, 1, 4), (0x35, 1, 4)]);
 }
@@ -0,0 +1,120 @@
+//! Generate the device tables (`hdmx` and `VDMX`) from scaled glyph metrics.
+//!
+//! The values are computed by scaling the metrics in font units to each pixel
+//! size, rounding outwards where clipping could happen. This matches the
+//! rasterizer output as long as the instructions don't move points beyond
+//! the glyph bounding boxes or alter advance widths, which is the case for
+//! the instructions generated by `monoxide`.
+
+use crate::model::{glyf, hdmx, hmtx, vdmx};
+
+/// Generate the `hdmx` table at the given pixel sizes.
+pub fn hdmx(hmtx: &hmtx::Table, n_glyphs: usize, units_per_em: u16, ppems: &[u8]) -> hdmx::Table {
+    let mut ppems = ppems.to_vec();
+    ppems.sort_unstable();
+    ppems.dedup();
+
+    let upem = units_per_em as f64;
+    let records = ppems
+        .into_iter()
+        .map(|ppem| hdmx::DeviceRecord {
+            pixel_size: ppem,
+            widths: (0..n_glyphs)
+                .map(|ix| {
+                    let advance = advance_width(hmtx, ix) as f64;
+                    (advance * ppem as f64 / upem).round().min(u8::MAX as f64) as u8
+                })
+                .collect(),
+        })
+        .collect();
+    hdmx::Table { records }
+}
+
+/// Generate the `VDMX` table at the given pixel heights, with a single group
+/// that applies to all aspect ratios.
+pub fn vdmx(glyf: &glyf::Table, units_per_em: u16, ppems: &[u8]) -> vdmx::Table {
+    let mut ppems = ppems.to_vec();
+    ppems.sort_unstable();
+    ppems.dedup();
+
+    let y_max = glyf
+        .glyphs
+        .iter()
+        .map(|g| g.common().y_max)
+        .max()
+        .unwrap_or(0);
+    let y_min = glyf
+        .glyphs
+        .iter()
+        .map(|g| g.common().y_min)
+        .min()
+        .unwrap_or(0);
+
+    let upem = units_per_em as f64;
+    let records = ppems
+        .into_iter()
+        .map(|ppem| {
+            let scale = ppem as f64 / upem;
+            vdmx::VTableRecord {
+                y_pel_height: ppem as u16,
+                y_max: (y_max as f64 * scale).ceil() as i16,
+                y_min: (y_min as f64 * scale).floor() as i16,
+            }
+        })
+        .collect();
+    vdmx::Table {
+        groups: vec![vdmx::Group { records }],
+        ratios: vec![(vdmx::RatioRange::ALL, 0)],
+    }
+}
+
+/// The advance width of a glyph. Glyphs beyond the long metrics share the
+/// advance of the last one.
+pub(super) fn advance_width(hmtx: &hmtx::Table, ix: usize) -> u16 {
+    hmtx.metrics
+        .get(ix)
+        .or(hmtx.metrics.last())
+        .map_or(0, |m| m.advance_width)
+}
+
+#[test]
+fn test_device_metrics() {
+    use crate::model::hmtx::LongHorizontalMetric;
+
+    let metric = |advance_width| LongHorizontalMetric {
+        advance_width,
+        left_side_bearing: 0,
+    };
+    // The third glyph shares the advance of the last long metric.
+    let hmtx = hmtx::Table {
+        metrics: vec![metric(500), metric(1000)],
+        left_side_bearings: vec![0],
+    };
+    let table = hdmx(&hmtx, 3, 1000, &[12, 9, 12]);
+    let records = (table.records.iter())
+        .map(|r| (r.pixel_size, r.max_width(), r.widths.clone()))
+        .collect::<Vec<_>>();
+    // 4.5 pixels round up to 5
+    assert_eq!(records, [(9, 9, vec![5, 9, 9]), (12, 12, vec![6, 12, 12])]);
+
+    let rect = |y0, y1| {
+        let mut outline = monoxide_curves::QuadBezier::builder((0, y0));
+        outline
+            .line_to((0, y1))
+            .line_to((500, y1))
+            .line_to((500, y0))
+            .line_to((0, y0))
+            .close();
+        glyf::Glyph::Simple(crate::hl::glyf::encode(&[outline.build()]).unwrap())
+    };
+    let glyf = glyf::Table {
+        glyphs: vec![rect(0, 700), rect(-200, 750)],
+    };
+    let table = vdmx(&glyf, 1000, &[12, 9]);
+    let records = (table.groups[0].records.iter())
+        .map(|r| (r.y_pel_height, r.y_max, r.y_min))
+        .collect::<Vec<_>>();
+    // Rounded outwards: 6.75 up to 7, -1.8 down to -2 at 9 ppem
+    assert_eq!(records, [(9, 7, -2), (12, 9, -3)]);
+    assert_eq!(table.ratios.len(), 1);
+}
@@ -4,7 +4,10 @@ use monoxide_curves::QuadBezier;
 
 use crate::model::{
     fword,
-    glyf::simple::{Coord, FlagOrRepeat, OutlineFlag, SimpleGlyph, SimpleGlyphVerifyError},
+    glyf::{
+        GlyphCommon,
+        simple::{Coord, FlagOrRepeat, OutlineFlag, SimpleGlyph, SimpleGlyphVerifyError},
+    },
 };
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -20,6 +23,14 @@ pub fn encode(outlines: &[QuadBezier<(fword, fword)>]) -> Result<SimpleGlyph, Co
         return Ok(glyph_data);
     }
 
+    // The bounding box starts empty rather than at the origin.
+    glyph_data.common = GlyphCommon {
+        x_min: fword::MAX,
+        y_min: fword::MAX,
+        x_max: fword::MIN,
+        y_max: fword::MIN,
+    };
+
     let mut last_x: fword = 0;
     let mut last_y: fword = 0;
     let mut raw_flags = Vec::new();
@@ -57,6 +68,10 @@ pub fn encode(outlines: &[QuadBezier<(fword, fword)>]) -> Result<SimpleGlyph, Co
             .push(raw_flags.len() as u16 - 1);
     }
 
+    if raw_flags.is_empty() {
+        return Ok(SimpleGlyph::default());
+    }
+
     // Simplify flags by using repeat flag
     let mut new_flags = Vec::new();
     let mut last_flag = raw_flags[0];
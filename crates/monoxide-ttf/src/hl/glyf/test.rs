@@ -1,5 +1,8 @@
 #![cfg(test)]
-use crate::hl::glyf::{QuadBezier, decode, encode};
+use crate::{
+    hl::glyf::{QuadBezier, decode, encode},
+    model::glyf::simple::OutlineFlag,
+};
 
 #[test]
 fn test_points_iter_1() {
@@ -100,3 +103,45 @@ fn test_encode_decode_large_coordinates() {
 
     assert_eq!(outlines, decoded, "Encode-decode test failed.");
 }
+
+#[test]
+fn test_overlap_simple() {
+    use crate::{
+        model::glyf::simple::FlagOrRepeat,
+        read::glyf::{OVERLAP_SIMPLE, Outline, parse_glyph},
+    };
+
+    let mut square = QuadBezier::builder((0, 0));
+    square
+        .line_to((100, 0))
+        .line_to((100, 100))
+        .line_to((0, 100))
+        .line_to((0, 0))
+        .close();
+    let outlines = vec![square.build()];
+
+    let mut glyph = encode(&outlines).unwrap();
+    glyph.set_overlap_simple();
+    glyph.verify().unwrap();
+    // Only the first point is marked, even if its flag was repeated.
+    let flags = (glyph.flags.iter())
+        .flat_map(|f| std::iter::repeat_n(f.get_flag(), f.get_repeat_times()))
+        .collect::<Vec<_>>();
+    assert_eq!(flags.len(), 4);
+    assert!(flags[0].contains(OutlineFlag::OVERLAP_SIMPLE));
+    assert!(
+        !flags[1..]
+            .iter()
+            .any(|f| f.contains(OutlineFlag::OVERLAP_SIMPLE))
+    );
+    assert!(matches!(glyph.flags[0], FlagOrRepeat::Single(_)));
+    assert_eq!(decode(&glyph).unwrap(), outlines);
+
+    let mut buf = vec![];
+    glyph.write(&mut buf);
+    let Outline::Simple { points, .. } = parse_glyph(&buf).unwrap().outline else {
+        panic!("expected a simple glyph");
+    };
+    let overlap = points.iter().map(|p| p.flags & OVERLAP_SIMPLE != 0);
+    assert_eq!(overlap.collect::<Vec<_>>(), [true, false, false, false]);
+}
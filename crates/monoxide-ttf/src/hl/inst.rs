@@ -0,0 +1,493 @@
+//! Static analysis of TrueType instructions.
+//!
+//! The `maxp` table needs to declare the resources used by all programs in the
+//! font, e.g. the maximum stack depth and the number of functions. These are
+//! computed by running the programs on an abstract interpreter that only
+//! tracks values known at compile time (i.e. those pushed by the program
+//! itself).
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::model::inst::{DisassembleError, Instruction, StackEffect, disassemble};
+
+/// Maximum nesting of function calls before giving up on the analysis.
+const MAX_CALL_DEPTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum AnalysisError {
+    #[error("{0}")]
+    Disassemble(#[from] DisassembleError),
+    #[error("Stack underflow in {0}")]
+    StackUnderflow(&'static str),
+    #[error("The operand of {0} cannot be determined statically")]
+    UnknownOperand(&'static str),
+    #[error("Unmatched {0}")]
+    UnmatchedControlFlow(&'static str),
+    #[error("The branches of an IF leave the stack at different depths ({0} and {1})")]
+    UnbalancedBranches(usize, usize),
+    #[error("Call to undefined function {0}")]
+    UndefinedFunction(i32),
+    #[error("Function calls nested deeper than {MAX_CALL_DEPTH} levels")]
+    CallTooDeep,
+    #[error("{0} is not supported by the analysis")]
+    Unsupported(&'static str),
+}
+
+/// The resources used by a set of programs, as declared in `maxp`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub max_stack_elements: usize,
+    /// One more than the largest function number defined.
+    pub max_function_defs: usize,
+    pub max_instruction_defs: usize,
+    /// One more than the largest storage index written or read.
+    pub max_storage: usize,
+    /// One more than the largest point number used while a zone pointer
+    /// points to the twilight zone.
+    pub max_twilight_points: usize,
+    pub uses_twilight_zone: bool,
+}
+
+/// A value on the stack. `None` if it cannot be known statically.
+type Value = Option<i32>;
+
+#[derive(Debug, Clone)]
+struct State {
+    stack: Vec<Value>,
+    loop_count: Value,
+    zone_pointers: [Value; 3],
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            stack: vec![],
+            loop_count: Some(1),
+            zone_pointers: [Some(1); 3],
+        }
+    }
+}
+
+impl State {
+    fn merge(&mut self, other: State) -> Result<(), AnalysisError> {
+        if self.stack.len() != other.stack.len() {
+            return Err(AnalysisError::UnbalancedBranches(
+                self.stack.len(),
+                other.stack.len(),
+            ));
+        }
+        fn merge_value(a: &mut Value, b: Value) {
+            if *a != b {
+                *a = None;
+            }
+        }
+        for (a, b) in self.stack.iter_mut().zip(other.stack) {
+            merge_value(a, b);
+        }
+        merge_value(&mut self.loop_count, other.loop_count);
+        for (a, b) in self.zone_pointers.iter_mut().zip(other.zone_pointers) {
+            merge_value(a, b);
+        }
+        Ok(())
+    }
+
+    fn in_twilight_zone(&self) -> bool {
+        self.zone_pointers.contains(&Some(0))
+    }
+}
+
+/// Runs programs on an abstract interpreter to find their resource usage.
+///
+/// Programs must be analyzed in the order they are run by the rasterizer:
+/// `fpgm` first, then `prep`, then the glyph programs, so that functions are
+/// defined before they are called.
+#[derive(Debug, Default)]
+pub struct Analyzer {
+    functions: HashMap<i32, Vec<Instruction>>,
+    storage: HashMap<i32, Value>,
+    usage: ResourceUsage,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The resources used by all programs analyzed so far.
+    pub fn usage(&self) -> ResourceUsage {
+        self.usage
+    }
+
+    /// Analyzes a program given as bytecode.
+    pub fn analyze_bytecode(&mut self, code: &[u8]) -> Result<(), AnalysisError> {
+        self.analyze(&disassemble(code)?)
+    }
+
+    /// Analyzes a program. Each program starts with an empty stack.
+    pub fn analyze(&mut self, code: &[Instruction]) -> Result<(), AnalysisError> {
+        self.exec(code, &mut State::default(), 0)
+    }
+
+    fn exec(
+        &mut self,
+        code: &[Instruction],
+        state: &mut State,
+        call_depth: usize,
+    ) -> Result<(), AnalysisError> {
+        let mut i = 0;
+        while i < code.len() {
+            let inst = &code[i];
+            let name = inst.mnemonic();
+            match inst {
+                Instruction::NpushB(v) | Instruction::PushB(v) => {
+                    state.stack.extend(v.iter().map(|&x| Some(x as i32)));
+                }
+                Instruction::NpushW(v) | Instruction::PushW(v) => {
+                    state.stack.extend(v.iter().map(|&x| Some(x as i32)));
+                }
+
+                // Control flow
+                Instruction::If => {
+                    let cond = pop(state, name)?;
+                    let (else_ix, eif_ix) = find_eif(code, i)?;
+                    let then_code = &code[i + 1..else_ix.unwrap_or(eif_ix)];
+                    let else_code = else_ix.map_or(&[][..], |e| &code[e + 1..eif_ix]);
+                    match cond {
+                        Some(0) => self.exec(else_code, state, call_depth)?,
+                        Some(_) => self.exec(then_code, state, call_depth)?,
+                        None => {
+                            let mut else_state = state.clone();
+                            self.exec(then_code, state, call_depth)?;
+                            self.exec(else_code, &mut else_state, call_depth)?;
+                            state.merge(else_state)?;
+                        }
+                    }
+                    i = eif_ix + 1;
+                    continue;
+                }
+                Instruction::Else | Instruction::Eif | Instruction::Endf => {
+                    return Err(AnalysisError::UnmatchedControlFlow(name));
+                }
+                Instruction::Jrot | Instruction::Jmpr | Instruction::Jrof => {
+                    return Err(AnalysisError::Unsupported(name));
+                }
+
+                // Functions
+                Instruction::Fdef => {
+                    let f = pop_known(state, name)?;
+                    let endf_ix = find_endf(code, i)?;
+                    self.functions.insert(f, code[i + 1..endf_ix].to_vec());
+                    let n_defs =
+                        usize::try_from(f).map_err(|_| AnalysisError::UnknownOperand(name))?;
+                    self.usage.max_function_defs = self.usage.max_function_defs.max(n_defs + 1);
+                    i = endf_ix + 1;
+                    continue;
+                }
+                Instruction::Idef => {
+                    pop(state, name)?;
+                    let endf_ix = find_endf(code, i)?;
+                    self.usage.max_instruction_defs += 1;
+                    i = endf_ix + 1;
+                    continue;
+                }
+                Instruction::Call => {
+                    let f = pop_known(state, name)?;
+                    self.call(f, state, call_depth)?;
+                }
+                Instruction::Loopcall => {
+                    let f = pop_known(state, name)?;
+                    let count = pop_known(state, name)?;
+                    for _ in 0..count {
+                        self.call(f, state, call_depth)?;
+                    }
+                }
+
+                // Instructions with stack effects depending on values
+                Instruction::Clear => state.stack.clear(),
+                Instruction::Depth => {
+                    let depth = state.stack.len() as i32;
+                    state.stack.push(Some(depth));
+                }
+                Instruction::Dup => {
+                    let v = pop(state, name)?;
+                    state.stack.extend([v, v]);
+                }
+                Instruction::Swap => {
+                    let e1 = pop(state, name)?;
+                    let e2 = pop(state, name)?;
+                    state.stack.extend([e1, e2]);
+                }
+                Instruction::Roll => {
+                    let a = pop(state, name)?;
+                    let b = pop(state, name)?;
+                    let c = pop(state, name)?;
+                    state.stack.extend([b, a, c]);
+                }
+                Instruction::Cindex | Instruction::Mindex => {
+                    let k = pop(state, name)?;
+                    let ix = match k {
+                        Some(k) if k >= 1 && k as usize <= state.stack.len() => {
+                            Some(state.stack.len() - k as usize)
+                        }
+                        Some(_) => return Err(AnalysisError::StackUnderflow(name)),
+                        None => None,
+                    };
+                    match (inst, ix) {
+                        (Instruction::Cindex, Some(ix)) => state.stack.push(state.stack[ix]),
+                        (Instruction::Cindex, None) => state.stack.push(None),
+                        (_, Some(ix)) => {
+                            let v = state.stack.remove(ix);
+                            state.stack.push(v);
+                        }
+                        (_, None) => {
+                            // We don't know which element is moved, so none
+                            // of the values are known anymore.
+                            pop(state, name)?;
+                            state.stack.fill(None);
+                            state.stack.push(None);
+                        }
+                    }
+                }
+                Instruction::Add | Instruction::Sub | Instruction::Max | Instruction::Min => {
+                    let n2 = pop(state, name)?;
+                    let n1 = pop(state, name)?;
+                    let n = n1.zip(n2).map(|(n1, n2)| match inst {
+                        Instruction::Add => n1.wrapping_add(n2),
+                        Instruction::Sub => n1.wrapping_sub(n2),
+                        Instruction::Max => n1.max(n2),
+                        _ => n1.min(n2),
+                    });
+                    state.stack.push(n);
+                }
+                Instruction::Lt
+                | Instruction::Lteq
+                | Instruction::Gt
+                | Instruction::Gteq
+                | Instruction::Eq
+                | Instruction::Neq => {
+                    let e2 = pop(state, name)?;
+                    let e1 = pop(state, name)?;
+                    let b = e1.zip(e2).map(|(e1, e2)| {
+                        let b = match inst {
+                            Instruction::Lt => e1 < e2,
+                            Instruction::Lteq => e1 <= e2,
+                            Instruction::Gt => e1 > e2,
+                            Instruction::Gteq => e1 >= e2,
+                            Instruction::Eq => e1 == e2,
+                            _ => e1 != e2,
+                        };
+                        b as i32
+                    });
+                    state.stack.push(b);
+                }
+                Instruction::Neg => {
+                    let n = pop(state, name)?;
+                    state.stack.push(n.map(i32::wrapping_neg));
+                }
+
+                // Graphics state tracked by the analysis
+                Instruction::Sloop => state.loop_count = pop(state, name)?,
+                Instruction::Szp0 | Instruction::Szp1 | Instruction::Szp2 | Instruction::Szps => {
+                    let zone = pop(state, name)?;
+                    match inst {
+                        Instruction::Szp0 => state.zone_pointers[0] = zone,
+                        Instruction::Szp1 => state.zone_pointers[1] = zone,
+                        Instruction::Szp2 => state.zone_pointers[2] = zone,
+                        _ => state.zone_pointers = [zone; 3],
+                    }
+                    if zone != Some(1) {
+                        self.usage.uses_twilight_zone = true;
+                    }
+                }
+                Instruction::Rs => {
+                    let index = pop_known(state, name)?;
+                    self.use_storage(index, name)?;
+                    state
+                        .stack
+                        .push(self.storage.get(&index).copied().flatten());
+                }
+                Instruction::Ws => {
+                    let value = pop(state, name)?;
+                    let index = pop_known(state, name)?;
+                    self.use_storage(index, name)?;
+                    self.storage.insert(index, value);
+                }
+
+                // Instructions moving points, tracked for twilight zone usage
+                Instruction::Srp0
+                | Instruction::Srp1
+                | Instruction::Srp2
+                | Instruction::Mdap(_)
+                | Instruction::Mdrp(_) => {
+                    let p = pop(state, name)?;
+                    self.use_point(state, p);
+                }
+                Instruction::Miap(_)
+                | Instruction::Mirp(_)
+                | Instruction::Msirp(_)
+                | Instruction::Scfs => {
+                    pop(state, name)?;
+                    let p = pop(state, name)?;
+                    self.use_point(state, p);
+                }
+
+                // Instructions repeated `loop` times
+                Instruction::Flippt
+                | Instruction::Shp(_)
+                | Instruction::Alignrp
+                | Instruction::Ip
+                | Instruction::Shpix => {
+                    if let Instruction::Shpix = inst {
+                        pop(state, name)?;
+                    }
+                    let n = state
+                        .loop_count
+                        .ok_or(AnalysisError::UnknownOperand(name))?;
+                    for _ in 0..n {
+                        let p = pop(state, name)?;
+                        self.use_point(state, p);
+                    }
+                    state.loop_count = Some(1);
+                }
+                Instruction::Deltap1
+                | Instruction::Deltap2
+                | Instruction::Deltap3
+                | Instruction::Deltac1
+                | Instruction::Deltac2
+                | Instruction::Deltac3 => {
+                    let n = pop_known(state, name)?;
+                    for _ in 0..2 * n {
+                        pop(state, name)?;
+                    }
+                }
+                Instruction::Getvariation | Instruction::User(_) => {
+                    return Err(AnalysisError::Unsupported(name));
+                }
+
+                _ => match inst.stack_effect() {
+                    StackEffect::Fixed { pops, pushes } => {
+                        for _ in 0..pops {
+                            pop(state, name)?;
+                        }
+                        state.stack.extend(std::iter::repeat_n(None, pushes));
+                    }
+                    StackEffect::Dynamic => unreachable!("{name} should be handled above"),
+                },
+            }
+            self.usage.max_stack_elements = self.usage.max_stack_elements.max(state.stack.len());
+            i += 1;
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, f: i32, state: &mut State, call_depth: usize) -> Result<(), AnalysisError> {
+        if call_depth >= MAX_CALL_DEPTH {
+            return Err(AnalysisError::CallTooDeep);
+        }
+        let body = self
+            .functions
+            .get(&f)
+            .cloned()
+            .ok_or(AnalysisError::UndefinedFunction(f))?;
+        self.exec(&body, state, call_depth + 1)
+    }
+
+    fn use_storage(&mut self, index: i32, name: &'static str) -> Result<(), AnalysisError> {
+        let index = usize::try_from(index).map_err(|_| AnalysisError::UnknownOperand(name))?;
+        self.usage.max_storage = self.usage.max_storage.max(index + 1);
+        Ok(())
+    }
+
+    fn use_point(&mut self, state: &State, p: Value) {
+        if let (true, Some(p)) = (state.in_twilight_zone(), p)
+            && let Ok(p) = usize::try_from(p)
+        {
+            self.usage.max_twilight_points = self.usage.max_twilight_points.max(p + 1);
+        }
+    }
+}
+
+fn pop(state: &mut State, name: &'static str) -> Result<Value, AnalysisError> {
+    state.stack.pop().ok_or(AnalysisError::StackUnderflow(name))
+}
+
+fn pop_known(state: &mut State, name: &'static str) -> Result<i32, AnalysisError> {
+    pop(state, name)?.ok_or(AnalysisError::UnknownOperand(name))
+}
+
+/// Finds the matching `ELSE` (if any) and `EIF` of the `IF` at `if_ix`.
+fn find_eif(code: &[Instruction], if_ix: usize) -> Result<(Option<usize>, usize), AnalysisError> {
+    let mut depth = 0;
+    let mut else_ix = None;
+    for (i, inst) in code.iter().enumerate().skip(if_ix + 1) {
+        match inst {
+            Instruction::If => depth += 1,
+            Instruction::Else if depth == 0 => else_ix = Some(i),
+            Instruction::Eif if depth == 0 => return Ok((else_ix, i)),
+            Instruction::Eif => depth -= 1,
+            _ => {}
+        }
+    }
+    Err(AnalysisError::UnmatchedControlFlow("IF"))
+}
+
+/// Finds the `ENDF` of the `FDEF` or `IDEF` at `def_ix`.
+fn find_endf(code: &[Instruction], def_ix: usize) -> Result<usize, AnalysisError> {
+    code.iter()
+        .skip(def_ix + 1)
+        .position(|inst| matches!(inst, Instruction::Endf))
+        .map(|pos| def_ix + 1 + pos)
+        .ok_or(AnalysisError::UnmatchedControlFlow(code[def_ix].mnemonic()))
+}
+
+#[test]
+fn test_function_calls() {
+    use crate::model::inst::{DistanceType, MoveFlags};
+
+    let mut analyzer = Analyzer::new();
+    // fpgm: function 0 takes a point and a CVT index, and links the point to
+    // rp0 with MIRP.
+    let mut fpgm = Instruction::push(&[0]);
+    fpgm.extend([
+        Instruction::Fdef,
+        Instruction::Mirp(MoveFlags {
+            set_rp0: false,
+            min_distance: true,
+            round: true,
+            distance_type: DistanceType::Gray,
+        }),
+        Instruction::Endf,
+    ]);
+    analyzer.analyze(&fpgm).unwrap();
+
+    // A glyph program calling the function twice
+    let mut glyph = Instruction::push(&[1, 3, 2, 3, 2, 0]);
+    glyph.extend([Instruction::Loopcall]);
+    glyph.extend(Instruction::push(&[4, 0, 7]));
+    glyph.extend([
+        Instruction::Ws,
+        Instruction::Mdap(true),
+        Instruction::Iup(crate::model::inst::Axis::Y),
+    ]);
+    analyzer.analyze(&glyph).unwrap();
+
+    let usage = analyzer.usage();
+    assert_eq!(usage.max_stack_elements, 6);
+    assert_eq!(usage.max_function_defs, 1);
+    assert_eq!(usage.max_storage, 1);
+    assert!(!usage.uses_twilight_zone);
+}
+
+#[test]
+fn test_unbalanced_branches() {
+    let mut code = Instruction::push(&[1]);
+    code.extend([Instruction::Mppem, Instruction::If]);
+    code.extend(Instruction::push(&[2]));
+    code.extend([Instruction::Else, Instruction::Eif]);
+    assert!(matches!(
+        Analyzer::new().analyze(&code),
+        Err(AnalysisError::UnbalancedBranches(2, 1))
+    ));
+}
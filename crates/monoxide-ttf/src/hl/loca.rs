@@ -12,20 +12,24 @@
 // to get the total size of each glyph data.
 
 use crate::{
-    model::{glyf, loca},
+    model::{glyf, head::IndexToLocFormat, loca},
     util::SizeOnlyBufWriter,
 };
 
-/// Generate the `loca` table from an existing `glyf` table.
+/// Generate the `loca` table from an existing `glyf` table, in the long format.
+/// The font writer may switch it to the short format if possible.
 pub fn glyf_to_loca(glyf: &glyf::Table) -> loca::Table {
     let mut offsets = Vec::with_capacity(glyf.glyphs.len());
     let mut w = SizeOnlyBufWriter::new();
     for it in &glyf.glyphs {
         offsets.push(w.size() as u32);
-        it.write(&mut w);
+        it.write_padded(&mut w);
     }
     offsets.push(w.size() as u32);
 
     debug_assert_eq!(offsets.len(), glyf.glyphs.len() + 1);
-    loca::Table { offsets }
+    loca::Table {
+        offsets,
+        format: IndexToLocFormat::Long,
+    }
 }
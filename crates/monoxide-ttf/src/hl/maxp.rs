@@ -1,9 +1,26 @@
 //! Generate the `maxp` table from the `glyf` table.
 
-use crate::model::{cff2, glyf, maxp};
+use super::inst::{AnalysisError, Analyzer};
+use crate::model::{cff2, fpgm, glyf, maxp, prep};
 
 /// Convert a `glyf` table to a `maxp` table version 1.
-pub fn glyf_to_maxp(glyf: &glyf::Table) -> maxp::TableV1 {
+///
+/// The instruction-related fields are computed by analyzing the font program,
+/// the control value program and the instructions of each glyph, see
+/// [`super::inst`].
+pub fn glyf_to_maxp(
+    glyf: &glyf::Table,
+    fpgm: Option<&fpgm::Table>,
+    prep: Option<&prep::Table>,
+) -> Result<maxp::TableV1, AnalysisError> {
+    let mut analyzer = Analyzer::new();
+    if let Some(fpgm) = fpgm {
+        analyzer.analyze_bytecode(&fpgm.instructions)?;
+    }
+    if let Some(prep) = prep {
+        analyzer.analyze_bytecode(&prep.instructions)?;
+    }
+
     let mut maxp = maxp::TableV1 {
         n_glyphs: glyf.glyphs.len() as u16,
         max_component_depth: 0,
@@ -15,8 +32,7 @@ pub fn glyf_to_maxp(glyf: &glyf::Table) -> maxp::TableV1 {
             glyf::Glyph::Simple(glyph) => {
                 maxp.max_points = maxp.max_points.max(glyph.n_points() as u16);
                 maxp.max_contours = maxp.max_contours.max(glyph.n_contours() as u16);
-                // TODO: zones, twilight points, storage, func defs, instruction defs, stack
-                // elements
+                analyzer.analyze_bytecode(&glyph.instructions)?;
                 maxp.max_size_of_instructions = maxp
                     .max_size_of_instructions
                     .max(glyph.instructions.len() as u16);
@@ -28,21 +44,35 @@ pub fn glyf_to_maxp(glyf: &glyf::Table) -> maxp::TableV1 {
                 maxp.max_composite_contours = maxp
                     .max_composite_contours
                     .max(glyph.n_contours(&glyf.glyphs) as u16);
-                // TODO: instruction stuff, see above
+                analyzer.analyze_bytecode(&glyph.instructions)?;
                 maxp.max_size_of_instructions = maxp
                     .max_size_of_instructions
                     .max(glyph.instructions.len() as u16);
                 maxp.max_component_elements = maxp
                     .max_component_elements
                     .max(glyph.components.len() as u16);
+                // Levels of recursion, 1 for compounds of simple glyphs,
+                // while `depth` counts simple glyphs as 1
                 maxp.max_component_depth = maxp
                     .max_component_depth
-                    .max(glyph.depth(&glyf.glyphs) as u16);
+                    .max(glyph.depth(&glyf.glyphs) as u16 - 1);
             }
         }
     }
 
-    maxp
+    let usage = analyzer.usage();
+    maxp.max_zones = if usage.uses_twilight_zone {
+        maxp::MaxZonesKind::UsesTwilightZone
+    } else {
+        maxp::MaxZonesKind::DoesNotUseTwilightZone
+    };
+    maxp.max_twilight_points = usage.max_twilight_points as u16;
+    maxp.max_storage = usage.max_storage as u16;
+    maxp.max_func_defs = usage.max_function_defs as u16;
+    maxp.max_instruction_defs = usage.max_instruction_defs as u16;
+    maxp.max_stack_elements = usage.max_stack_elements as u16;
+
+    Ok(maxp)
 }
 
 #[allow(deprecated)]
@@ -3,7 +3,14 @@
 //!
 //! These data structures will be mapped to the low-level structures in
 //! [`crate::model`] so that they can be written to the binary format.
+pub mod builder;
 pub mod cmap;
+pub mod device;
 pub mod glyf;
+pub mod inst;
 pub mod loca;
 pub mod maxp;
+pub mod name;
+pub mod os2;
+pub mod post;
+pub mod subset;
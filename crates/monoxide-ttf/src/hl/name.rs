@@ -0,0 +1,170 @@
+//! Derive the full set of `name` records from a few font-level names.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/name#name-ids>
+
+use crate::model::{
+    Fixed,
+    name::{self, Lang, MSLangID, MacLangID, NameRecords},
+};
+
+/// Characters not allowed in PostScript names, besides whitespace and
+/// non-printable ASCII.
+const POSTSCRIPT_FORBIDDEN: &[char] = &['[', ']', '(', ')', '{', '}', '<', '>', '/', '%'];
+
+/// Maximum length of PostScript names.
+const POSTSCRIPT_MAX_LEN: usize = 63;
+
+/// Style names that fit in the four-member family of legacy applications.
+const RIBBI_STYLES: &[&str] = &["Regular", "Italic", "Bold", "Bold Italic"];
+
+/// The names of a font, from which all the `name` records are derived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontNames {
+    /// The family name shared by all styles, e.g. `Monoxide`.
+    pub family: String,
+    /// The style within the family, e.g. `Regular` or `Light Italic`.
+    pub style: String,
+    /// The version number, e.g. `1.000`. It is written as `Version 1.000`,
+    /// and the leading number is also used as the font revision in `head`.
+    pub version: String,
+    pub copyright: Option<String>,
+    /// A description of the license.
+    pub license: Option<String>,
+    /// The name of the manufacturer.
+    pub vendor: Option<String>,
+    pub designer: Option<String>,
+}
+
+impl FontNames {
+    pub fn new(family: impl Into<String>, style: impl Into<String>) -> Self {
+        Self {
+            family: family.into(),
+            style: style.into(),
+            version: "1.000".into(),
+            copyright: None,
+            license: None,
+            vendor: None,
+            designer: None,
+        }
+    }
+
+    /// The full font name, e.g. `Monoxide Bold Italic`.
+    pub fn full_name(&self) -> String {
+        format!("{} {}", self.family, self.style)
+    }
+
+    /// The PostScript name, e.g. `Monoxide-BoldItalic`, with all disallowed
+    /// characters removed.
+    pub fn postscript_name(&self) -> String {
+        let sanitize = |s: &str| {
+            s.chars()
+                .filter(|&ch| ch.is_ascii_graphic() && !POSTSCRIPT_FORBIDDEN.contains(&ch))
+                .collect::<String>()
+        };
+        let mut name = format!("{}-{}", sanitize(&self.family), sanitize(&self.style));
+        name.truncate(POSTSCRIPT_MAX_LEN);
+        name
+    }
+
+    /// The font revision for the `head` table, from the leading number of
+    /// the version. Defaults to 0 if there is no such number.
+    pub fn font_revision(&self) -> Fixed {
+        let number = self
+            .version
+            .split(|ch: char| !ch.is_ascii_digit() && ch != '.')
+            .next()
+            .unwrap_or_default();
+        number.parse().unwrap_or_default()
+    }
+
+    /// The family and subfamily names for applications that only support
+    /// the regular, italic, bold and bold italic styles in a family.
+    ///
+    /// Other styles are moved into the family name, e.g. `Light Italic` of
+    /// `Monoxide` becomes the `Italic` style of `Monoxide Light`.
+    pub fn legacy_names(&self) -> (String, String) {
+        if self.is_ribbi() {
+            return (self.family.clone(), self.style.clone());
+        }
+        let mut words = self.style.split_whitespace().collect::<Vec<_>>();
+        let italic = words.last().is_some_and(|w| *w == "Italic");
+        if italic {
+            words.pop();
+        }
+        let family = format!("{} {}", self.family, words.join(" "));
+        let style = if italic { "Italic" } else { "Regular" };
+        (family, style.into())
+    }
+
+    fn is_ribbi(&self) -> bool {
+        RIBBI_STYLES.contains(&self.style.as_str())
+    }
+
+    /// Generate the name records, in English for both the Windows and the
+    /// Macintosh platforms.
+    ///
+    /// `vendor_id` is the vendor ID in the `OS/2` table, used in the unique
+    /// font identifier.
+    pub fn to_table(&self, vendor_id: [u8; 4]) -> name::Table {
+        let (family, subfamily) = self.legacy_names();
+        let postscript_name = self.postscript_name();
+        let vendor_id = String::from_utf8_lossy(&vendor_id).trim().to_owned();
+        let typographic = !self.is_ribbi();
+
+        let records = NameRecords {
+            copyright: self.copyright.clone(),
+            font_family_name: Some(family),
+            font_subfamily_name: Some(subfamily),
+            unique_font_identifier: Some(format!("{};{vendor_id};{postscript_name}", self.version)),
+            full_font_name: Some(self.full_name()),
+            version: Some(format!("Version {}", self.version)),
+            postscript_name: Some(postscript_name),
+            manufacturer: self.vendor.clone(),
+            designer: self.designer.clone(),
+            license_description: self.license.clone(),
+            preferred_family: typographic.then(|| self.family.clone()),
+            preferred_subfamily: typographic.then(|| self.style.clone()),
+            ..Default::default()
+        };
+        name::Table {
+            records: [
+                (Lang::Microsoft(MSLangID::en_us), records.clone()),
+                (Lang::Macintosh(MacLangID::en), records),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_derived_names() {
+    let names = FontNames::new("Mono Oxide", "Bold Italic");
+    assert_eq!(
+        names.legacy_names(),
+        ("Mono Oxide".into(), "Bold Italic".into())
+    );
+    assert_eq!(names.postscript_name(), "MonoOxide-BoldItalic");
+    assert_eq!(names.font_revision(), Fixed::from_num(1));
+
+    let names = FontNames {
+        version: "2.5 beta".into(),
+        ..FontNames::new("Mono(Oxide)", "Light Italic")
+    };
+    assert_eq!(
+        names.legacy_names(),
+        ("Mono(Oxide) Light".into(), "Italic".into())
+    );
+    assert_eq!(names.postscript_name(), "MonoOxide-LightItalic");
+    assert_eq!(names.font_revision(), Fixed::from_num(2.5));
+
+    let table = names.to_table(*b"TEST");
+    let records = &table.records[&Lang::Microsoft(MSLangID::en_us)];
+    assert_eq!(records.preferred_family.as_deref(), Some("Mono(Oxide)"));
+    assert_eq!(records.preferred_subfamily.as_deref(), Some("Light Italic"));
+    assert_eq!(
+        records.unique_font_identifier.as_deref(),
+        Some("2.5 beta;TEST;MonoOxide-LightItalic")
+    );
+    assert_eq!(table.records[&Lang::Macintosh(MacLangID::en)], *records);
+}
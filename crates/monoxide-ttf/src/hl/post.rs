@@ -0,0 +1,251 @@
+//! Glyph naming for the `post` table.
+//!
+//! Names generated here follow the [Adobe Glyph List Specification][agl], so
+//! that PDF readers and other tools can map glyphs back to Unicode text.
+//!
+//! [agl]: https://github.com/adobe-type-tools/agl-specification
+
+/// Glyph names of frequently used characters, as listed in the Adobe Glyph
+/// List. Characters not listed here are named `uniXXXX` or `uXXXXX`.
+const AGL_NAMES: &[(char, &str)] = &[
+    (' ', "space"),
+    ('!', "exclam"),
+    ('"', "quotedbl"),
+    ('#', "numbersign"),
+    ('$', "dollar"),
+    ('%', "percent"),
+    ('&', "ampersand"),
+    ('\'', "quotesingle"),
+    ('(', "parenleft"),
+    (')', "parenright"),
+    ('*', "asterisk"),
+    ('+', "plus"),
+    (',', "comma"),
+    ('-', "hyphen"),
+    ('.', "period"),
+    ('/', "slash"),
+    ('0', "zero"),
+    ('1', "one"),
+    ('2', "two"),
+    ('3', "three"),
+    ('4', "four"),
+    ('5', "five"),
+    ('6', "six"),
+    ('7', "seven"),
+    ('8', "eight"),
+    ('9', "nine"),
+    (':', "colon"),
+    (';', "semicolon"),
+    ('<', "less"),
+    ('=', "equal"),
+    ('>', "greater"),
+    ('?', "question"),
+    ('@', "at"),
+    ('[', "bracketleft"),
+    ('\\', "backslash"),
+    (']', "bracketright"),
+    ('^', "asciicircum"),
+    ('_', "underscore"),
+    ('`', "grave"),
+    ('{', "braceleft"),
+    ('|', "bar"),
+    ('}', "braceright"),
+    ('~', "asciitilde"),
+    ('\u{a1}', "exclamdown"),
+    ('\u{a2}', "cent"),
+    ('\u{a3}', "sterling"),
+    ('\u{a4}', "currency"),
+    ('\u{a5}', "yen"),
+    ('\u{a6}', "brokenbar"),
+    ('\u{a7}', "section"),
+    ('\u{a8}', "dieresis"),
+    ('\u{a9}', "copyright"),
+    ('\u{aa}', "ordfeminine"),
+    ('\u{ab}', "guillemotleft"),
+    ('\u{ac}', "logicalnot"),
+    ('\u{ae}', "registered"),
+    ('\u{af}', "macron"),
+    ('\u{b0}', "degree"),
+    ('\u{b1}', "plusminus"),
+    ('\u{b2}', "twosuperior"),
+    ('\u{b3}', "threesuperior"),
+    ('\u{b4}', "acute"),
+    ('\u{b5}', "mu"),
+    ('\u{b6}', "paragraph"),
+    ('\u{b7}', "periodcentered"),
+    ('\u{b8}', "cedilla"),
+    ('\u{b9}', "onesuperior"),
+    ('\u{ba}', "ordmasculine"),
+    ('\u{bb}', "guillemotright"),
+    ('\u{bc}', "onequarter"),
+    ('\u{bd}', "onehalf"),
+    ('\u{be}', "threequarters"),
+    ('\u{bf}', "questiondown"),
+    ('\u{c0}', "Agrave"),
+    ('\u{c1}', "Aacute"),
+    ('\u{c2}', "Acircumflex"),
+    ('\u{c3}', "Atilde"),
+    ('\u{c4}', "Adieresis"),
+    ('\u{c5}', "Aring"),
+    ('\u{c6}', "AE"),
+    ('\u{c7}', "Ccedilla"),
+    ('\u{c8}', "Egrave"),
+    ('\u{c9}', "Eacute"),
+    ('\u{ca}', "Ecircumflex"),
+    ('\u{cb}', "Edieresis"),
+    ('\u{cc}', "Igrave"),
+    ('\u{cd}', "Iacute"),
+    ('\u{ce}', "Icircumflex"),
+    ('\u{cf}', "Idieresis"),
+    ('\u{d0}', "Eth"),
+    ('\u{d1}', "Ntilde"),
+    ('\u{d2}', "Ograve"),
+    ('\u{d3}', "Oacute"),
+    ('\u{d4}', "Ocircumflex"),
+    ('\u{d5}', "Otilde"),
+    ('\u{d6}', "Odieresis"),
+    ('\u{d7}', "multiply"),
+    ('\u{d8}', "Oslash"),
+    ('\u{d9}', "Ugrave"),
+    ('\u{da}', "Uacute"),
+    ('\u{db}', "Ucircumflex"),
+    ('\u{dc}', "Udieresis"),
+    ('\u{dd}', "Yacute"),
+    ('\u{de}', "Thorn"),
+    ('\u{df}', "germandbls"),
+    ('\u{e0}', "agrave"),
+    ('\u{e1}', "aacute"),
+    ('\u{e2}', "acircumflex"),
+    ('\u{e3}', "atilde"),
+    ('\u{e4}', "adieresis"),
+    ('\u{e5}', "aring"),
+    ('\u{e6}', "ae"),
+    ('\u{e7}', "ccedilla"),
+    ('\u{e8}', "egrave"),
+    ('\u{e9}', "eacute"),
+    ('\u{ea}', "ecircumflex"),
+    ('\u{eb}', "edieresis"),
+    ('\u{ec}', "igrave"),
+    ('\u{ed}', "iacute"),
+    ('\u{ee}', "icircumflex"),
+    ('\u{ef}', "idieresis"),
+    ('\u{f0}', "eth"),
+    ('\u{f1}', "ntilde"),
+    ('\u{f2}', "ograve"),
+    ('\u{f3}', "oacute"),
+    ('\u{f4}', "ocircumflex"),
+    ('\u{f5}', "otilde"),
+    ('\u{f6}', "odieresis"),
+    ('\u{f7}', "divide"),
+    ('\u{f8}', "oslash"),
+    ('\u{f9}', "ugrave"),
+    ('\u{fa}', "uacute"),
+    ('\u{fb}', "ucircumflex"),
+    ('\u{fc}', "udieresis"),
+    ('\u{fd}', "yacute"),
+    ('\u{fe}', "thorn"),
+    ('\u{ff}', "ydieresis"),
+    ('\u{131}', "dotlessi"),
+    ('\u{141}', "Lslash"),
+    ('\u{142}', "lslash"),
+    ('\u{152}', "OE"),
+    ('\u{153}', "oe"),
+    ('\u{160}', "Scaron"),
+    ('\u{161}', "scaron"),
+    ('\u{178}', "Ydieresis"),
+    ('\u{17d}', "Zcaron"),
+    ('\u{17e}', "zcaron"),
+    ('\u{192}', "florin"),
+    ('\u{2c6}', "circumflex"),
+    ('\u{2c7}', "caron"),
+    ('\u{2d8}', "breve"),
+    ('\u{2d9}', "dotaccent"),
+    ('\u{2da}', "ring"),
+    ('\u{2db}', "ogonek"),
+    ('\u{2dc}', "tilde"),
+    ('\u{2dd}', "hungarumlaut"),
+    ('\u{2013}', "endash"),
+    ('\u{2014}', "emdash"),
+    ('\u{2018}', "quoteleft"),
+    ('\u{2019}', "quoteright"),
+    ('\u{201a}', "quotesinglbase"),
+    ('\u{201c}', "quotedblleft"),
+    ('\u{201d}', "quotedblright"),
+    ('\u{201e}', "quotedblbase"),
+    ('\u{2020}', "dagger"),
+    ('\u{2021}', "daggerdbl"),
+    ('\u{2022}', "bullet"),
+    ('\u{2026}', "ellipsis"),
+    ('\u{2030}', "perthousand"),
+    ('\u{2039}', "guilsinglleft"),
+    ('\u{203a}', "guilsinglright"),
+    ('\u{2044}', "fraction"),
+    ('\u{20ac}', "Euro"),
+    ('\u{2122}', "trademark"),
+    ('\u{2202}', "partialdiff"),
+    ('\u{220f}', "product"),
+    ('\u{2211}', "summation"),
+    ('\u{2212}', "minus"),
+    ('\u{221a}', "radical"),
+    ('\u{221e}', "infinity"),
+    ('\u{222b}', "integral"),
+    ('\u{2248}', "approxequal"),
+    ('\u{2260}', "notequal"),
+    ('\u{2264}', "lessequal"),
+    ('\u{2265}', "greaterequal"),
+    ('\u{25ca}', "lozenge"),
+];
+
+/// The name of the glyph mapped from the given character.
+///
+/// Latin letters and characters in the Adobe Glyph List use their listed
+/// names, and other characters are named `uniXXXX` (BMP) or `uXXXXX`.
+pub fn glyph_name(ch: char) -> String {
+    if ch.is_ascii_alphabetic() {
+        return ch.to_string();
+    }
+    if let Some((_, name)) = AGL_NAMES.iter().find(|(c, _)| *c == ch) {
+        return (*name).to_owned();
+    }
+    match ch as u32 {
+        cp @ ..=0xffff => format!("uni{cp:04X}"),
+        cp => format!("u{cp:05X}"),
+    }
+}
+
+/// The name of a ligature glyph composed of the given characters, e.g.
+/// `f_f` for "ff".
+pub fn ligature_name(chars: impl IntoIterator<Item = char>) -> String {
+    chars
+        .into_iter()
+        .map(glyph_name)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Whether the name can be written to the `post` table: 1 to 63 characters
+/// from `A`–`Z`, `a`–`z`, `0`–`9`, `.` and `_`, as the specification
+/// requires.
+pub fn is_valid_glyph_name(name: &str) -> bool {
+    (1..64).contains(&name.len())
+        && (name.bytes()).all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'_')
+}
+
+#[test]
+fn test_glyph_names() {
+    assert_eq!(glyph_name('A'), "A");
+    assert_eq!(glyph_name('0'), "zero");
+    assert_eq!(glyph_name('\u{e9}'), "eacute");
+    assert_eq!(glyph_name('\u{410}'), "uni0410");
+    assert_eq!(glyph_name('\u{1f600}'), "u1F600");
+    assert_eq!(ligature_name("ff".chars()), "f_f");
+    assert_eq!(ligature_name("-->".chars()), "hyphen_hyphen_greater");
+
+    assert!(is_valid_glyph_name(".notdef"));
+    assert!(is_valid_glyph_name("f_f.liga"));
+    assert!(!is_valid_glyph_name(""));
+    assert!(!is_valid_glyph_name("a-b"));
+    assert!(!is_valid_glyph_name("\u{e9}"));
+    assert!(!is_valid_glyph_name(&"a".repeat(64)));
+    assert!(is_valid_glyph_name(&"a".repeat(63)));
+}
@@ -0,0 +1,396 @@
+//! Subset a font to the glyphs needed for a set of characters, e.g. to preview
+//! a short text on the web without downloading the whole font.
+//!
+//! The subset keeps `.notdef`, the glyphs mapped from the characters and the
+//! components they are made of, renumbered in their original order. The
+//! tables indexed by glyph ID are rebuilt for the new glyph IDs. Font-wide
+//! metrics such as the line spacing in `hhea` and `OS/2` are kept as is, so
+//! that text laid out in the subset looks exactly like in the full font, while
+//! the statistics over the glyphs, such as the bounding box and the average
+//! advance, are computed again.
+//!
+//! The font program, the control value program and the control values are
+//! kept whole, since glyph instructions may call any function or read any
+//! control value.
+//!
+//! Fonts built by this crate have no layout tables, but raw `GDEF`, `GSUB` and
+//! `GPOS` tables are remapped to the new glyph IDs. The glyphs that the
+//! substitutions can produce from the kept glyphs are kept as well, so that
+//! ligatures and alternates of the characters still work in the subset. Other
+//! raw tables known to refer to glyph IDs cannot be remapped, and fonts
+//! containing them are rejected rather than subset without them. The rest of
+//! the raw tables are kept as is.
+
+mod layout;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use thiserror::Error;
+
+use super::{
+    builder::{GlyphStats, hmtx_table},
+    cmap::{self as hl_cmap, VariationMapping},
+    device::advance_width,
+    inst::AnalysisError,
+    loca::glyf_to_loca,
+    maxp::glyf_to_maxp,
+    os2 as hl_os2,
+};
+use crate::{
+    model::{FontFile, Outline, RawTable, TrueTypeTables, cmap, glyf},
+    read::cmap::{Mappings, read_format4, read_format12, read_format14},
+};
+
+/// Tags of the tables that refer to glyph IDs, and so can't be kept in a
+/// subset as raw tables. The layout tables are remapped by [`layout`] instead.
+const GLYPH_TABLES: &[&[u8; 4]] = &[
+    b"BASE", b"CBDT", b"CBLC", b"COLR", b"EBDT", b"EBLC", b"JSTF", b"LTSH", b"MATH", b"SVG ",
+    b"VORG", b"kern", b"sbix", b"vhea", b"vmtx",
+];
+
+#[derive(Debug, Error)]
+pub enum SubsetError {
+    #[error("Subsetting fonts with CFF2 outlines is not supported")]
+    UnsupportedOutline,
+
+    #[error(
+        "Table {:?} refers to glyph IDs and can't be subset",
+        String::from_utf8_lossy(.0)
+    )]
+    UnsupportedTable([u8; 4]),
+
+    #[error("Table {:?} is malformed", String::from_utf8_lossy(.0))]
+    MalformedTable([u8; 4]),
+
+    #[error(
+        "Offsets in table {:?} overflow after subsetting",
+        String::from_utf8_lossy(.0)
+    )]
+    TableOverflow([u8; 4]),
+
+    #[error("Failed to analyze TrueType instructions: {0}")]
+    InstructionError(#[from] AnalysisError),
+}
+
+/// Subset a font to the glyphs needed to display the given characters.
+///
+/// Characters the font doesn't map are ignored, and display as `.notdef`.
+/// Fonts with raw tables referring to glyph IDs, other than the layout tables,
+/// fail with [`SubsetError::UnsupportedTable`].
+/// The `cmap` table is rebuilt with a single Unicode subtable, the same way
+/// [`super::builder::FontBuilder`] builds it.
+pub fn subset(
+    font: FontFile,
+    chars: impl IntoIterator<Item = char>,
+) -> Result<FontFile, SubsetError> {
+    let FontFile {
+        mut head,
+        mut hhea,
+        hmtx,
+        cmap,
+        name,
+        mut os2,
+        mut post,
+        outline,
+        gasp,
+        hdmx,
+        vdmx,
+        dsig,
+        extra_tables,
+    } = font;
+    let Outline::TrueType(TrueTypeTables {
+        glyf,
+        fpgm,
+        prep,
+        cvt,
+        ..
+    }) = outline
+    else {
+        return Err(SubsetError::UnsupportedOutline);
+    };
+    if let Some(table) = (extra_tables.iter()).find(|table| GLYPH_TABLES.contains(&&table.tag)) {
+        return Err(SubsetError::UnsupportedTable(table.tag));
+    }
+
+    let chars = chars.into_iter().map(u32::from).collect::<BTreeSet<_>>();
+    let (mappings, variations) = decode_cmap(&cmap)?;
+    let mappings = (mappings.into_iter())
+        .filter(|(code, _)| chars.contains(code))
+        .collect::<Mappings>();
+    let variations = (variations.into_iter())
+        .filter(|v| chars.contains(&v.code))
+        .collect::<Vec<_>>();
+
+    // Collect the glyphs to keep by their old IDs, following the components
+    // of compound glyphs and the substitutions, until neither adds a glyph.
+    let gsub = extra_tables.iter().find(|table| &table.tag == b"GSUB");
+    let mut kept = BTreeSet::new();
+    let mut pending = (mappings.values().copied())
+        .chain(variations.iter().filter_map(|v| v.glyph_id))
+        .chain([0])
+        .collect::<Vec<_>>();
+    while !pending.is_empty() {
+        while let Some(gid) = pending.pop() {
+            if gid as usize >= glyf.glyphs.len() || !kept.insert(gid) {
+                continue;
+            }
+            if let glyf::Glyph::Compound(glyph) = &glyf.glyphs[gid as usize] {
+                pending.extend(glyph.components.iter().map(|c| c.glyph_index as u32));
+            }
+        }
+        if let Some(gsub) = gsub {
+            let substituted =
+                layout::substituted(&gsub.data, &kept).map_err(|e| layout_error(e, gsub.tag))?;
+            pending.extend(substituted.difference(&kept).filter(|&&gid| {
+                // Out of range glyphs would be added over and over.
+                (gid as usize) < glyf.glyphs.len()
+            }));
+        }
+    }
+    let new_ids = (kept.iter().enumerate())
+        .map(|(new, &old)| (old, new as u32))
+        .collect::<BTreeMap<_, _>>();
+
+    let advances = (kept.iter())
+        .map(|&gid| advance_width(&hmtx, gid as usize))
+        .collect::<Vec<_>>();
+    let mut glyphs = retain_kept(glyf.glyphs, &kept);
+    for glyph in &mut glyphs {
+        if let glyf::Glyph::Compound(glyph) = glyph {
+            for component in &mut glyph.components {
+                component.glyph_index = new_ids[&(component.glyph_index as u32)] as u16;
+            }
+        }
+    }
+    let glyf = glyf::Table { glyphs };
+    let loca = glyf_to_loca(&glyf);
+    let maxp = glyf_to_maxp(&glyf, fpgm.as_ref(), prep.as_ref())?;
+    let hmtx = hmtx_table(&glyf, &advances);
+
+    let stats = GlyphStats::new(&glyf, &advances);
+    head.x_min = stats.bbox.x_min;
+    head.y_min = stats.bbox.y_min;
+    head.x_max = stats.bbox.x_max;
+    head.y_max = stats.bbox.y_max;
+    head.index_to_loc_format = loca.format;
+    hhea.advance_width_max = stats.advance_width_max;
+    hhea.min_left_side_bearing = stats.min_left_side_bearing;
+    hhea.min_right_side_bearing = stats.min_right_side_bearing;
+    hhea.x_max_extent = stats.x_max_extent;
+    hhea.number_of_hmetrics = hmtx.metrics.len() as u16;
+    os2.x_avg_char_width = stats.x_avg_char_width;
+
+    let mappings = (mappings.into_iter())
+        .filter_map(|(code, gid)| Some((code, *new_ids.get(&gid)?)))
+        .collect::<Mappings>();
+    let variations = (variations.into_iter())
+        .filter_map(|v| {
+            let glyph_id = match v.glyph_id {
+                Some(gid) => Some(*new_ids.get(&gid)?),
+                None => None,
+            };
+            Some(VariationMapping { glyph_id, ..v })
+        })
+        .collect();
+
+    let has_char = |ch: char| mappings.contains_key(&(ch as u32));
+    let chars = mappings.keys().filter_map(|&code| char::from_u32(code));
+    os2.unicode_range = hl_os2::unicode_range(chars);
+    os2.code_page_range = hl_os2::code_page_range(has_char);
+    let first_char = mappings.keys().next().copied().unwrap_or(0);
+    let last_char = mappings.keys().next_back().copied().unwrap_or(0);
+    os2.first_char_index = first_char.min(0xffff) as u16;
+    os2.last_char_index = last_char.min(0xffff) as u16;
+
+    let cmap = cmap::Table::from_raw(hl_cmap::Table {
+        subtables: vec![hl_cmap::subtable_from_mappings(mappings)],
+        mapping: vec![(hl_cmap::Encoding::Unicode, 0)],
+        variations,
+    });
+
+    post.glyph_names = post.glyph_names.map(|names| retain_kept(names, &kept));
+    let extra_tables = (extra_tables.into_iter())
+        .map(|table| {
+            let remap = match &table.tag {
+                b"GDEF" => layout::remap_gdef,
+                b"GSUB" => layout::remap_gsub,
+                b"GPOS" => layout::remap_gpos,
+                _ => return Ok(table),
+            };
+            let data = remap(&table.data, &new_ids).map_err(|e| layout_error(e, table.tag))?;
+            Ok(RawTable::new(table.tag, data))
+        })
+        .collect::<Result<Vec<_>, SubsetError>>()?;
+    let hdmx = hdmx.map(|mut hdmx| {
+        for record in &mut hdmx.records {
+            record.widths = retain_kept(std::mem::take(&mut record.widths), &kept);
+        }
+        hdmx
+    });
+
+    Ok(FontFile {
+        head,
+        hhea,
+        hmtx,
+        cmap,
+        name,
+        os2,
+        post,
+        outline: Outline::TrueType(TrueTypeTables {
+            glyf,
+            loca,
+            maxp,
+            fpgm,
+            prep,
+            cvt,
+        }),
+        gasp,
+        hdmx,
+        // The bounds of the full font still cover the glyphs of the subset.
+        vdmx,
+        dsig,
+        extra_tables,
+    })
+}
+
+/// Keep the items indexed by the kept glyph IDs, in order.
+fn retain_kept<T>(items: Vec<T>, kept: &BTreeSet<u32>) -> Vec<T> {
+    (items.into_iter().enumerate())
+        .filter(|&(gid, _)| kept.contains(&(gid as u32)))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+fn layout_error(error: layout::Error, tag: [u8; 4]) -> SubsetError {
+    match error {
+        layout::Error::Malformed => SubsetError::MalformedTable(tag),
+        layout::Error::Unsupported => SubsetError::UnsupportedTable(tag),
+        layout::Error::Overflow => SubsetError::TableOverflow(tag),
+    }
+}
+
+/// Decode the character mappings and the variation sequences of a `cmap`
+/// table.
+fn decode_cmap(table: &cmap::Table) -> Result<(Mappings, Vec<VariationMapping>), SubsetError> {
+    let malformed = |_| SubsetError::MalformedTable(*b"cmap");
+    let mut mappings = Mappings::new();
+    let mut variations = vec![];
+    for subtable in &table.subtables {
+        let mut buf = vec![];
+        subtable.write(&mut buf);
+        match subtable {
+            cmap::Subtable::Format4(_) => mappings.extend(read_format4(&buf).map_err(malformed)?),
+            cmap::Subtable::Format12(_) => mappings.extend(read_format12(&buf).map_err(malformed)?),
+            cmap::Subtable::Format14(_) => {
+                for s in read_format14(&buf).map_err(malformed)? {
+                    variations.extend(s.default.into_iter().map(|code| VariationMapping {
+                        code,
+                        selector: s.selector,
+                        glyph_id: None,
+                    }));
+                    variations.extend(s.non_default.into_iter().map(|(code, gid)| {
+                        VariationMapping {
+                            code,
+                            selector: s.selector,
+                            glyph_id: Some(gid.into()),
+                        }
+                    }));
+                }
+            }
+        }
+    }
+    Ok((mappings, variations))
+}
+
+#[test]
+fn test_subset() {
+    use crate::{model::RawTable, validate};
+
+    // The compound glyph of 'B' pulls in its component, the glyph of 'A'.
+    let font = subset(validate::test_font(), ['B', 'C']).unwrap();
+    let Outline::TrueType(tt) = &font.outline else {
+        unreachable!()
+    };
+    assert_eq!(tt.glyf.glyphs.len(), 3);
+    assert_eq!(tt.maxp.n_glyphs, 3);
+    let glyf::Glyph::Compound(glyph) = &tt.glyf.glyphs[2] else {
+        panic!("the compound glyph should be kept");
+    };
+    assert_eq!(glyph.components[0].glyph_index, 1);
+    let (mappings, _) = decode_cmap(&font.cmap).unwrap();
+    assert_eq!(mappings.into_iter().collect::<Vec<_>>(), [(0x42, 2)]);
+    assert_eq!(
+        (font.os2.first_char_index, font.os2.last_char_index),
+        (0x42, 0x42)
+    );
+    assert_eq!((font.head.y_min, font.head.y_max), (0, 800));
+
+    // The average advance is taken over the glyphs of the subset.
+    let mut font = validate::test_font();
+    font.os2.x_avg_char_width = 0;
+    let font = subset(font, [' ', 'A']).unwrap();
+    assert_eq!(font.os2.x_avg_char_width, 500);
+    let (mappings, _) = decode_cmap(&font.cmap).unwrap();
+    assert_eq!(
+        mappings.into_iter().collect::<Vec<_>>(),
+        [(0x20, 2), (0x41, 1)]
+    );
+    assert_eq!((font.head.y_min, font.head.y_max), (0, 700));
+    assert_eq!(font.hmtx.metrics.len(), 1);
+    assert_eq!(validate::font_file(&font).unwrap(), []);
+
+    // Subtables are decoded again from their bytes, which may not make sense.
+    let mut font = validate::test_font();
+    font.cmap.subtables[0] = cmap::Subtable::Format12(cmap::fmt12::Table {
+        groups: vec![cmap::fmt12::SequentialMapGroup {
+            start_code: 0x42,
+            end_code: 0x41,
+            start_glyph_id: 1,
+        }],
+    });
+    assert!(matches!(
+        subset(font, ['A']),
+        Err(SubsetError::MalformedTable(tag)) if &tag == b"cmap"
+    ));
+
+    // A single substitution of the glyph of ' ' by the one of 'A' pulls the
+    // latter into the subset, and is remapped to the new glyph IDs.
+    let words = |words: &[u16]| {
+        words
+            .iter()
+            .flat_map(|w| w.to_be_bytes())
+            .collect::<Vec<_>>()
+    };
+    let mut font = validate::test_font();
+    font.extra_tables.push(RawTable::new(*b"meta", vec![]));
+    let gsub = words(&[
+        1, 0, 10, 12, 14, 0, 0, 1, 4, 1, 0, 1, 8, 1, 6, 0xfffe, 1, 1, 3,
+    ]);
+    font.extra_tables.push(RawTable::new(*b"GSUB", gsub));
+    let font = subset(font, [' ']).unwrap();
+    let Outline::TrueType(tt) = &font.outline else {
+        unreachable!()
+    };
+    assert_eq!(tt.glyf.glyphs.len(), 3);
+    let gsub = words(&[
+        1, 0, 10, 12, 14, 0, 0, 1, 4, 1, 0, 1, 8, 1, 6, 0xffff, 1, 1, 2,
+    ]);
+    assert_eq!(font.extra_tables[1].data, gsub);
+    assert_eq!(validate::font_file(&font).unwrap(), []);
+
+    // Other tables referring to glyph IDs can't be remapped, and are not
+    // silently dropped either.
+    let mut font = validate::test_font();
+    font.extra_tables.push(RawTable::new(*b"kern", vec![]));
+    assert!(matches!(
+        subset(font, ['A']),
+        Err(SubsetError::UnsupportedTable(tag)) if &tag == b"kern"
+    ));
+
+    let mut font = validate::test_font();
+    font.extra_tables.push(RawTable::new(*b"GPOS", vec![]));
+    assert!(matches!(
+        subset(font, ['A']),
+        Err(SubsetError::MalformedTable(tag)) if &tag == b"GPOS"
+    ));
+}
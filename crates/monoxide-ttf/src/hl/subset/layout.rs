@@ -0,0 +1,1313 @@
+//! Remapping of the glyph IDs in the layout tables `GDEF`, `GSUB` and `GPOS`.
+//!
+//! The tables are decoded as far as they refer to glyphs and written again.
+//! Coverage tables lose the glyphs that are not kept, along with the records
+//! they index, and so do class definitions and the rules matching glyph
+//! sequences. Lookups are never dropped, even when all their subtables end up
+//! empty, so that the lookup indices of the features and of the contextual
+//! lookups stay valid. Anchors, device tables and the other parts that don't
+//! refer to glyphs are copied as they are.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/chapter2>
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bytes::BufMut;
+
+use crate::read::{u16_at, u32_at};
+
+/// Why a layout table could not be remapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Error {
+    Malformed,
+    /// The table has feature variations, an item variation store or feature
+    /// parameters of an unknown format.
+    Unsupported,
+    /// An offset of the written table doesn't fit in its field.
+    Overflow,
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn get_u16(data: &[u8], at: usize) -> Result<u16> {
+    u16_at(data, at).ok_or(Error::Malformed)
+}
+
+fn get_u32(data: &[u8], at: usize) -> Result<u32> {
+    u32_at(data, at).ok_or(Error::Malformed)
+}
+
+fn get_u16s(data: &[u8], at: usize, count: usize) -> Result<Vec<u16>> {
+    (0..count).map(|ix| get_u16(data, at + 2 * ix)).collect()
+}
+
+fn get_bytes(data: &[u8], at: usize, len: usize) -> Result<&[u8]> {
+    data.get(at..at + len).ok_or(Error::Malformed)
+}
+
+/// The subtable at the 16-bit offset stored at `at`, or `None` for a null
+/// offset.
+fn subtable(data: &[u8], at: usize) -> Result<Option<&[u8]>> {
+    match get_u16(data, at)? {
+        0 => Ok(None),
+        offset => data
+            .get(offset as usize..)
+            .map(Some)
+            .ok_or(Error::Malformed),
+    }
+}
+
+/// The subtable at the 16-bit offset stored at `at`, which must not be null.
+fn required(data: &[u8], at: usize) -> Result<&[u8]> {
+    subtable(data, at)?.ok_or(Error::Malformed)
+}
+
+/// A table being written, with the subtables it links to by offset.
+#[derive(Default)]
+struct Table {
+    data: Vec<u8>,
+    links: Vec<Link>,
+}
+
+/// An offset field of a [`Table`], and the subtable it points to.
+struct Link {
+    at: usize,
+    wide: bool,
+    table: Table,
+}
+
+impl Table {
+    fn opaque(data: &[u8]) -> Self {
+        Self {
+            data: data.to_vec(),
+            links: vec![],
+        }
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.data.put_u16(value);
+    }
+
+    fn put_u16s(&mut self, values: &[u16]) {
+        values.iter().for_each(|&value| self.put_u16(value));
+    }
+
+    fn put_offset16(&mut self, table: Option<Table>) {
+        self.put_link(table, false);
+    }
+
+    fn put_offset32(&mut self, table: Option<Table>) {
+        self.put_link(table, true);
+    }
+
+    fn put_link(&mut self, table: Option<Table>, wide: bool) {
+        let at = self.data.len();
+        if wide {
+            self.data.put_u32(0);
+        } else {
+            self.data.put_u16(0);
+        }
+        if let Some(table) = table {
+            self.links.push(Link { at, wide, table });
+        }
+    }
+
+    /// Write the table followed by its subtables, depth first. Shared
+    /// subtables of the original table are written once per link.
+    fn write(self) -> Result<Vec<u8>> {
+        let mut out = self.data;
+        for link in self.links {
+            if !out.len().is_multiple_of(2) {
+                out.push(0);
+            }
+            let offset = out.len();
+            if link.wide {
+                let offset = u32::try_from(offset).map_err(|_| Error::Overflow)?;
+                out[link.at..link.at + 4].copy_from_slice(&offset.to_be_bytes());
+            } else {
+                let offset = u16::try_from(offset).map_err(|_| Error::Overflow)?;
+                out[link.at..link.at + 2].copy_from_slice(&offset.to_be_bytes());
+            }
+            out.extend(link.table.write()?);
+        }
+        Ok(out)
+    }
+}
+
+/// The glyphs of a Coverage table, in coverage index order.
+fn read_coverage(data: &[u8]) -> Result<Vec<u16>> {
+    let count = get_u16(data, 2)? as usize;
+    match get_u16(data, 0)? {
+        1 => get_u16s(data, 4, count),
+        2 => {
+            let mut glyphs = vec![];
+            for ix in 0..count {
+                let [start, end] = [0, 2].map(|field| get_u16(data, 4 + 6 * ix + field));
+                let (start, end) = (start?, end?);
+                if start > end {
+                    return Err(Error::Malformed);
+                }
+                glyphs.extend(start..=end);
+            }
+            Ok(glyphs)
+        }
+        _ => Err(Error::Malformed),
+    }
+}
+
+/// The glyphs of a class definition table with their classes, leaving out
+/// class 0.
+fn read_class_def(data: &[u8]) -> Result<Vec<(u16, u16)>> {
+    let mut classes = vec![];
+    match get_u16(data, 0)? {
+        1 => {
+            let (start, count) = (get_u16(data, 2)?, get_u16(data, 4)?);
+            for (ix, class) in get_u16s(data, 6, count as usize)?.into_iter().enumerate() {
+                let glyph = (start as usize + ix)
+                    .try_into()
+                    .map_err(|_| Error::Malformed)?;
+                classes.push((glyph, class));
+            }
+        }
+        2 => {
+            for ix in 0..get_u16(data, 2)? as usize {
+                let [start, end, class] = [0, 2, 4].map(|field| get_u16(data, 4 + 6 * ix + field));
+                let (start, end, class) = (start?, end?, class?);
+                if start > end {
+                    return Err(Error::Malformed);
+                }
+                classes.extend((start..=end).map(|glyph| (glyph, class)));
+            }
+        }
+        _ => return Err(Error::Malformed),
+    }
+    classes.retain(|&(_, class)| class != 0);
+    Ok(classes)
+}
+
+/// Group consecutive glyphs with the same value into ranges.
+fn ranges<T: Copy + PartialEq>(glyphs: impl IntoIterator<Item = (u16, T)>) -> Vec<(u16, u16, T)> {
+    let mut ranges: Vec<(u16, u16, T)> = vec![];
+    for (glyph, value) in glyphs {
+        match ranges.last_mut() {
+            Some((_, end, v)) if end.checked_add(1) == Some(glyph) && *v == value => *end = glyph,
+            _ => ranges.push((glyph, glyph, value)),
+        }
+    }
+    ranges
+}
+
+fn is_sorted_strictly(glyphs: impl IntoIterator<Item = u16>) -> bool {
+    let glyphs = glyphs.into_iter().collect::<Vec<_>>();
+    glyphs.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// A Coverage table of glyphs in their coverage order, which must be sorted,
+/// in whichever format is smaller.
+fn coverage_table(glyphs: &[u16]) -> Result<Table> {
+    if !is_sorted_strictly(glyphs.iter().copied()) {
+        return Err(Error::Malformed);
+    }
+    let ranges = ranges(glyphs.iter().map(|&glyph| (glyph, ())));
+    let mut table = Table::default();
+    if 3 * ranges.len() < glyphs.len() {
+        table.put_u16s(&[2, ranges.len() as u16]);
+        let mut index = 0;
+        for (start, end, ()) in ranges {
+            table.put_u16s(&[start, end, index]);
+            index += end - start + 1;
+        }
+    } else {
+        table.put_u16s(&[1, glyphs.len() as u16]);
+        table.put_u16s(glyphs);
+    }
+    Ok(table)
+}
+
+/// A class definition table of sorted glyphs with their classes, in
+/// whichever format is smaller.
+fn class_def_table(classes: &[(u16, u16)]) -> Result<Table> {
+    if !is_sorted_strictly(classes.iter().map(|&(glyph, _)| glyph)) {
+        return Err(Error::Malformed);
+    }
+    let ranges = ranges(classes.iter().copied());
+    let mut table = Table::default();
+    match (classes.first(), classes.last()) {
+        (Some(&(first, _)), Some(&(last, _))) if last - first < 3 * ranges.len() as u16 => {
+            table.put_u16s(&[1, first, last - first + 1]);
+            let mut classes = classes.iter().peekable();
+            for glyph in first..=last {
+                let class = classes
+                    .next_if(|&&(g, _)| g == glyph)
+                    .map_or(0, |&(_, c)| c);
+                table.put_u16(class);
+            }
+        }
+        _ => {
+            table.put_u16s(&[2, ranges.len() as u16]);
+            for (start, end, class) in ranges {
+                table.put_u16s(&[start, end, class]);
+            }
+        }
+    }
+    Ok(table)
+}
+
+/// A glyph count followed by the glyphs.
+fn glyph_array(glyphs: &[u16]) -> Table {
+    let mut table = Table::default();
+    table.put_u16(glyphs.len() as u16);
+    table.put_u16s(glyphs);
+    table
+}
+
+fn device(data: &[u8]) -> Result<Table> {
+    let len = match get_u16(data, 4)? {
+        format @ 1..=3 => {
+            let (start, end) = (get_u16(data, 0)?, get_u16(data, 2)?);
+            let sizes = (end as usize + 1).saturating_sub(start as usize);
+            6 + 2 * (sizes << format).div_ceil(16)
+        }
+        // A variation index.
+        0x8000 => 6,
+        _ => return Err(Error::Malformed),
+    };
+    Ok(Table::opaque(get_bytes(data, 0, len)?))
+}
+
+fn anchor(data: &[u8]) -> Result<Table> {
+    match get_u16(data, 0)? {
+        1 => Ok(Table::opaque(get_bytes(data, 0, 6)?)),
+        2 => Ok(Table::opaque(get_bytes(data, 0, 8)?)),
+        3 => {
+            let mut table = Table::opaque(get_bytes(data, 0, 6)?);
+            for at in [6, 8] {
+                table.put_offset16(subtable(data, at)?.map(device).transpose()?);
+            }
+            Ok(table)
+        }
+        _ => Err(Error::Malformed),
+    }
+}
+
+fn value_record_size(format: u16) -> usize {
+    2 * (format & 0xff).count_ones() as usize
+}
+
+/// Copy a value record of the given format, whose device tables are located
+/// relative to `parent`.
+fn copy_value_record(table: &mut Table, parent: &[u8], at: usize, format: u16) -> Result<()> {
+    let fields = (0..8).filter(|bit| format & (1 << bit) != 0);
+    for (ix, bit) in fields.enumerate() {
+        let at = at + 2 * ix;
+        if bit < 4 {
+            table.put_u16(get_u16(parent, at)?);
+        } else {
+            table.put_offset16(subtable(parent, at)?.map(device).transpose()?);
+        }
+    }
+    Ok(())
+}
+
+fn lang_sys(data: &[u8]) -> Result<Table> {
+    let count = get_u16(data, 4)? as usize;
+    Ok(Table::opaque(get_bytes(data, 0, 6 + 2 * count)?))
+}
+
+fn script_list(data: &[u8]) -> Result<Table> {
+    let count = get_u16(data, 0)? as usize;
+    let mut table = Table::default();
+    table.put_u16(count as u16);
+    for ix in 0..count {
+        let script = required(data, 2 + 6 * ix + 4)?;
+        let mut script_table = Table::default();
+        script_table.put_offset16(subtable(script, 0)?.map(lang_sys).transpose()?);
+        let lang_sys_count = get_u16(script, 2)? as usize;
+        script_table.put_u16(lang_sys_count as u16);
+        for jx in 0..lang_sys_count {
+            script_table.data.extend(get_bytes(script, 4 + 6 * jx, 4)?);
+            script_table.put_offset16(Some(lang_sys(required(script, 4 + 6 * jx + 4)?)?));
+        }
+        table.data.extend(get_bytes(data, 2 + 6 * ix, 4)?);
+        table.put_offset16(Some(script_table));
+    }
+    Ok(table)
+}
+
+fn feature_params(tag: &[u8], data: &[u8]) -> Result<Table> {
+    let len = match tag {
+        b"size" => 10,
+        [b's', b's', ..] => 4,
+        [b'c', b'v', ..] => 14 + 3 * get_u16(data, 12)? as usize,
+        _ => return Err(Error::Unsupported),
+    };
+    Ok(Table::opaque(get_bytes(data, 0, len)?))
+}
+
+fn feature_list(data: &[u8]) -> Result<Table> {
+    let count = get_u16(data, 0)? as usize;
+    let mut table = Table::default();
+    table.put_u16(count as u16);
+    for ix in 0..count {
+        let tag = get_bytes(data, 2 + 6 * ix, 4)?;
+        let feature = required(data, 2 + 6 * ix + 4)?;
+        let mut feature_table = Table::default();
+        let params = subtable(feature, 0)?.map(|params| feature_params(tag, params));
+        feature_table.put_offset16(params.transpose()?);
+        let lookup_count = get_u16(feature, 2)? as usize;
+        feature_table
+            .data
+            .extend(get_bytes(feature, 2, 2 + 2 * lookup_count)?);
+        table.data.extend(tag);
+        table.put_offset16(Some(feature_table));
+    }
+    Ok(table)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Gsub,
+    Gpos,
+}
+
+impl Layout {
+    fn extension_type(self) -> u16 {
+        match self {
+            Layout::Gsub => 7,
+            Layout::Gpos => 9,
+        }
+    }
+}
+
+/// The lookup type and the subtables of a lookup.
+type Lookup<'a> = (u16, Vec<&'a [u8]>);
+
+/// The lookups of a `GSUB` or `GPOS` table, with the extension subtables
+/// resolved.
+fn lookups(table: &[u8], layout: Layout) -> Result<Vec<Lookup<'_>>> {
+    let list = required(table, 8)?;
+    let mut lookups = vec![];
+    for ix in 0..get_u16(list, 0)? as usize {
+        let lookup = required(list, 2 + 2 * ix)?;
+        let mut lookup_type = get_u16(lookup, 0)?;
+        let count = get_u16(lookup, 4)? as usize;
+        let mut subtables = (0..count)
+            .map(|jx| required(lookup, 6 + 2 * jx))
+            .collect::<Result<Vec<_>>>()?;
+        if lookup_type == layout.extension_type() {
+            let mut extended = None;
+            for subtable in &mut subtables {
+                let (subtable_type, inner) = extension(subtable, layout)?;
+                if extended
+                    .replace(subtable_type)
+                    .is_some_and(|t| t != subtable_type)
+                {
+                    return Err(Error::Malformed);
+                }
+                *subtable = inner;
+            }
+            lookup_type = extended.unwrap_or(lookup_type);
+        }
+        lookups.push((lookup_type, subtables));
+    }
+    Ok(lookups)
+}
+
+/// The lookup type and the subtable an extension subtable points to.
+fn extension(data: &[u8], layout: Layout) -> Result<(u16, &[u8])> {
+    let lookup_type = get_u16(data, 2)?;
+    let offset = get_u32(data, 4)? as usize;
+    if get_u16(data, 0)? != 1 || lookup_type == layout.extension_type() {
+        return Err(Error::Malformed);
+    }
+    Ok((lookup_type, data.get(offset..).ok_or(Error::Malformed)?))
+}
+
+/// Check the version of a `GSUB` or `GPOS` table.
+fn check_layout_header(table: &[u8]) -> Result<()> {
+    let (major, minor) = (get_u16(table, 0)?, get_u16(table, 2)?);
+    if major != 1 {
+        return Err(Error::Malformed);
+    }
+    if minor >= 1 && get_u32(table, 10)? != 0 {
+        return Err(Error::Unsupported);
+    }
+    Ok(())
+}
+
+/// The glyphs the substitutions of a `GSUB` table produce from the given
+/// glyphs. Contextual substitutions are assumed to apply in any context.
+pub(super) fn substituted(gsub: &[u8], glyphs: &BTreeSet<u32>) -> Result<BTreeSet<u32>> {
+    check_layout_header(gsub)?;
+    let has = |glyph: u16| glyphs.contains(&(glyph as u32));
+    let mut produced = BTreeSet::new();
+    for (lookup_type, subtables) in lookups(gsub, Layout::Gsub)? {
+        for data in subtables {
+            let coverage = || read_coverage(required(data, 2)?);
+            match (lookup_type, get_u16(data, 0)?) {
+                (1, 1) => {
+                    let delta = get_u16(data, 4)?;
+                    let covered = coverage()?.into_iter().filter(|&glyph| has(glyph));
+                    produced.extend(covered.map(|glyph| glyph.wrapping_add(delta) as u32));
+                }
+                (1, 2) => {
+                    let substitutes = get_u16s(data, 6, get_u16(data, 4)? as usize)?;
+                    let pairs = coverage()?.into_iter().zip(substitutes);
+                    produced.extend(pairs.filter(|&(g, _)| has(g)).map(|(_, s)| s as u32));
+                }
+                // Multiple and alternate substitutions.
+                (2 | 3, 1) => {
+                    for (ix, glyph) in coverage()?.into_iter().enumerate() {
+                        if has(glyph) {
+                            let array = required(data, 6 + 2 * ix)?;
+                            let substitutes = get_u16s(array, 2, get_u16(array, 0)? as usize)?;
+                            produced.extend(substitutes.into_iter().map(u32::from));
+                        }
+                    }
+                }
+                (4, 1) => {
+                    for (ix, glyph) in coverage()?.into_iter().enumerate() {
+                        if !has(glyph) {
+                            continue;
+                        }
+                        let set = required(data, 6 + 2 * ix)?;
+                        for jx in 0..get_u16(set, 0)? as usize {
+                            let ligature = required(set, 2 + 2 * jx)?;
+                            let count = get_u16(ligature, 2)?.checked_sub(1);
+                            let components =
+                                get_u16s(ligature, 4, count.ok_or(Error::Malformed)? as usize)?;
+                            if components.into_iter().all(has) {
+                                produced.insert(get_u16(ligature, 0)? as u32);
+                            }
+                        }
+                    }
+                }
+                (5 | 6, 1..=3) => {}
+                (8, 1) => {
+                    let backtrack = get_u16(data, 4)? as usize;
+                    let lookahead = get_u16(data, 6 + 2 * backtrack)? as usize;
+                    let at = 8 + 2 * (backtrack + lookahead);
+                    let substitutes = get_u16s(data, at + 2, get_u16(data, at)? as usize)?;
+                    let pairs = coverage()?.into_iter().zip(substitutes);
+                    produced.extend(pairs.filter(|&(g, _)| has(g)).map(|(_, s)| s as u32));
+                }
+                _ => return Err(Error::Malformed),
+            }
+        }
+    }
+    Ok(produced)
+}
+
+/// Rewrite a `GSUB` table for the new glyph IDs.
+pub(super) fn remap_gsub(data: &[u8], new_ids: &BTreeMap<u32, u32>) -> Result<Vec<u8>> {
+    Remap { new_ids }.layout_table(data, Layout::Gsub)
+}
+
+/// Rewrite a `GPOS` table for the new glyph IDs.
+pub(super) fn remap_gpos(data: &[u8], new_ids: &BTreeMap<u32, u32>) -> Result<Vec<u8>> {
+    Remap { new_ids }.layout_table(data, Layout::Gpos)
+}
+
+/// Rewrite a `GDEF` table for the new glyph IDs.
+pub(super) fn remap_gdef(data: &[u8], new_ids: &BTreeMap<u32, u32>) -> Result<Vec<u8>> {
+    Remap { new_ids }.gdef(data)?.write()
+}
+
+/// Rewrites the parts of the layout tables referring to glyphs.
+struct Remap<'a> {
+    /// The new glyph IDs of the kept glyphs by their old IDs.
+    new_ids: &'a BTreeMap<u32, u32>,
+}
+
+impl Remap<'_> {
+    fn glyph(&self, glyph: u16) -> Option<u16> {
+        self.new_ids.get(&(glyph as u32)).map(|&glyph| glyph as u16)
+    }
+
+    /// The new glyph IDs of a glyph sequence, or `None` if any glyph is not
+    /// kept.
+    fn glyphs(&self, glyphs: &[u16]) -> Option<Vec<u16>> {
+        glyphs.iter().map(|&glyph| self.glyph(glyph)).collect()
+    }
+
+    /// The kept glyphs of the Coverage table at the offset stored at `at`, by
+    /// their new IDs, and their old coverage indices.
+    fn coverage(&self, data: &[u8], at: usize) -> Result<(Vec<u16>, Vec<usize>)> {
+        let glyphs = read_coverage(required(data, at)?)?;
+        Ok((glyphs.into_iter().enumerate())
+            .filter_map(|(ix, glyph)| Some((self.glyph(glyph)?, ix)))
+            .unzip())
+    }
+
+    /// The Coverage table at the offset stored at `at`, with the glyphs that
+    /// are not kept left out.
+    fn coverage_table(&self, data: &[u8], at: usize) -> Result<Table> {
+        coverage_table(&self.coverage(data, at)?.0)
+    }
+
+    /// The class definition table at the offset stored at `at`, with the
+    /// glyphs that are not kept left out.
+    fn class_def_table(&self, data: &[u8], at: usize) -> Result<Option<Table>> {
+        let Some(class_def) = subtable(data, at)? else {
+            return Ok(None);
+        };
+        let classes = (read_class_def(class_def)?.into_iter())
+            .filter_map(|(glyph, class)| Some((self.glyph(glyph)?, class)))
+            .collect::<Vec<_>>();
+        class_def_table(&classes).map(Some)
+    }
+
+    fn gdef(&self, data: &[u8]) -> Result<Table> {
+        let (major, minor) = (get_u16(data, 0)?, get_u16(data, 2)?.min(3));
+        if major != 1 {
+            return Err(Error::Malformed);
+        }
+        if minor >= 3 && get_u32(data, 14)? != 0 {
+            return Err(Error::Unsupported);
+        }
+        let mut table = Table::default();
+        table.put_u16s(&[major, minor]);
+        table.put_offset16(self.class_def_table(data, 4)?);
+        let attach_list = subtable(data, 6)?.map(|list| self.attach_list(list));
+        table.put_offset16(attach_list.transpose()?);
+        let lig_caret_list = subtable(data, 8)?.map(|list| self.lig_caret_list(list));
+        table.put_offset16(lig_caret_list.transpose()?);
+        table.put_offset16(self.class_def_table(data, 10)?);
+        if minor >= 2 {
+            let mark_glyph_sets = subtable(data, 12)?.map(|sets| self.mark_glyph_sets(sets));
+            table.put_offset16(mark_glyph_sets.transpose()?);
+        }
+        if minor >= 3 {
+            table.put_offset32(None);
+        }
+        Ok(table)
+    }
+
+    fn attach_list(&self, data: &[u8]) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 0)?;
+        let mut points = vec![];
+        for ix in indices {
+            let data = required(data, 4 + 2 * ix)?;
+            let count = get_u16(data, 0)? as usize;
+            points.push(Some(Table::opaque(get_bytes(data, 0, 2 + 2 * count)?)));
+        }
+        let mut table = Table::default();
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        put_subtables(&mut table, points);
+        Ok(table)
+    }
+
+    fn lig_caret_list(&self, data: &[u8]) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 0)?;
+        let mut lig_glyphs = vec![];
+        for ix in indices {
+            let carets = required(data, 4 + 2 * ix)?;
+            let mut caret_tables = vec![];
+            for jx in 0..get_u16(carets, 0)? as usize {
+                let caret = required(carets, 2 + 2 * jx)?;
+                let mut caret_table = Table::opaque(get_bytes(caret, 0, 4)?);
+                match get_u16(caret, 0)? {
+                    1 | 2 => {}
+                    3 => caret_table.put_offset16(Some(device(required(caret, 4)?)?)),
+                    _ => return Err(Error::Malformed),
+                }
+                caret_tables.push(Some(caret_table));
+            }
+            let mut lig_glyph = Table::default();
+            put_subtables(&mut lig_glyph, caret_tables);
+            lig_glyphs.push(Some(lig_glyph));
+        }
+        let mut table = Table::default();
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        put_subtables(&mut table, lig_glyphs);
+        Ok(table)
+    }
+
+    fn mark_glyph_sets(&self, data: &[u8]) -> Result<Table> {
+        let count = get_u16(data, 2)? as usize;
+        let mut table = Table::default();
+        table.put_u16s(&[get_u16(data, 0)?, count as u16]);
+        for ix in 0..count {
+            let offset = get_u32(data, 4 + 4 * ix)? as usize;
+            let coverage = data.get(offset..).ok_or(Error::Malformed)?;
+            let glyphs = read_coverage(coverage)?.into_iter();
+            let glyphs = glyphs
+                .filter_map(|glyph| self.glyph(glyph))
+                .collect::<Vec<_>>();
+            table.put_offset32(Some(coverage_table(&glyphs)?));
+        }
+        Ok(table)
+    }
+
+    /// Rewrite a `GSUB` or `GPOS` table. The feature variations of version
+    /// 1.1 are not supported, so the table is always written as version 1.0.
+    fn layout_table(&self, data: &[u8], layout: Layout) -> Result<Vec<u8>> {
+        check_layout_header(data)?;
+        let mut table = Table::default();
+        table.put_u16s(&[1, 0]);
+        table.put_offset16(Some(script_list(required(data, 4)?)?));
+        table.put_offset16(Some(feature_list(required(data, 6)?)?));
+
+        let list = required(data, 8)?;
+        let count = get_u16(list, 0)? as usize;
+        let mut list_table = Table::default();
+        list_table.put_u16(count as u16);
+        for ix in 0..count {
+            list_table.put_offset16(Some(self.lookup(required(list, 2 + 2 * ix)?, layout)?));
+        }
+        table.put_offset16(Some(list_table));
+        table.write()
+    }
+
+    fn lookup(&self, data: &[u8], layout: Layout) -> Result<Table> {
+        let (lookup_type, flag) = (get_u16(data, 0)?, get_u16(data, 2)?);
+        let count = get_u16(data, 4)? as usize;
+        let mut table = Table::default();
+        table.put_u16s(&[lookup_type, flag, count as u16]);
+        for ix in 0..count {
+            let subtable = required(data, 6 + 2 * ix)?;
+            let subtable = if lookup_type == layout.extension_type() {
+                let (extended_type, inner) = extension(subtable, layout)?;
+                let mut extension = Table::default();
+                extension.put_u16s(&[1, extended_type]);
+                extension.put_offset32(Some(self.subtable(inner, extended_type, layout)?));
+                extension
+            } else {
+                self.subtable(subtable, lookup_type, layout)?
+            };
+            table.put_offset16(Some(subtable));
+        }
+        // The mark filtering set.
+        if flag & 0x10 != 0 {
+            table.put_u16(get_u16(data, 6 + 2 * count)?);
+        }
+        Ok(table)
+    }
+
+    fn subtable(&self, data: &[u8], lookup_type: u16, layout: Layout) -> Result<Table> {
+        match (layout, lookup_type, get_u16(data, 0)?) {
+            (Layout::Gsub, 1, format @ 1..=2) => self.single_subst(data, format),
+            (Layout::Gsub, 2 | 3, 1) => self.sequence_subst(data),
+            (Layout::Gsub, 4, 1) => self.ligature_subst(data),
+            (Layout::Gsub, 5, format) | (Layout::Gpos, 7, format) => {
+                self.context(data, format, false)
+            }
+            (Layout::Gsub, 6, format) | (Layout::Gpos, 8, format) => {
+                self.context(data, format, true)
+            }
+            (Layout::Gsub, 8, 1) => self.reverse_chain_subst(data),
+            (Layout::Gpos, 1, format @ 1..=2) => self.single_pos(data, format),
+            (Layout::Gpos, 2, 1) => self.pair_pos_glyphs(data),
+            (Layout::Gpos, 2, 2) => self.pair_pos_classes(data),
+            (Layout::Gpos, 3, 1) => self.cursive_pos(data),
+            (Layout::Gpos, 4 | 6, 1) => self.mark_pos(data, false),
+            (Layout::Gpos, 5, 1) => self.mark_pos(data, true),
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    fn single_subst(&self, data: &[u8], format: u16) -> Result<Table> {
+        let glyphs = read_coverage(required(data, 2)?)?;
+        let substitutes = match format {
+            1 => {
+                let delta = get_u16(data, 4)?;
+                glyphs.iter().map(|g| g.wrapping_add(delta)).collect()
+            }
+            _ => get_u16s(data, 6, get_u16(data, 4)? as usize)?,
+        };
+        if substitutes.len() != glyphs.len() {
+            return Err(Error::Malformed);
+        }
+        let (glyphs, substitutes): (Vec<_>, Vec<_>) = (glyphs.into_iter().zip(substitutes))
+            .filter_map(|(glyph, substitute)| Some((self.glyph(glyph)?, self.glyph(substitute)?)))
+            .unzip();
+
+        let mut table = Table::default();
+        let deltas = (glyphs.iter().zip(&substitutes)).map(|(g, s)| s.wrapping_sub(*g));
+        let deltas = deltas.collect::<BTreeSet<_>>();
+        if deltas.len() <= 1 {
+            table.put_u16(1);
+            table.put_offset16(Some(coverage_table(&glyphs)?));
+            table.put_u16(deltas.first().copied().unwrap_or(0));
+        } else {
+            table.put_u16(2);
+            table.put_offset16(Some(coverage_table(&glyphs)?));
+            table.put_u16(substitutes.len() as u16);
+            table.put_u16s(&substitutes);
+        }
+        Ok(table)
+    }
+
+    /// A multiple or alternate substitution, which both map each covered
+    /// glyph to a glyph array.
+    fn sequence_subst(&self, data: &[u8]) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 2)?;
+        let (mut covered, mut arrays) = (vec![], vec![]);
+        for (glyph, ix) in glyphs.into_iter().zip(indices) {
+            let array = required(data, 6 + 2 * ix)?;
+            let substitutes = get_u16s(array, 2, get_u16(array, 0)? as usize)?;
+            // The closure keeps the substitutes of the kept glyphs, unless
+            // they are out of range.
+            if let Some(substitutes) = self.glyphs(&substitutes) {
+                covered.push(glyph);
+                arrays.push(Some(glyph_array(&substitutes)));
+            }
+        }
+        let mut table = Table::default();
+        table.put_u16(1);
+        table.put_offset16(Some(coverage_table(&covered)?));
+        put_subtables(&mut table, arrays);
+        Ok(table)
+    }
+
+    fn ligature_subst(&self, data: &[u8]) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 2)?;
+        let mut sets = vec![];
+        for ix in indices {
+            let set = required(data, 6 + 2 * ix)?;
+            let mut ligatures = vec![];
+            for jx in 0..get_u16(set, 0)? as usize {
+                let ligature = required(set, 2 + 2 * jx)?;
+                let count = get_u16(ligature, 2)?
+                    .checked_sub(1)
+                    .ok_or(Error::Malformed)?;
+                let components = get_u16s(ligature, 4, count as usize)?;
+                let glyph = self.glyph(get_u16(ligature, 0)?);
+                if let (Some(glyph), Some(components)) = (glyph, self.glyphs(&components)) {
+                    let mut ligature_table = Table::default();
+                    ligature_table.put_u16s(&[glyph, count + 1]);
+                    ligature_table.put_u16s(&components);
+                    ligatures.push(Some(ligature_table));
+                }
+            }
+            let mut set_table = Table::default();
+            put_subtables(&mut set_table, ligatures);
+            sets.push(Some(set_table));
+        }
+        let mut table = Table::default();
+        table.put_u16(1);
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        put_subtables(&mut table, sets);
+        Ok(table)
+    }
+
+    fn reverse_chain_subst(&self, data: &[u8]) -> Result<Table> {
+        let glyphs = read_coverage(required(data, 2)?)?;
+        let backtrack = get_u16(data, 4)? as usize;
+        let lookahead = get_u16(data, 6 + 2 * backtrack)? as usize;
+        let at = 8 + 2 * (backtrack + lookahead);
+        let substitutes = get_u16s(data, at + 2, get_u16(data, at)? as usize)?;
+        if substitutes.len() != glyphs.len() {
+            return Err(Error::Malformed);
+        }
+        let (glyphs, substitutes): (Vec<_>, Vec<_>) = (glyphs.into_iter().zip(substitutes))
+            .filter_map(|(glyph, substitute)| Some((self.glyph(glyph)?, self.glyph(substitute)?)))
+            .unzip();
+
+        let mut table = Table::default();
+        table.put_u16(1);
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        let mut at = 4;
+        for _ in 0..2 {
+            let count = get_u16(data, at)? as usize;
+            table.put_u16(count as u16);
+            for ix in 0..count {
+                table.put_offset16(Some(self.coverage_table(data, at + 2 + 2 * ix)?));
+            }
+            at += 2 + 2 * count;
+        }
+        table.put_u16(substitutes.len() as u16);
+        table.put_u16s(&substitutes);
+        Ok(table)
+    }
+
+    /// A contextual lookup, or a chained one if `chained`.
+    fn context(&self, data: &[u8], format: u16, chained: bool) -> Result<Table> {
+        let mut table = Table::default();
+        table.put_u16(format);
+        match format {
+            1 => {
+                let (glyphs, indices) = self.coverage(data, 2)?;
+                let sets = (indices.into_iter())
+                    .map(|ix| {
+                        let set = subtable(data, 6 + 2 * ix)?;
+                        set.map(|set| self.rule_set(set, chained, true)).transpose()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                table.put_offset16(Some(coverage_table(&glyphs)?));
+                put_subtables(&mut table, sets);
+            }
+            2 => {
+                table.put_offset16(Some(self.coverage_table(data, 2)?));
+                // The backtrack and lookahead class definitions come around
+                // the input one.
+                let class_defs = if chained { 3 } else { 1 };
+                for ix in 0..class_defs {
+                    table.put_offset16(self.class_def_table(data, 4 + 2 * ix)?);
+                }
+                let at = 4 + 2 * class_defs;
+                let count = get_u16(data, at)? as usize;
+                table.put_u16(count as u16);
+                for ix in 0..count {
+                    let set = subtable(data, at + 2 + 2 * ix)?;
+                    table.put_offset16(
+                        set.map(|set| self.rule_set(set, chained, false))
+                            .transpose()?,
+                    );
+                }
+            }
+            3 if !chained => {
+                let (count, lookup_count) =
+                    (get_u16(data, 2)? as usize, get_u16(data, 4)? as usize);
+                table.put_u16s(&[count as u16, lookup_count as u16]);
+                for ix in 0..count {
+                    table.put_offset16(Some(self.coverage_table(data, 6 + 2 * ix)?));
+                }
+                table
+                    .data
+                    .extend(get_bytes(data, 6 + 2 * count, 4 * lookup_count)?);
+            }
+            3 => {
+                let mut at = 2;
+                // The backtrack, input and lookahead coverages.
+                for _ in 0..3 {
+                    let count = get_u16(data, at)? as usize;
+                    table.put_u16(count as u16);
+                    for ix in 0..count {
+                        table.put_offset16(Some(self.coverage_table(data, at + 2 + 2 * ix)?));
+                    }
+                    at += 2 + 2 * count;
+                }
+                let lookup_count = get_u16(data, at)? as usize;
+                table
+                    .data
+                    .extend(get_bytes(data, at, 2 + 4 * lookup_count)?);
+            }
+            _ => return Err(Error::Malformed),
+        }
+        Ok(table)
+    }
+
+    /// A rule set of a contextual lookup, whose rules match either glyphs,
+    /// which are remapped, or classes, which are copied.
+    fn rule_set(&self, data: &[u8], chained: bool, glyphs: bool) -> Result<Table> {
+        let mut rules = vec![];
+        for ix in 0..get_u16(data, 0)? as usize {
+            let rule = self.rule(required(data, 2 + 2 * ix)?, chained, glyphs)?;
+            rules.extend(rule.map(Some));
+        }
+        let mut table = Table::default();
+        put_subtables(&mut table, rules);
+        Ok(table)
+    }
+
+    /// A rule of a contextual lookup, or `None` if it matches a glyph that is
+    /// not kept.
+    fn rule(&self, data: &[u8], chained: bool, glyphs: bool) -> Result<Option<Table>> {
+        let sequence = |at: usize, count: usize| -> Result<Option<Vec<u16>>> {
+            let sequence = get_u16s(data, at, count)?;
+            Ok(if glyphs {
+                self.glyphs(&sequence)
+            } else {
+                Some(sequence)
+            })
+        };
+        let mut table = Table::default();
+        if chained {
+            let mut at = 0;
+            for input in [false, true, false] {
+                let count = get_u16(data, at)?;
+                // The first input glyph is the covered one, and is left out.
+                let len = if input {
+                    count.checked_sub(1)
+                } else {
+                    Some(count)
+                };
+                let len = len.ok_or(Error::Malformed)? as usize;
+                let Some(sequence) = sequence(at + 2, len)? else {
+                    return Ok(None);
+                };
+                table.put_u16(count);
+                table.put_u16s(&sequence);
+                at += 2 + 2 * len;
+            }
+            let lookup_count = get_u16(data, at)? as usize;
+            table
+                .data
+                .extend(get_bytes(data, at, 2 + 4 * lookup_count)?);
+        } else {
+            let (count, lookup_count) = (get_u16(data, 0)?, get_u16(data, 2)? as usize);
+            let len = count.checked_sub(1).ok_or(Error::Malformed)? as usize;
+            let Some(sequence) = sequence(4, len)? else {
+                return Ok(None);
+            };
+            table.put_u16s(&[count, lookup_count as u16]);
+            table.put_u16s(&sequence);
+            table
+                .data
+                .extend(get_bytes(data, 4 + 2 * len, 4 * lookup_count)?);
+        }
+        Ok(Some(table))
+    }
+
+    fn single_pos(&self, data: &[u8], format: u16) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 2)?;
+        let value_format = get_u16(data, 4)?;
+        let mut table = Table::default();
+        table.put_u16(format);
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        table.put_u16(value_format);
+        if format == 1 {
+            copy_value_record(&mut table, data, 6, value_format)?;
+        } else {
+            table.put_u16(indices.len() as u16);
+            for ix in indices {
+                let at = 8 + value_record_size(value_format) * ix;
+                copy_value_record(&mut table, data, at, value_format)?;
+            }
+        }
+        Ok(table)
+    }
+
+    /// A pair adjustment by glyph pairs.
+    fn pair_pos_glyphs(&self, data: &[u8]) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 2)?;
+        let formats = [get_u16(data, 4)?, get_u16(data, 6)?];
+        let sizes = formats.map(value_record_size);
+        let mut sets = vec![];
+        for ix in indices {
+            let set = required(data, 10 + 2 * ix)?;
+            let mut pairs = vec![];
+            for jx in 0..get_u16(set, 0)? as usize {
+                let at = 2 + (2 + sizes[0] + sizes[1]) * jx;
+                pairs.extend(self.glyph(get_u16(set, at)?).map(|second| (second, at)));
+            }
+            let mut set_table = Table::default();
+            set_table.put_u16(pairs.len() as u16);
+            for (second, at) in pairs {
+                set_table.put_u16(second);
+                copy_value_record(&mut set_table, set, at + 2, formats[0])?;
+                copy_value_record(&mut set_table, set, at + 2 + sizes[0], formats[1])?;
+            }
+            sets.push(Some(set_table));
+        }
+        let mut table = Table::default();
+        table.put_u16(1);
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        table.put_u16s(&formats);
+        put_subtables(&mut table, sets);
+        Ok(table)
+    }
+
+    /// A pair adjustment by class pairs.
+    fn pair_pos_classes(&self, data: &[u8]) -> Result<Table> {
+        let formats = [get_u16(data, 4)?, get_u16(data, 6)?];
+        let sizes = formats.map(value_record_size);
+        let mut table = Table::default();
+        table.put_u16(2);
+        table.put_offset16(Some(self.coverage_table(data, 2)?));
+        table.put_u16s(&formats);
+        table.put_offset16(self.class_def_table(data, 8)?);
+        table.put_offset16(self.class_def_table(data, 10)?);
+        let counts = [get_u16(data, 12)?, get_u16(data, 14)?];
+        table.put_u16s(&counts);
+        for ix in 0..counts[0] as usize * counts[1] as usize {
+            let at = 16 + (sizes[0] + sizes[1]) * ix;
+            copy_value_record(&mut table, data, at, formats[0])?;
+            copy_value_record(&mut table, data, at + sizes[0], formats[1])?;
+        }
+        Ok(table)
+    }
+
+    fn cursive_pos(&self, data: &[u8]) -> Result<Table> {
+        let (glyphs, indices) = self.coverage(data, 2)?;
+        let mut table = Table::default();
+        table.put_u16(1);
+        table.put_offset16(Some(coverage_table(&glyphs)?));
+        table.put_u16(indices.len() as u16);
+        for ix in indices {
+            // The entry and exit anchors.
+            for at in [6 + 4 * ix, 8 + 4 * ix] {
+                table.put_offset16(subtable(data, at)?.map(anchor).transpose()?);
+            }
+        }
+        Ok(table)
+    }
+
+    /// A mark-to-base or mark-to-mark attachment, or a mark-to-ligature one
+    /// if `ligatures`.
+    fn mark_pos(&self, data: &[u8], ligatures: bool) -> Result<Table> {
+        let (marks, mark_indices) = self.coverage(data, 2)?;
+        let (bases, base_indices) = self.coverage(data, 4)?;
+        let class_count = get_u16(data, 6)? as usize;
+        let mut table = Table::default();
+        table.put_u16(1);
+        table.put_offset16(Some(coverage_table(&marks)?));
+        table.put_offset16(Some(coverage_table(&bases)?));
+        table.put_u16(class_count as u16);
+
+        let mark_array = required(data, 8)?;
+        let mut mark_array_table = Table::default();
+        mark_array_table.put_u16(mark_indices.len() as u16);
+        for ix in mark_indices {
+            mark_array_table.put_u16(get_u16(mark_array, 2 + 4 * ix)?);
+            mark_array_table.put_offset16(Some(anchor(required(mark_array, 4 + 4 * ix)?)?));
+        }
+        table.put_offset16(Some(mark_array_table));
+
+        let base_array = required(data, 10)?;
+        let base_array_table = if ligatures {
+            let mut attachments = vec![];
+            for ix in base_indices {
+                let attach = required(base_array, 2 + 2 * ix)?;
+                let components = 0..get_u16(attach, 0)? as usize;
+                attachments.push(Some(anchor_matrix(attach, components, class_count)?));
+            }
+            let mut table = Table::default();
+            put_subtables(&mut table, attachments);
+            table
+        } else {
+            anchor_matrix(base_array, base_indices, class_count)?
+        };
+        table.put_offset16(Some(base_array_table));
+        Ok(table)
+    }
+}
+
+/// Write the count of and the offsets to the given subtables.
+fn put_subtables(table: &mut Table, subtables: Vec<Option<Table>>) {
+    table.put_u16(subtables.len() as u16);
+    for subtable in subtables {
+        table.put_offset16(subtable);
+    }
+}
+
+/// Copy the given rows of an array with an anchor per mark class, such as a
+/// BaseArray.
+fn anchor_matrix(
+    data: &[u8],
+    rows: impl IntoIterator<Item = usize>,
+    class_count: usize,
+) -> Result<Table> {
+    let rows = rows.into_iter().collect::<Vec<_>>();
+    let mut table = Table::default();
+    table.put_u16(rows.len() as u16);
+    for row in rows {
+        for class in 0..class_count {
+            let at = 2 + 2 * (row * class_count + class);
+            table.put_offset16(subtable(data, at)?.map(anchor).transpose()?);
+        }
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+fn words(words: &[u16]) -> Table {
+    let mut table = Table::default();
+    table.put_u16s(words);
+    table
+}
+
+/// A `GSUB` or `GPOS` table with the given lookups, each with one subtable.
+#[cfg(test)]
+fn test_layout_table(
+    script_list: Table,
+    feature_list: Table,
+    lookups: Vec<(u16, Table)>,
+) -> Vec<u8> {
+    let lookups = (lookups.into_iter())
+        .map(|(lookup_type, subtable)| {
+            let mut lookup = words(&[lookup_type, 0, 1]);
+            lookup.put_offset16(Some(subtable));
+            Some(lookup)
+        })
+        .collect();
+    let mut lookup_list = Table::default();
+    put_subtables(&mut lookup_list, lookups);
+    let mut table = words(&[1, 0]);
+    for list in [script_list, feature_list, lookup_list] {
+        table.put_offset16(Some(list));
+    }
+    table.write().unwrap()
+}
+
+#[cfg(test)]
+fn test_ids(kept: &[u32]) -> BTreeMap<u32, u32> {
+    (kept.iter().enumerate())
+        .map(|(new, &old)| (old, new as u32))
+        .collect()
+}
+
+#[test]
+fn test_remap_gsub() {
+    let coverage = |glyphs: &[u16]| Some(coverage_table(glyphs).unwrap());
+
+    // The glyphs are f, i, fi, l, fl, f.alt and i.alt, from glyph 1 on.
+    let mut ligature_set = Table::default();
+    put_subtables(
+        &mut ligature_set,
+        vec![Some(words(&[3, 2, 2])), Some(words(&[5, 2, 4]))],
+    );
+    let mut ligature = words(&[1]);
+    ligature.put_offset16(coverage(&[1]));
+    put_subtables(&mut ligature, vec![Some(ligature_set)]);
+    let mut single = words(&[1]);
+    single.put_offset16(coverage(&[1, 2]));
+    single.put_u16(5);
+    // Apply the single substitution to f after i or l.
+    let mut chain = words(&[3, 1]);
+    chain.put_offset16(coverage(&[2, 4]));
+    chain.put_u16(1);
+    chain.put_offset16(coverage(&[1]));
+    chain.put_u16s(&[0, 1, 0, 1]);
+
+    let mut script = Table::default();
+    script.put_offset16(Some(words(&[0, 0xffff, 1, 0])));
+    script.put_u16(0);
+    let mut script_list = words(&[1]);
+    script_list.data.extend(b"DFLT");
+    script_list.put_offset16(Some(script));
+    let mut feature_list = words(&[1]);
+    feature_list.data.extend(b"liga");
+    feature_list.put_offset16(Some(words(&[0, 3, 0, 1, 2])));
+    let gsub = test_layout_table(
+        script_list,
+        feature_list,
+        vec![(4, ligature), (1, single), (6, chain)],
+    );
+
+    let all = test_ids(&(0..8).collect::<Vec<_>>());
+    assert_eq!(remap_gsub(&gsub, &all).unwrap(), gsub);
+
+    // fl is not produced without l.
+    let glyphs = BTreeSet::from([0, 1, 2]);
+    assert_eq!(
+        substituted(&gsub, &glyphs).unwrap(),
+        BTreeSet::from([3, 6, 7])
+    );
+
+    let remapped = remap_gsub(&gsub, &test_ids(&[0, 1, 2, 3, 6, 7])).unwrap();
+    assert_eq!(&remapped[..4], [0, 1, 0, 0]);
+    let lookups = lookups(&remapped, Layout::Gsub).unwrap();
+    let subtables = lookups.iter().map(|(_, s)| s[0]).collect::<Vec<_>>();
+    let set = required(subtables[0], 6).unwrap();
+    assert_eq!(get_u16(set, 0), Ok(1));
+    assert_eq!(get_u16s(required(set, 2).unwrap(), 0, 3), Ok(vec![3, 2, 2]));
+    assert_eq!(
+        read_coverage(required(subtables[1], 2).unwrap()),
+        Ok(vec![1, 2])
+    );
+    assert_eq!(get_u16s(subtables[1], 0, 3), Ok(vec![1, 6, 3]));
+    assert_eq!(
+        read_coverage(required(subtables[2], 4).unwrap()),
+        Ok(vec![2])
+    );
+    // The features are copied as they are.
+    let features = required(&remapped, 6).unwrap();
+    assert_eq!(
+        get_u16s(required(features, 6).unwrap(), 0, 5),
+        Ok(vec![0, 3, 0, 1, 2])
+    );
+}
+
+#[test]
+fn test_remap_gpos() {
+    let coverage = |glyphs: &[u16]| Some(coverage_table(glyphs).unwrap());
+
+    // Kern glyph 1 against glyphs 2 and 5, and glyph 4 against glyph 1.
+    let mut pair = words(&[1]);
+    pair.put_offset16(coverage(&[1, 4]));
+    pair.put_u16s(&[4, 0]);
+    let sets = vec![
+        Some(words(&[2, 2, -50i16 as u16, 5, -60i16 as u16])),
+        Some(words(&[1, 1, -70i16 as u16])),
+    ];
+    put_subtables(&mut pair, sets);
+
+    // Attach mark 3 to bases 1 and 4, with a device table for the former.
+    let mut mark = words(&[1]);
+    mark.put_offset16(coverage(&[3]));
+    mark.put_offset16(coverage(&[1, 4]));
+    mark.put_u16(1);
+    let mut mark_array = words(&[1, 0]);
+    mark_array.put_offset16(Some(words(&[1, 250, 600])));
+    mark.put_offset16(Some(mark_array));
+    let mut anchor = words(&[3, 100, 700]);
+    anchor.put_offset16(Some(words(&[12, 12, 1, 0x4000])));
+    anchor.put_offset16(None);
+    let mut base_array = words(&[2]);
+    base_array.put_offset16(Some(anchor));
+    base_array.put_offset16(Some(words(&[1, 400, 700])));
+    mark.put_offset16(Some(base_array));
+
+    let gpos = test_layout_table(words(&[0]), words(&[0]), vec![(2, pair), (4, mark)]);
+    let all = test_ids(&(0..6).collect::<Vec<_>>());
+    assert_eq!(remap_gpos(&gpos, &all).unwrap(), gpos);
+
+    let remapped = remap_gpos(&gpos, &test_ids(&[0, 1, 3, 5])).unwrap();
+    let lookups = lookups(&remapped, Layout::Gpos).unwrap();
+    let subtables = lookups.iter().map(|(_, s)| s[0]).collect::<Vec<_>>();
+    assert_eq!(
+        read_coverage(required(subtables[0], 2).unwrap()),
+        Ok(vec![1])
+    );
+    let set = required(subtables[0], 10).unwrap();
+    assert_eq!(get_u16s(set, 0, 3), Ok(vec![1, 3, -60i16 as u16]));
+
+    assert_eq!(
+        read_coverage(required(subtables[1], 2).unwrap()),
+        Ok(vec![2])
+    );
+    assert_eq!(
+        read_coverage(required(subtables[1], 4).unwrap()),
+        Ok(vec![1])
+    );
+    let base_array = required(subtables[1], 10).unwrap();
+    assert_eq!(get_u16(base_array, 0), Ok(1));
+    let anchor = required(base_array, 2).unwrap();
+    assert_eq!(get_u16s(anchor, 0, 3), Ok(vec![3, 100, 700]));
+    assert_eq!(
+        get_u16s(required(anchor, 6).unwrap(), 0, 4),
+        Ok(vec![12, 12, 1, 0x4000])
+    );
+}
+
+#[test]
+fn test_remap_gdef() {
+    let classes = [(1, 1), (2, 1), (3, 2), (4, 3), (5, 3)];
+    let mut lig_glyph = words(&[1]);
+    lig_glyph.put_offset16(Some(words(&[1, 500])));
+    let mut lig_caret_list = Table::default();
+    lig_caret_list.put_offset16(Some(coverage_table(&[3]).unwrap()));
+    put_subtables(&mut lig_caret_list, vec![Some(lig_glyph)]);
+    let mut mark_glyph_sets = words(&[1, 1]);
+    mark_glyph_sets.put_offset32(Some(coverage_table(&[4, 5]).unwrap()));
+
+    let mut gdef = words(&[1, 2]);
+    gdef.put_offset16(Some(class_def_table(&classes).unwrap()));
+    gdef.put_offset16(None);
+    gdef.put_offset16(Some(lig_caret_list));
+    gdef.put_offset16(None);
+    gdef.put_offset16(Some(mark_glyph_sets));
+    let gdef = gdef.write().unwrap();
+    let all = test_ids(&(0..6).collect::<Vec<_>>());
+    assert_eq!(remap_gdef(&gdef, &all).unwrap(), gdef);
+
+    let remapped = remap_gdef(&gdef, &test_ids(&[0, 1, 3, 5])).unwrap();
+    let class_def = required(&remapped, 4).unwrap();
+    assert_eq!(read_class_def(class_def), Ok(vec![(1, 1), (2, 2), (3, 3)]));
+    let lig_caret_list = required(&remapped, 8).unwrap();
+    assert_eq!(
+        read_coverage(required(lig_caret_list, 0).unwrap()),
+        Ok(vec![2])
+    );
+    let mark_glyph_sets = required(&remapped, 12).unwrap();
+    let offset = get_u32(mark_glyph_sets, 4).unwrap() as usize;
+    assert_eq!(read_coverage(&mark_glyph_sets[offset..]), Ok(vec![3]));
+
+    // Variations can't be remapped.
+    let mut gdef = gdef;
+    gdef[3] = 3;
+    gdef.splice(14..14, [0, 0, 0, 1]);
+    assert_eq!(remap_gdef(&gdef, &all).unwrap_err(), Error::Unsupported);
+}
@@ -1,3 +1,6 @@
+pub mod dump;
 pub mod hl;
 pub mod model;
+pub(crate) mod read;
 pub mod util;
+pub mod validate;
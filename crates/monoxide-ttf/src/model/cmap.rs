@@ -1,9 +1,9 @@
 //! Model of the `cmap` table.
 //!
 //! The `cmap` table has a lot of different formats due to historical reasons.
-//! This implementation, however, currently only support writing formats 4 and
-//! 12, as they are the most commonly used today. Support for format 13 might be
-//! added in the future.
+//! This implementation, however, currently only support writing formats 4, 12
+//! and 14, as they are the most commonly used today. Support for format 13
+//! might be added in the future.
 //!
 //! The following quote comes from Microsoft's OpenType specification:
 //!
@@ -18,6 +18,7 @@
 //! <https://learn.microsoft.com/zh-cn/typography/opentype/spec/cmap>
 
 pub mod fmt12;
+pub mod fmt14;
 pub mod fmt4;
 
 use super::encoding::EncodingRecord;
@@ -26,6 +27,7 @@ use super::encoding::EncodingRecord;
 pub enum Subtable {
     Format4(fmt4::Table),
     Format12(fmt12::Table),
+    Format14(fmt14::Table),
 }
 
 impl Subtable {
@@ -33,6 +35,7 @@ impl Subtable {
         match self {
             Subtable::Format4(table) => table.byte_length(),
             Subtable::Format12(table) => table.byte_length(),
+            Subtable::Format14(table) => table.byte_length(),
         }
     }
 
@@ -40,6 +43,7 @@ impl Subtable {
         match self {
             Subtable::Format4(table) => table.write(writer),
             Subtable::Format12(table) => table.write(writer),
+            Subtable::Format14(table) => table.write(writer),
         }
     }
 }
@@ -65,7 +69,8 @@ impl Table {
     /// representation ([`Table`]).
     ///
     /// This function will generate the correct format 4 and 12 subtables based
-    /// on the input raw table. Subtable sharing is explicitly supported here.
+    /// on the input raw table, and a format 14 subtable if there are any
+    /// variation sequences. Subtable sharing is explicitly supported here.
     pub fn from_raw(mut raw: crate::hl::cmap::Table) -> Self {
         // Ensure that the character mappings within each subtable is sorted.
         for subtable in &mut raw.subtables {
@@ -102,6 +107,17 @@ impl Table {
             }
         }
 
+        if !raw.variations.is_empty() {
+            let idx = subtables.len();
+            let subtable = Subtable::Format14(fmt14::Table::from_raw(&raw.variations));
+            subtables.push(subtable);
+            for fmt14_tbl in
+                crate::hl::cmap::Encoding::UnicodeVariations.to_encoding_records_fmt14()
+            {
+                mapping.push((*fmt14_tbl, idx));
+            }
+        }
+
         // The mapping should be sorted by platform ID and encoding ID,
         // as required by the OpenType specification.
         mapping.sort_by_key(|x| (x.0.platform_id as u16, x.0.encoding_id));
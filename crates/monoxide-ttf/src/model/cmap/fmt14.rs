@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use bytes::BufMut;
+
+use crate::hl::cmap as hl;
+
+/// A range of characters whose variation sequences use the default glyph.
+#[derive(Debug, PartialEq, Eq)]
+struct UnicodeRange {
+    start_unicode_value: u32, // u24
+    /// The number of characters in the range, minus one.
+    additional_count: u8,
+}
+
+/// A variation sequence mapped to a specific glyph.
+#[derive(Debug, PartialEq, Eq)]
+struct UvsMapping {
+    unicode_value: u32, // u24
+    glyph_id: u16,
+}
+
+#[derive(Debug)]
+struct VariationSelectorRecord {
+    var_selector: u32, // u24
+    default_uvs: Vec<UnicodeRange>,
+    non_default_uvs: Vec<UvsMapping>,
+}
+
+impl VariationSelectorRecord {
+    fn default_uvs_byte_length(&self) -> usize {
+        if self.default_uvs.is_empty() {
+            0
+        } else {
+            // numUnicodeValueRanges + ranges
+            4 + 4 * self.default_uvs.len()
+        }
+    }
+
+    fn non_default_uvs_byte_length(&self) -> usize {
+        if self.non_default_uvs.is_empty() {
+            0
+        } else {
+            // numUVSMappings + mappings
+            4 + 5 * self.non_default_uvs.len()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Table {
+    // format: u16 = 14,
+    // length: u32,
+    // num_var_selector_records: u32,
+    records: Vec<VariationSelectorRecord>,
+}
+
+fn put_u24(writer: &mut impl BufMut, value: u32) {
+    writer.put_uint(value as u64, 3);
+}
+
+impl Table {
+    fn header_byte_length(&self) -> usize {
+        // format + length + numVarSelectorRecords + records
+        2 + 4 + 4 + 11 * self.records.len()
+    }
+
+    pub fn byte_length(&self) -> usize {
+        self.header_byte_length()
+            + self
+                .records
+                .iter()
+                .map(|r| r.default_uvs_byte_length() + r.non_default_uvs_byte_length())
+                .sum::<usize>()
+    }
+
+    pub fn write(&self, writer: &mut impl BufMut) {
+        writer.put_u16(14);
+        writer.put_u32(self.byte_length() as u32);
+        writer.put_u32(self.records.len() as u32);
+
+        // Offsets are from the beginning of this subtable. A zero offset
+        // means the corresponding list is absent.
+        let mut offset = self.header_byte_length();
+        for record in &self.records {
+            put_u24(writer, record.var_selector);
+            for len in [
+                record.default_uvs_byte_length(),
+                record.non_default_uvs_byte_length(),
+            ] {
+                writer.put_u32(if len == 0 { 0 } else { offset as u32 });
+                offset += len;
+            }
+        }
+
+        for record in &self.records {
+            if !record.default_uvs.is_empty() {
+                writer.put_u32(record.default_uvs.len() as u32);
+                for range in &record.default_uvs {
+                    put_u24(writer, range.start_unicode_value);
+                    writer.put_u8(range.additional_count);
+                }
+            }
+            if !record.non_default_uvs.is_empty() {
+                writer.put_u32(record.non_default_uvs.len() as u32);
+                for mapping in &record.non_default_uvs {
+                    put_u24(writer, mapping.unicode_value);
+                    writer.put_u16(mapping.glyph_id);
+                }
+            }
+        }
+    }
+
+    /// Convert a list of variation sequences to a format 14 subtable.
+    pub fn from_raw(tbl: &hl::VariationSubTable) -> Self {
+        // Group the sequences by selector, which also sorts them as required.
+        let mut by_selector = BTreeMap::<u32, Vec<&hl::VariationMapping>>::new();
+        for mapping in tbl {
+            by_selector
+                .entry(mapping.selector)
+                .or_default()
+                .push(mapping);
+        }
+
+        let records = by_selector
+            .into_iter()
+            .map(|(var_selector, mut mappings)| {
+                mappings.sort_by_key(|m| m.code);
+
+                let mut default_uvs = Vec::<UnicodeRange>::new();
+                let mut non_default_uvs = vec![];
+                for mapping in mappings {
+                    let Some(glyph_id) = mapping.glyph_id else {
+                        // Extend the last range if possible
+                        if let Some(last) = default_uvs.last_mut()
+                            && last.start_unicode_value + last.additional_count as u32 + 1
+                                == mapping.code
+                            && last.additional_count < u8::MAX
+                        {
+                            last.additional_count += 1;
+                        } else {
+                            default_uvs.push(UnicodeRange {
+                                start_unicode_value: mapping.code,
+                                additional_count: 0,
+                            });
+                        }
+                        continue;
+                    };
+                    non_default_uvs.push(UvsMapping {
+                        unicode_value: mapping.code,
+                        glyph_id: glyph_id as u16,
+                    });
+                }
+
+                VariationSelectorRecord {
+                    var_selector,
+                    default_uvs,
+                    non_default_uvs,
+                }
+            })
+            .collect();
+
+        Table { records }
+    }
+}
+
+#[test]
+fn test_variation_sequences() {
+    let raw = vec![
+        hl::VariationMapping {
+            code: '0' as u32,
+            selector: 0xfe00,
+            glyph_id: Some(20),
+        },
+        hl::VariationMapping {
+            code: 0x2615,
+            selector: 0xfe0e,
+            glyph_id: None,
+        },
+        hl::VariationMapping {
+            code: 0x2614,
+            selector: 0xfe0e,
+            glyph_id: None,
+        },
+    ];
+    let table = Table::from_raw(&raw);
+    assert_eq!(table.records.len(), 2);
+    assert_eq!(
+        table.records[1].default_uvs,
+        [UnicodeRange {
+            start_unicode_value: 0x2614,
+            additional_count: 1,
+        }]
+    );
+
+    let mut buf = vec![];
+    table.write(&mut buf);
+    assert_eq!(buf.len(), table.byte_length());
+    #[rustfmt::skip]
+    assert_eq!(
+        buf,
+        [
+            0, 14, 0, 0, 0, 49, 0, 0, 0, 2,
+            // VS1: non-default only
+            0x00, 0xfe, 0x00, 0, 0, 0, 0, 0, 0, 0, 32,
+            // VS15: default only
+            0x00, 0xfe, 0x0e, 0, 0, 0, 41, 0, 0, 0, 0,
+            // VS1 non-default UVS
+            0, 0, 0, 1, 0x00, 0x00, 0x30, 0, 20,
+            // VS15 default UVS
+            0, 0, 0, 1, 0x00, 0x26, 0x14, 1,
+        ]
+    );
+}
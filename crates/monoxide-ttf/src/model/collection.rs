@@ -0,0 +1,141 @@
+//! Writing several fonts into a TrueType Collection (`.ttc`).
+//!
+//! Fonts in a collection have their own offset tables, but may point to the
+//! same table data. Tables that serialize to identical bytes, such as `cmap`
+//! or `post` shared by the styles of a family, are only written once.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/otff#font-collections>
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+
+use super::{
+    FontFile, TableRecord, WriteOptions, allocate_tables, offset_table, offset_table_size,
+    serialize_font, write_table_data,
+};
+
+/// The tag of the collection header.
+const TTC_TAG: &[u8; 4] = b"ttcf";
+
+/// Version 1.0 of the collection header, without a digital signature.
+const TTC_VERSION: [u16; 2] = [1, 0];
+
+/// Write the fonts into a TrueType Collection.
+///
+/// The `head` checksum adjustment of each font is calculated as if it were a
+/// standalone font file, as it is ignored in collections anyway.
+pub fn write_collection(
+    fonts: &[FontFile],
+    options: &WriteOptions,
+    mut w: impl std::io::Write,
+) -> std::io::Result<()> {
+    let fonts = fonts
+        .iter()
+        .map(|font| serialize_font(font, options))
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    // TTC header, followed by the offset tables of all fonts
+    let header_size = 12 + 4 * fonts.len();
+    let offset_table_offsets = fonts
+        .iter()
+        .scan(header_size, |offset, font| {
+            let this = *offset;
+            *offset += offset_table_size(font.tables.len());
+            Some(this)
+        })
+        .collect::<Vec<_>>();
+    let data_start = header_size
+        + (fonts.iter())
+            .map(|font| offset_table_size(font.tables.len()))
+            .sum::<usize>();
+    let mut offset = data_start;
+
+    // Allocate table data in the order of the fonts, skipping tables that
+    // have already been written with the same data.
+    let mut allocated = HashMap::<(&[u8; 4], &Bytes), TableRecord>::new();
+    let mut unique_tables = vec![];
+    let mut font_records = vec![];
+    for font in &fonts {
+        let mut records = vec![];
+        for (tag, data) in &font.tables {
+            if let Some(record) = allocated.get(&(tag, data)) {
+                records.push(record.clone());
+                continue;
+            }
+            let (mut new_records, end) = allocate_tables([(tag, data)], offset, options);
+            let record = new_records.pop().expect("one table is allocated");
+            offset = end;
+            allocated.insert((tag, data), record.clone());
+            unique_tables.push((data, record.clone()));
+            records.push(record);
+        }
+        font_records.push(records);
+    }
+
+    let mut header = Vec::with_capacity(header_size);
+    header.extend_from_slice(TTC_TAG);
+    header.extend_from_slice(&TTC_VERSION[0].to_be_bytes());
+    header.extend_from_slice(&TTC_VERSION[1].to_be_bytes());
+    header.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+    for offset in &offset_table_offsets {
+        header.extend_from_slice(&(*offset as u32).to_be_bytes());
+    }
+    w.write_all(&header)?;
+
+    for (font, records) in fonts.iter().zip(&font_records) {
+        w.write_all(&offset_table(font.version, records))?;
+    }
+
+    write_table_data(
+        &mut w,
+        unique_tables.iter().map(|(data, record)| (*data, record)),
+        data_start,
+        options,
+    )
+}
+
+#[test]
+fn test_shared_tables() {
+    use crate::{
+        hl::{
+            builder::{FontBuilder, test_metrics},
+            name::FontNames,
+        },
+        model::glyf::{Glyph, simple::SimpleGlyph},
+    };
+
+    let build = |style: &str| {
+        FontBuilder::new(test_metrics())
+            .glyph(Glyph::Simple(SimpleGlyph::default()), 500)
+            .glyph(Glyph::Simple(SimpleGlyph::default()), 500)
+            .map(' ', 1)
+            .names(FontNames::new("Test", style))
+            .timestamp(std::time::UNIX_EPOCH)
+            .build()
+            .unwrap()
+    };
+    let fonts = [build("Regular"), build("Bold")];
+    let options = WriteOptions::default();
+
+    let mut buf = vec![];
+    write_collection(&fonts, &options, &mut buf).unwrap();
+    assert_eq!(&buf[..4], TTC_TAG);
+    let u32_at = |at: usize| u32::from_be_bytes(buf[at..at + 4].try_into().unwrap());
+    assert_eq!(u32_at(8), 2);
+
+    // The offset tables of the fonts start at the offsets in the header
+    let [regular, bold] = [12, 16].map(|at| &buf[u32_at(at) as usize..]);
+    let table_offset = crate::read::table_offset;
+    assert_eq!(table_offset(regular, b"glyf"), table_offset(bold, b"glyf"));
+    assert_eq!(table_offset(regular, b"cmap"), table_offset(bold, b"cmap"));
+    assert_ne!(table_offset(regular, b"name"), table_offset(bold, b"name"));
+    assert_ne!(table_offset(regular, b"head"), table_offset(bold, b"head"));
+
+    let standalone = fonts.iter().map(|font| {
+        let mut buf = vec![];
+        font.write(&options, &mut buf).unwrap();
+        buf.len()
+    });
+    assert!(buf.len() < standalone.sum::<usize>());
+}
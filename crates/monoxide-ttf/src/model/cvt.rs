@@ -0,0 +1,21 @@
+//! The `cvt ` table.
+
+use super::{ITable, fword};
+
+/// `cvt ` table. Values in font units that can be referenced by instructions.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub values: Vec<fword>,
+}
+
+impl ITable for Table {
+    fn name(&self) -> &'static [u8; 4] {
+        b"cvt "
+    }
+
+    fn write(&self, writer: &mut impl bytes::BufMut) {
+        for &x in &self.values {
+            writer.put_i16(x);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! The `fpgm` table.
+
+use super::ITable;
+
+/// `fpgm` table. The font program, run once before the font is first used.
+/// Usually defines the functions called by the other programs.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    /// The bytecode of the program, see [`super::inst`].
+    pub instructions: Vec<u8>,
+}
+
+impl ITable for Table {
+    fn name(&self) -> &'static [u8; 4] {
+        b"fpgm"
+    }
+
+    fn write(&self, writer: &mut impl bytes::BufMut) {
+        writer.put_slice(&self.instructions);
+    }
+}
@@ -0,0 +1,73 @@
+//! The `gasp` table.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/gasp>
+
+use bitflags::bitflags;
+
+use super::ITable;
+
+pub const VERSION: u16 = 1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Behavior(u16);
+bitflags! {
+    impl Behavior: u16 {
+        /// Use gridfitting.
+        const GRIDFIT = 0x0001;
+        /// Use grayscale rendering.
+        const DOGRAY = 0x0002;
+        /// Use gridfitting with ClearType symmetric smoothing.
+        const SYMMETRIC_GRIDFIT = 0x0004;
+        /// Use smoothing along multiple axes with ClearType.
+        const SYMMETRIC_SMOOTHING = 0x0008;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Range {
+    /// Upper limit of the range, in ppem. The last range should have
+    /// `0xFFFF` as the upper limit.
+    pub range_max_ppem: u16,
+    pub behavior: Behavior,
+}
+
+/// `gasp` table version 1.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    /// Ranges sorted by ascending `range_max_ppem`.
+    pub ranges: Vec<Range>,
+}
+
+impl ITable for Table {
+    fn name(&self) -> &'static [u8; 4] {
+        b"gasp"
+    }
+
+    fn write(&self, writer: &mut impl bytes::BufMut) {
+        writer.put_u16(VERSION);
+        writer.put_u16(self.ranges.len() as u16);
+        for range in &self.ranges {
+            writer.put_u16(range.range_max_ppem);
+            writer.put_u16(range.behavior.bits());
+        }
+    }
+}
+
+#[test]
+fn test_ranges() {
+    let table = Table {
+        ranges: vec![
+            Range {
+                range_max_ppem: 8,
+                behavior: Behavior::DOGRAY,
+            },
+            Range {
+                range_max_ppem: 0xffff,
+                behavior: Behavior::all(),
+            },
+        ],
+    };
+    let mut buf = vec![];
+    table.write(&mut buf);
+    assert_eq!(buf, [0, 1, 0, 2, 0, 8, 0, 2, 0xff, 0xff, 0, 0x0f]);
+}
@@ -2,6 +2,7 @@ use bytes::BufMut;
 use thiserror::Error;
 
 use super::{ITable, fword};
+use crate::util::SizeOnlyBufWriter;
 
 #[derive(Debug, Clone, Default)]
 pub struct GlyphCommon {
@@ -86,6 +87,22 @@ impl Glyph {
             Glyph::Compound(g) => g.write(w),
         }
     }
+
+    /// The size of the glyph data, excluding padding.
+    pub fn byte_length(&self) -> usize {
+        let mut w = SizeOnlyBufWriter::new();
+        self.write(&mut w);
+        w.size()
+    }
+
+    /// Write the glyph data, padded to 2 bytes so that it can be located by
+    /// a short `loca` table.
+    pub fn write_padded(&self, w: &mut impl BufMut) {
+        self.write(w);
+        if !self.byte_length().is_multiple_of(2) {
+            w.put_u8(0);
+        }
+    }
 }
 
 pub struct Table {
@@ -110,7 +127,7 @@ impl ITable for Table {
 
     fn write(&self, w: &mut impl BufMut) {
         for g in &self.glyphs {
-            g.write(w);
+            g.write_padded(w);
         }
     }
 }
@@ -64,6 +64,10 @@ bitflags::bitflags! {
         /// **Only if `Y_SHORT_VECTOR` is set.**
         /// Describes the sign of the value, with a value of 1 equalling positive and a zero value negative.
         const SHORT_Y_SIGN = 0b00100000;
+        /// If set, the contours of the glyph may overlap. Only valid on the first flag of the glyph.
+        ///
+        /// Set it with [`SimpleGlyph::set_overlap_simple`].
+        const OVERLAP_SIMPLE = 0b01000000;
     }
 }
 
@@ -96,6 +100,9 @@ impl std::fmt::Debug for OutlineFlag {
                 f.entry(&"LONG_Y_SAME");
             }
         }
+        if self.intersects(OutlineFlag::OVERLAP_SIMPLE) {
+            f.entry(&"OVERLAP_SIMPLE");
+        }
         f.finish()
     }
 }
@@ -199,6 +206,33 @@ impl SimpleGlyph {
             .map_or(0, |x| x + 1) as usize
     }
 
+    /// Mark the contours of the glyph as overlapping, by setting
+    /// [`OutlineFlag::OVERLAP_SIMPLE`] on the first point. A repeated first
+    /// flag is split, so that the other points don't have it set.
+    pub fn set_overlap_simple(&mut self) {
+        let Some(first) = self.flags.first_mut() else {
+            return;
+        };
+        let flag = first.get_flag();
+        let rest = match *first {
+            FlagOrRepeat::Single(_)
+            | FlagOrRepeat::Repeat {
+                times_minus_1: 0, ..
+            } => None,
+            FlagOrRepeat::Repeat {
+                times_minus_1: 1, ..
+            } => Some(FlagOrRepeat::Single(flag)),
+            FlagOrRepeat::Repeat { times_minus_1, .. } => Some(FlagOrRepeat::Repeat {
+                flag,
+                times_minus_1: times_minus_1 - 1,
+            }),
+        };
+        *first = FlagOrRepeat::Single(flag | OutlineFlag::OVERLAP_SIMPLE);
+        if let Some(rest) = rest {
+            self.flags.insert(1, rest);
+        }
+    }
+
     pub fn n_contours(&self) -> usize {
         self.end_points_of_countours.len()
     }
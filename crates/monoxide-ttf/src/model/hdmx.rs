@@ -0,0 +1,92 @@
+//! The `hdmx` table.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/hdmx>
+
+use super::ITable;
+
+pub const VERSION: u16 = 0;
+
+/// The advance widths of all glyphs at a specific pixel size.
+#[derive(Debug, Clone)]
+pub struct DeviceRecord {
+    pub pixel_size: u8,
+    /// Advance width of each glyph in pixels, one entry per glyph.
+    pub widths: Vec<u8>,
+}
+
+impl DeviceRecord {
+    pub fn max_width(&self) -> u8 {
+        self.widths.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The size of the record, padded to 4 bytes.
+    fn byte_length(n_glyphs: usize) -> usize {
+        (2 + n_glyphs).next_multiple_of(4)
+    }
+}
+
+/// `hdmx` table. All records must have the same number of widths as the
+/// number of glyphs in the font.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    /// Records sorted by ascending pixel size.
+    pub records: Vec<DeviceRecord>,
+}
+
+impl ITable for Table {
+    fn name(&self) -> &'static [u8; 4] {
+        b"hdmx"
+    }
+
+    fn write(&self, writer: &mut impl bytes::BufMut) {
+        let n_glyphs = self.records.first().map_or(0, |r| r.widths.len());
+        let record_size = DeviceRecord::byte_length(n_glyphs);
+
+        writer.put_u16(VERSION);
+        writer.put_i16(self.records.len() as i16);
+        writer.put_i32(record_size as i32);
+        for record in &self.records {
+            debug_assert_eq!(
+                record.widths.len(),
+                n_glyphs,
+                "all device records should have the same number of glyphs"
+            );
+            writer.put_u8(record.pixel_size);
+            writer.put_u8(record.max_width());
+            writer.put_slice(&record.widths);
+            writer.put_bytes(0, record_size - 2 - n_glyphs);
+        }
+    }
+}
+
+#[test]
+fn test_record_padding() {
+    let table = Table {
+        records: vec![
+            DeviceRecord {
+                pixel_size: 8,
+                widths: vec![4, 5, 0],
+            },
+            DeviceRecord {
+                pixel_size: 12,
+                widths: vec![6, 7, 0],
+            },
+        ],
+    };
+    let mut buf = vec![];
+    table.write(&mut buf);
+    // 2 bytes of header and 3 widths, padded to 8 bytes
+    #[rustfmt::skip]
+    assert_eq!(
+        buf,
+        [
+            0, 0, 0, 2, 0, 0, 0, 8,
+            8, 5, 4, 5, 0, 0, 0, 0,
+            12, 7, 6, 7, 0, 0, 0, 0,
+        ]
+    );
+
+    // Records already aligned to 4 bytes are not padded
+    assert_eq!(DeviceRecord::byte_length(2), 4);
+    assert_eq!(DeviceRecord::byte_length(6), 8);
+}
@@ -24,10 +24,12 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone)]
-#[allow(unused)]
+/// The format of the `loca` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IndexToLocFormat {
+    /// Offsets are stored as `u16`, divided by 2.
     Short = 0,
+    /// Offsets are stored as `u32`.
     Long = 1,
 }
 
@@ -65,7 +67,8 @@ pub struct Table {
     pub mac_style: MacStyle,
     pub lowest_rec_ppem: u16,
     // pub font_direction_hint: i16, = 2
-    // pub index_to_loc_format: IndexToLocFormat,
+    /// Overwritten by the font writer to match the `loca` table.
+    pub index_to_loc_format: IndexToLocFormat,
     // pub glyph_data_format: i16, = 0
 }
 
@@ -112,7 +115,7 @@ impl ITable for Table {
         writer.put_u16(self.lowest_rec_ppem);
         // writer.put_i16(self.font_direction_hint);
         writer.put_i16(2);
-        writer.put_u16(IndexToLocFormat::Long as u16);
+        writer.put_u16(self.index_to_loc_format as u16);
         // writer.put_i16(self.glyph_data_format);
         writer.put_i16(0);
     }
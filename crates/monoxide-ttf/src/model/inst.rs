@@ -0,0 +1,840 @@
+//! TrueType instructions.
+//!
+//! This is the bytecode language used by the `fpgm` and `prep` tables, and by
+//! the instructions of each glyph in the `glyf` table. See the [OpenType
+//! spec](https://learn.microsoft.com/en-us/typography/opentype/spec/tt_instructions)
+//! for the meaning of each instruction.
+//!
+//! [`assemble`] and [`disassemble`] convert between [`Instruction`]s and their
+//! binary representation. For analyzing programs, see [`crate::hl::inst`].
+
+use std::fmt;
+
+use bytes::BufMut;
+use thiserror::Error;
+
+macro_rules! count {
+    () => { 0usize };
+    ($head:ident $($tail:ident)*) => { 1usize + count!($($tail)*) };
+}
+
+macro_rules! inst {
+    (
+        $(#[$inst_attr:meta])*
+        $vis:vis // instruction visibility
+        enum $typename:ident { // instruction type name
+            // instructions without operands in the instruction stream
+            $(
+                $(#[$attr:meta])* // Meta attributes
+                $name:ident // instruction name
+                (
+                    $opcode:literal, // opcode of the instruction
+                    $mnemonic:literal, // mnemonic of the instruction
+                    // Stack manipulation, from bottom to top
+                    [ $( $pop:ident )* ] -> [ $( $push:ident )* ]
+                )
+            ),* $(,)?
+            ;
+            // instructions with operands or flags, encoded by hand
+            $(
+                $(#[$xattr:meta])*
+                $xname:ident $( ( $( $xty:ty ),* ) )?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$inst_attr])*
+        $vis enum $typename {
+            $(
+                $(#[$attr])*
+                $name,
+            )*
+            $(
+                $(#[$xattr])*
+                $xname $( ( $( $xty ),* ) )?,
+            )*
+        }
+
+        impl $typename {
+            /// Returns the opcode, mnemonic and the number of popped and
+            /// pushed stack elements of an instruction with a fixed encoding.
+            fn fixed_info(&self) -> Option<(u8, &'static str, usize, usize)> {
+                match self {
+                    $(
+                        $typename::$name => Some((
+                            $opcode,
+                            $mnemonic,
+                            count!($($pop)*),
+                            count!($($push)*),
+                        )),
+                    )*
+                    _ => None,
+                }
+            }
+
+            /// Returns the instruction with a fixed encoding for the opcode.
+            fn from_fixed_opcode(opcode: u8) -> Option<Self> {
+                match opcode {
+                    $( $opcode => Some($typename::$name), )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+/// The axis an instruction works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Y = 0,
+    X = 1,
+}
+
+/// How a vector is set from a line between two points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineDirection {
+    Parallel = 0,
+    Perpendicular = 1,
+}
+
+/// Which outline a coordinate or distance is measured on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outline {
+    /// The current, possibly grid-fitted outline
+    Current = 0,
+    /// The original outline
+    Original = 1,
+}
+
+/// The reference point used by the `SHP`, `SHC` and `SHZ` instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefPoint {
+    /// `rp2` in the zone pointed to by `zp1`
+    Rp2 = 0,
+    /// `rp1` in the zone pointed to by `zp0`
+    Rp1 = 1,
+}
+
+/// The engine compensation applied when rounding a distance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceType {
+    Gray = 0,
+    Black = 1,
+    White = 2,
+    /// Reserved by the spec, kept so that any bytecode can round-trip.
+    Reserved = 3,
+}
+
+impl DistanceType {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => DistanceType::Gray,
+            1 => DistanceType::Black,
+            2 => DistanceType::White,
+            _ => DistanceType::Reserved,
+        }
+    }
+}
+
+/// The flags of the `MDRP` and `MIRP` instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveFlags {
+    /// Set `rp0` to the moved point afterwards.
+    pub set_rp0: bool,
+    /// Keep the distance greater than or equal to the minimum distance.
+    pub min_distance: bool,
+    /// Round the distance (and cut-in the control value for `MIRP`).
+    pub round: bool,
+    pub distance_type: DistanceType,
+}
+
+impl MoveFlags {
+    fn bits(self) -> u8 {
+        (self.set_rp0 as u8) << 4
+            | (self.min_distance as u8) << 3
+            | (self.round as u8) << 2
+            | self.distance_type as u8
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        MoveFlags {
+            set_rp0: bits & 0b10000 != 0,
+            min_distance: bits & 0b1000 != 0,
+            round: bits & 0b100 != 0,
+            distance_type: DistanceType::from_bits(bits),
+        }
+    }
+}
+
+inst! {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Instruction {
+        // Managing the storage area
+        /// Read store
+        Rs(0x43, "RS", [index] -> [value]),
+        /// Write store
+        Ws(0x42, "WS", [index value] -> []),
+
+        // Managing the control value table
+        /// Write control value table in pixel units
+        Wcvtp(0x44, "WCVTP", [index value] -> []),
+        /// Write control value table in funits
+        Wcvtf(0x70, "WCVTF", [index value] -> []),
+        /// Read control value table entry
+        Rcvt(0x45, "RCVT", [index] -> [value]),
+
+        // Managing the graphics state
+        /// Set freedom vector to projection vector
+        Sfvtpv(0x0e, "SFVTPV", [] -> []),
+        /// Set projection vector from stack
+        Spvfs(0x0a, "SPVFS", [x y] -> []),
+        /// Set freedom vector from stack
+        Sfvfs(0x0b, "SFVFS", [x y] -> []),
+        /// Get projection vector
+        Gpv(0x0c, "GPV", [] -> [x y]),
+        /// Get freedom vector
+        Gfv(0x0d, "GFV", [] -> [x y]),
+        /// Set reference point 0
+        Srp0(0x10, "SRP0", [p] -> []),
+        /// Set reference point 1
+        Srp1(0x11, "SRP1", [p] -> []),
+        /// Set reference point 2
+        Srp2(0x12, "SRP2", [p] -> []),
+        /// Set zone pointer 0
+        Szp0(0x13, "SZP0", [zone] -> []),
+        /// Set zone pointer 1
+        Szp1(0x14, "SZP1", [zone] -> []),
+        /// Set zone pointer 2
+        Szp2(0x15, "SZP2", [zone] -> []),
+        /// Set zone pointers
+        Szps(0x16, "SZPS", [zone] -> []),
+        /// Round to half grid
+        Rthg(0x19, "RTHG", [] -> []),
+        /// Round to grid
+        Rtg(0x18, "RTG", [] -> []),
+        /// Round to double grid
+        Rtdg(0x3d, "RTDG", [] -> []),
+        /// Round down to grid
+        Rdtg(0x7d, "RDTG", [] -> []),
+        /// Round up to grid
+        Rutg(0x7c, "RUTG", [] -> []),
+        /// Round off
+        Roff(0x7a, "ROFF", [] -> []),
+        /// Super round
+        Sround(0x76, "SROUND", [n] -> []),
+        /// Super round 45 degrees
+        S45round(0x77, "S45ROUND", [n] -> []),
+        /// Set loop variable
+        Sloop(0x17, "SLOOP", [n] -> []),
+        /// Set minimum distance
+        Smd(0x1a, "SMD", [distance] -> []),
+        /// Instruction execution control
+        Instctrl(0x8e, "INSTCTRL", [selector value] -> []),
+        /// Scan conversion control
+        Scanctrl(0x85, "SCANCTRL", [n] -> []),
+        /// Scan type
+        Scantype(0x8d, "SCANTYPE", [n] -> []),
+        /// Set control value table cut-in
+        Scvtci(0x1d, "SCVTCI", [n] -> []),
+        /// Set single width cut-in
+        Sswci(0x1e, "SSWCI", [n] -> []),
+        /// Set single width
+        Ssw(0x1f, "SSW", [n] -> []),
+        /// Set the auto flip boolean to on
+        Flipon(0x4d, "FLIPON", [] -> []),
+        /// Set the auto flip boolean to off
+        Flipoff(0x4e, "FLIPOFF", [] -> []),
+        /// Set angle weight (deprecated)
+        Sangw(0x7e, "SANGW", [weight] -> []),
+        /// Set delta base in the graphics state
+        Sdb(0x5e, "SDB", [n] -> []),
+        /// Set delta shift in the graphics state
+        Sds(0x5f, "SDS", [n] -> []),
+
+        // Reading and writing data
+        /// Set coordinate from the stack using projection vector and freedom vector
+        Scfs(0x48, "SCFS", [p value] -> []),
+        /// Measure pixels per em
+        Mppem(0x4b, "MPPEM", [] -> [ppem]),
+        /// Measure point size
+        Mps(0x4c, "MPS", [] -> [size]),
+
+        // Managing outlines
+        /// Flip range on
+        Fliprgon(0x81, "FLIPRGON", [low high] -> []),
+        /// Flip range off
+        Fliprgoff(0x82, "FLIPRGOFF", [low high] -> []),
+        /// Moves point p to the intersection of two lines
+        Isect(0x0f, "ISECT", [p a0 a1 b0 b1] -> []),
+        /// Align points
+        Alignpts(0x27, "ALIGNPTS", [p1 p2] -> []),
+        /// Untouch point
+        Utp(0x29, "UTP", [p] -> []),
+
+        // Managing the stack
+        /// Duplicate top stack element
+        Dup(0x20, "DUP", [e] -> [e e]),
+        /// Pop top stack element
+        Pop(0x21, "POP", [e] -> []),
+        /// Swap the top two elements on the stack
+        Swap(0x23, "SWAP", [e2 e1] -> [e1 e2]),
+        /// Returns the depth of the stack
+        Depth(0x24, "DEPTH", [] -> [n]),
+        /// Copy the indexed element to the top of the stack
+        Cindex(0x25, "CINDEX", [k] -> [ek]),
+        /// Move the indexed element to the top of the stack
+        Mindex(0x26, "MINDEX", [k] -> []),
+        /// Roll the top three stack elements
+        Roll(0x8a, "ROLL", [a b c] -> [b c a]),
+
+        // Managing the flow of control
+        /// If test
+        If(0x58, "IF", [e] -> []),
+        /// Else
+        Else(0x1b, "ELSE", [] -> []),
+        /// End if
+        Eif(0x59, "EIF", [] -> []),
+        /// Jump relative on true
+        Jrot(0x78, "JROT", [offset e] -> []),
+        /// Jump relative
+        Jmpr(0x1c, "JMPR", [offset] -> []),
+        /// Jump relative on false
+        Jrof(0x79, "JROF", [offset e] -> []),
+
+        // Logical functions
+        /// Less than
+        Lt(0x50, "LT", [e1 e2] -> [b]),
+        /// Less than or equal
+        Lteq(0x51, "LTEQ", [e1 e2] -> [b]),
+        /// Greater than
+        Gt(0x52, "GT", [e1 e2] -> [b]),
+        /// Greater than or equal
+        Gteq(0x53, "GTEQ", [e1 e2] -> [b]),
+        /// Equal
+        Eq(0x54, "EQ", [e1 e2] -> [b]),
+        /// Not equal
+        Neq(0x55, "NEQ", [e1 e2] -> [b]),
+        /// Odd
+        Odd(0x56, "ODD", [e] -> [b]),
+        /// Even
+        Even(0x57, "EVEN", [e] -> [b]),
+        /// Logical and
+        And(0x5a, "AND", [e1 e2] -> [b]),
+        /// Logical or
+        Or(0x5b, "OR", [e1 e2] -> [b]),
+        /// Logical not
+        Not(0x5c, "NOT", [e] -> [b]),
+
+        // Arithmetic and math instructions
+        /// Add
+        Add(0x60, "ADD", [n1 n2] -> [n]),
+        /// Subtract
+        Sub(0x61, "SUB", [n1 n2] -> [n]),
+        /// Divide
+        Div(0x62, "DIV", [n1 n2] -> [n]),
+        /// Multiply
+        Mul(0x63, "MUL", [n1 n2] -> [n]),
+        /// Absolute value
+        Abs(0x64, "ABS", [n] -> [n]),
+        /// Negate
+        Neg(0x65, "NEG", [n] -> [n]),
+        /// Floor
+        Floor(0x66, "FLOOR", [n] -> [n]),
+        /// Ceiling
+        Ceiling(0x67, "CEILING", [n] -> [n]),
+        /// Maximum of top two stack elements
+        Max(0x8b, "MAX", [n1 n2] -> [n]),
+        /// Minimum of top two stack elements
+        Min(0x8c, "MIN", [n1 n2] -> [n]),
+
+        // Defining and using functions and instructions
+        /// Function definition
+        Fdef(0x2c, "FDEF", [f] -> []),
+        /// End function definition
+        Endf(0x2d, "ENDF", [] -> []),
+        /// Call function
+        Call(0x2b, "CALL", [f] -> []),
+        /// Loop and call function
+        Loopcall(0x2a, "LOOPCALL", [count f] -> []),
+        /// Instruction definition
+        Idef(0x89, "IDEF", [opcode] -> []),
+
+        // Miscellaneous instructions
+        /// Get information
+        Getinfo(0x88, "GETINFO", [selector] -> [result]),
+        /// Adjust angle (deprecated)
+        Aa(0x7f, "AA", [p] -> []),
+        /// Debug call
+        Debug(0x4f, "DEBUG", [n] -> []),
+        ;
+        /// Push n bytes, with n given in the instruction stream
+        NpushB(Vec<u8>),
+        /// Push n words, with n given in the instruction stream
+        NpushW(Vec<i16>),
+        /// Push 1 to 8 bytes
+        PushB(Vec<u8>),
+        /// Push 1 to 8 words
+        PushW(Vec<i16>),
+
+        /// Set freedom and projection vectors to coordinate axis
+        Svtca(Axis),
+        /// Set projection vector to coordinate axis
+        Spvtca(Axis),
+        /// Set freedom vector to coordinate axis
+        Sfvtca(Axis),
+        /// Set projection vector to line
+        Spvtl(LineDirection),
+        /// Set freedom vector to line
+        Sfvtl(LineDirection),
+        /// Set dual projection vector to line
+        Sdpvtl(LineDirection),
+
+        /// Get coordinate projected onto the projection vector
+        Gc(Outline),
+        /// Measure distance
+        Md(Outline),
+
+        /// Flip point, repeated `loop` times
+        Flippt,
+        /// Shift point by the last point, repeated `loop` times
+        Shp(RefPoint),
+        /// Shift contour by the last point
+        Shc(RefPoint),
+        /// Shift zone by the last point
+        Shz(RefPoint),
+        /// Shift point by a pixel amount, repeated `loop` times
+        Shpix,
+        /// Move stack indirect relative point, optionally setting `rp0`
+        Msirp(bool),
+        /// Move direct absolute point, optionally rounding
+        Mdap(bool),
+        /// Move indirect absolute point, optionally rounding
+        Miap(bool),
+        /// Move direct relative point
+        Mdrp(MoveFlags),
+        /// Move indirect relative point
+        Mirp(MoveFlags),
+        /// Align relative point, repeated `loop` times
+        Alignrp,
+        /// Interpolate point by the last relative stretch, repeated `loop` times
+        Ip,
+        /// Interpolate untouched points through the outline
+        Iup(Axis),
+
+        /// Delta exception P1
+        Deltap1,
+        /// Delta exception P2
+        Deltap2,
+        /// Delta exception P3
+        Deltap3,
+        /// Delta exception C1
+        Deltac1,
+        /// Delta exception C2
+        Deltac2,
+        /// Delta exception C3
+        Deltac3,
+
+        /// Clear the entire stack
+        Clear,
+
+        /// Round value
+        Round(DistanceType),
+        /// No rounding of value
+        Nround(DistanceType),
+
+        /// Get variation, pushing one value per variation axis
+        Getvariation,
+
+        /// An opcode not defined by the spec. It can only be executed if given a
+        /// meaning by `IDEF`.
+        User(u8),
+    }
+}
+
+/// The stack effect of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackEffect {
+    /// Pops and pushes a fixed number of elements.
+    Fixed { pops: usize, pushes: usize },
+    /// The effect depends on the values on the stack, the graphics state or
+    /// an `IDEF`.
+    Dynamic,
+}
+
+impl Instruction {
+    /// Creates the shortest sequence of push instructions that pushes the
+    /// given values, in order.
+    pub fn push(values: &[i16]) -> Vec<Instruction> {
+        values
+            .chunks(u8::MAX as usize)
+            .map(|chunk| {
+                if chunk.iter().all(|&v| (0..=u8::MAX as i16).contains(&v)) {
+                    let bytes = chunk.iter().map(|&v| v as u8).collect();
+                    if chunk.len() <= 8 {
+                        Instruction::PushB(bytes)
+                    } else {
+                        Instruction::NpushB(bytes)
+                    }
+                } else if chunk.len() <= 8 {
+                    Instruction::PushW(chunk.to_vec())
+                } else {
+                    Instruction::NpushW(chunk.to_vec())
+                }
+            })
+            .collect()
+    }
+
+    /// The mnemonic of the instruction, without flags.
+    pub fn mnemonic(&self) -> &'static str {
+        if let Some((_, mnemonic, _, _)) = self.fixed_info() {
+            return mnemonic;
+        }
+        match self {
+            Instruction::NpushB(_) => "NPUSHB",
+            Instruction::NpushW(_) => "NPUSHW",
+            Instruction::PushB(_) => "PUSHB",
+            Instruction::PushW(_) => "PUSHW",
+            Instruction::Svtca(_) => "SVTCA",
+            Instruction::Spvtca(_) => "SPVTCA",
+            Instruction::Sfvtca(_) => "SFVTCA",
+            Instruction::Spvtl(_) => "SPVTL",
+            Instruction::Sfvtl(_) => "SFVTL",
+            Instruction::Sdpvtl(_) => "SDPVTL",
+            Instruction::Gc(_) => "GC",
+            Instruction::Md(_) => "MD",
+            Instruction::Flippt => "FLIPPT",
+            Instruction::Shp(_) => "SHP",
+            Instruction::Shc(_) => "SHC",
+            Instruction::Shz(_) => "SHZ",
+            Instruction::Shpix => "SHPIX",
+            Instruction::Msirp(_) => "MSIRP",
+            Instruction::Mdap(_) => "MDAP",
+            Instruction::Miap(_) => "MIAP",
+            Instruction::Mdrp(_) => "MDRP",
+            Instruction::Mirp(_) => "MIRP",
+            Instruction::Alignrp => "ALIGNRP",
+            Instruction::Ip => "IP",
+            Instruction::Iup(_) => "IUP",
+            Instruction::Deltap1 => "DELTAP1",
+            Instruction::Deltap2 => "DELTAP2",
+            Instruction::Deltap3 => "DELTAP3",
+            Instruction::Deltac1 => "DELTAC1",
+            Instruction::Deltac2 => "DELTAC2",
+            Instruction::Deltac3 => "DELTAC3",
+            Instruction::Clear => "CLEAR",
+            Instruction::Round(_) => "ROUND",
+            Instruction::Nround(_) => "NROUND",
+            Instruction::Getvariation => "GETVARIATION",
+            Instruction::User(_) => "USER",
+            _ => unreachable!("instructions with fixed encoding are handled above"),
+        }
+    }
+
+    /// The opcode of the instruction, including flags.
+    pub fn opcode(&self) -> u8 {
+        if let Some((opcode, _, _, _)) = self.fixed_info() {
+            return opcode;
+        }
+        match *self {
+            Instruction::NpushB(_) => 0x40,
+            Instruction::NpushW(_) => 0x41,
+            Instruction::PushB(ref v) => 0xb0 + (v.len() as u8 - 1),
+            Instruction::PushW(ref v) => 0xb8 + (v.len() as u8 - 1),
+            Instruction::Svtca(a) => a as u8,
+            Instruction::Spvtca(a) => 0x02 + a as u8,
+            Instruction::Sfvtca(a) => 0x04 + a as u8,
+            Instruction::Spvtl(d) => 0x06 + d as u8,
+            Instruction::Sfvtl(d) => 0x08 + d as u8,
+            Instruction::Sdpvtl(d) => 0x86 + d as u8,
+            Instruction::Gc(o) => 0x46 + o as u8,
+            Instruction::Md(o) => 0x49 + o as u8,
+            Instruction::Flippt => 0x80,
+            Instruction::Shp(r) => 0x32 + r as u8,
+            Instruction::Shc(r) => 0x34 + r as u8,
+            Instruction::Shz(r) => 0x36 + r as u8,
+            Instruction::Shpix => 0x38,
+            Instruction::Msirp(set_rp0) => 0x3a + set_rp0 as u8,
+            Instruction::Mdap(round) => 0x2e + round as u8,
+            Instruction::Miap(round) => 0x3e + round as u8,
+            Instruction::Mdrp(flags) => 0xc0 + flags.bits(),
+            Instruction::Mirp(flags) => 0xe0 + flags.bits(),
+            Instruction::Alignrp => 0x3c,
+            Instruction::Ip => 0x39,
+            Instruction::Iup(a) => 0x30 + a as u8,
+            Instruction::Deltap1 => 0x5d,
+            Instruction::Deltap2 => 0x71,
+            Instruction::Deltap3 => 0x72,
+            Instruction::Deltac1 => 0x73,
+            Instruction::Deltac2 => 0x74,
+            Instruction::Deltac3 => 0x75,
+            Instruction::Clear => 0x22,
+            Instruction::Round(d) => 0x68 + d as u8,
+            Instruction::Nround(d) => 0x6c + d as u8,
+            Instruction::Getvariation => 0x91,
+            Instruction::User(opcode) => opcode,
+            _ => unreachable!("instructions with fixed encoding are handled above"),
+        }
+    }
+
+    /// The stack effect of the instruction.
+    pub fn stack_effect(&self) -> StackEffect {
+        if let Some((_, _, pops, pushes)) = self.fixed_info() {
+            return StackEffect::Fixed { pops, pushes };
+        }
+        let (pops, pushes) = match self {
+            Instruction::NpushB(v) | Instruction::PushB(v) => (0, v.len()),
+            Instruction::NpushW(v) | Instruction::PushW(v) => (0, v.len()),
+            Instruction::Svtca(_) | Instruction::Spvtca(_) | Instruction::Sfvtca(_) => (0, 0),
+            Instruction::Spvtl(_) | Instruction::Sfvtl(_) | Instruction::Sdpvtl(_) => (2, 0),
+            Instruction::Gc(_) => (1, 1),
+            Instruction::Md(_) => (2, 1),
+            Instruction::Shc(_) | Instruction::Shz(_) => (1, 0),
+            Instruction::Msirp(_) => (2, 0),
+            Instruction::Mdap(_) => (1, 0),
+            Instruction::Miap(_) => (2, 0),
+            Instruction::Mdrp(_) => (1, 0),
+            Instruction::Mirp(_) => (2, 0),
+            Instruction::Iup(_) => (0, 0),
+            Instruction::Round(_) | Instruction::Nround(_) => (1, 1),
+            _ => return StackEffect::Dynamic,
+        };
+        StackEffect::Fixed { pops, pushes }
+    }
+
+    /// The number of bytes the instruction takes in the instruction stream.
+    pub fn byte_length(&self) -> usize {
+        match self {
+            Instruction::NpushB(v) => 2 + v.len(),
+            Instruction::NpushW(v) => 2 + 2 * v.len(),
+            Instruction::PushB(v) => 1 + v.len(),
+            Instruction::PushW(v) => 1 + 2 * v.len(),
+            _ => 1,
+        }
+    }
+
+    pub fn write(&self, w: &mut impl BufMut) {
+        match self {
+            Instruction::NpushB(v) | Instruction::PushB(v) => {
+                debug_assert!(
+                    matches!(self, Instruction::NpushB(_)) || (1..=8).contains(&v.len()),
+                    "PUSHB can only push 1 to 8 bytes"
+                );
+                w.put_u8(self.opcode());
+                if let Instruction::NpushB(_) = self {
+                    w.put_u8(v.len().try_into().expect("NPUSHB can only push 255 bytes"));
+                }
+                w.put_slice(v);
+            }
+            Instruction::NpushW(v) | Instruction::PushW(v) => {
+                debug_assert!(
+                    matches!(self, Instruction::NpushW(_)) || (1..=8).contains(&v.len()),
+                    "PUSHW can only push 1 to 8 words"
+                );
+                w.put_u8(self.opcode());
+                if let Instruction::NpushW(_) = self {
+                    w.put_u8(v.len().try_into().expect("NPUSHW can only push 255 words"));
+                }
+                for &x in v {
+                    w.put_i16(x);
+                }
+            }
+            _ => w.put_u8(self.opcode()),
+        }
+    }
+
+    /// Reads one instruction from the start of `code`, returning the number
+    /// of bytes consumed along with it.
+    pub fn read(code: &[u8]) -> Result<(Instruction, usize), DisassembleError> {
+        let &opcode = code.first().ok_or(DisassembleError::UnexpectedEnd)?;
+        if let Some(inst) = Instruction::from_fixed_opcode(opcode) {
+            return Ok((inst, 1));
+        }
+
+        let data = |offset: usize, len: usize| {
+            code.get(offset..offset + len)
+                .ok_or(DisassembleError::UnexpectedEnd)
+        };
+        let words = |bytes: &[u8]| {
+            bytes
+                .chunks_exact(2)
+                .map(|c| i16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>()
+        };
+
+        let inst = match opcode {
+            0x40 => {
+                let n = *data(1, 1)?.first().unwrap() as usize;
+                return Ok((Instruction::NpushB(data(2, n)?.to_vec()), 2 + n));
+            }
+            0x41 => {
+                let n = *data(1, 1)?.first().unwrap() as usize;
+                return Ok((Instruction::NpushW(words(data(2, 2 * n)?)), 2 + 2 * n));
+            }
+            0xb0..=0xb7 => {
+                let n = (opcode - 0xb0 + 1) as usize;
+                return Ok((Instruction::PushB(data(1, n)?.to_vec()), 1 + n));
+            }
+            0xb8..=0xbf => {
+                let n = (opcode - 0xb8 + 1) as usize;
+                return Ok((Instruction::PushW(words(data(1, 2 * n)?)), 1 + 2 * n));
+            }
+            0x00 | 0x01 => Instruction::Svtca(axis(opcode)),
+            0x02 | 0x03 => Instruction::Spvtca(axis(opcode)),
+            0x04 | 0x05 => Instruction::Sfvtca(axis(opcode)),
+            0x06 | 0x07 => Instruction::Spvtl(line_direction(opcode)),
+            0x08 | 0x09 => Instruction::Sfvtl(line_direction(opcode)),
+            0x86 | 0x87 => Instruction::Sdpvtl(line_direction(opcode)),
+            0x46 | 0x47 => Instruction::Gc(outline(opcode)),
+            0x49 | 0x4a => Instruction::Md(outline(opcode - 1)),
+            0x80 => Instruction::Flippt,
+            0x32 | 0x33 => Instruction::Shp(ref_point(opcode)),
+            0x34 | 0x35 => Instruction::Shc(ref_point(opcode)),
+            0x36 | 0x37 => Instruction::Shz(ref_point(opcode)),
+            0x38 => Instruction::Shpix,
+            0x3a | 0x3b => Instruction::Msirp(opcode & 1 != 0),
+            0x2e | 0x2f => Instruction::Mdap(opcode & 1 != 0),
+            0x3e | 0x3f => Instruction::Miap(opcode & 1 != 0),
+            0xc0..=0xdf => Instruction::Mdrp(MoveFlags::from_bits(opcode - 0xc0)),
+            0xe0..=0xff => Instruction::Mirp(MoveFlags::from_bits(opcode - 0xe0)),
+            0x3c => Instruction::Alignrp,
+            0x39 => Instruction::Ip,
+            0x30 | 0x31 => Instruction::Iup(axis(opcode)),
+            0x5d => Instruction::Deltap1,
+            0x71 => Instruction::Deltap2,
+            0x72 => Instruction::Deltap3,
+            0x73 => Instruction::Deltac1,
+            0x74 => Instruction::Deltac2,
+            0x75 => Instruction::Deltac3,
+            0x22 => Instruction::Clear,
+            0x68..=0x6b => Instruction::Round(DistanceType::from_bits(opcode)),
+            0x6c..=0x6f => Instruction::Nround(DistanceType::from_bits(opcode)),
+            0x91 => Instruction::Getvariation,
+            _ => Instruction::User(opcode),
+        };
+        Ok((inst, 1))
+    }
+}
+
+fn axis(opcode: u8) -> Axis {
+    if opcode & 1 == 0 { Axis::Y } else { Axis::X }
+}
+
+fn line_direction(opcode: u8) -> LineDirection {
+    if opcode & 1 == 0 {
+        LineDirection::Parallel
+    } else {
+        LineDirection::Perpendicular
+    }
+}
+
+fn outline(opcode: u8) -> Outline {
+    if opcode & 1 == 0 {
+        Outline::Current
+    } else {
+        Outline::Original
+    }
+}
+
+fn ref_point(opcode: u8) -> RefPoint {
+    if opcode & 1 == 0 {
+        RefPoint::Rp2
+    } else {
+        RefPoint::Rp1
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        match self {
+            Instruction::NpushB(v) | Instruction::PushB(v) => {
+                for x in v {
+                    write!(f, " {x}")?;
+                }
+                Ok(())
+            }
+            Instruction::NpushW(v) | Instruction::PushW(v) => {
+                for x in v {
+                    write!(f, " {x}")?;
+                }
+                Ok(())
+            }
+            Instruction::Svtca(a)
+            | Instruction::Spvtca(a)
+            | Instruction::Sfvtca(a)
+            | Instruction::Iup(a) => write!(f, "[{}]", *a as u8),
+            Instruction::Spvtl(d) | Instruction::Sfvtl(d) | Instruction::Sdpvtl(d) => {
+                write!(f, "[{}]", *d as u8)
+            }
+            Instruction::Gc(o) | Instruction::Md(o) => write!(f, "[{}]", *o as u8),
+            Instruction::Shp(r) | Instruction::Shc(r) | Instruction::Shz(r) => {
+                write!(f, "[{}]", *r as u8)
+            }
+            Instruction::Msirp(b) | Instruction::Mdap(b) | Instruction::Miap(b) => {
+                write!(f, "[{}]", *b as u8)
+            }
+            Instruction::Round(d) | Instruction::Nround(d) => write!(f, "[{:02b}]", *d as u8),
+            Instruction::Mdrp(flags) | Instruction::Mirp(flags) => {
+                write!(f, "[{:05b}]", flags.bits())
+            }
+            Instruction::User(opcode) => write!(f, "[{opcode:#04x}]"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DisassembleError {
+    #[error("Unexpected end of instruction stream")]
+    UnexpectedEnd,
+}
+
+/// Encodes the instructions into bytecode.
+pub fn assemble(insts: &[Instruction]) -> Vec<u8> {
+    let mut code = Vec::with_capacity(insts.iter().map(Instruction::byte_length).sum());
+    for inst in insts {
+        inst.write(&mut code);
+    }
+    code
+}
+
+/// Decodes bytecode into instructions.
+pub fn disassemble(mut code: &[u8]) -> Result<Vec<Instruction>, DisassembleError> {
+    let mut insts = Vec::new();
+    while !code.is_empty() {
+        let (inst, len) = Instruction::read(code)?;
+        insts.push(inst);
+        code = &code[len..];
+    }
+    Ok(insts)
+}
+
+#[test]
+fn test_roundtrip_all_opcodes() {
+    for opcode in 0..=u8::MAX {
+        // Pad with enough data for the longest push instruction
+        let mut code = vec![opcode, 8];
+        code.extend(std::iter::repeat_n(0x7f, 16));
+        let (inst, len) = Instruction::read(&code).unwrap();
+        assert_eq!(inst.opcode(), opcode, "{inst}");
+        assert_eq!(inst.byte_length(), len, "{inst}");
+        assert_eq!(assemble(std::slice::from_ref(&inst)), code[..len], "{inst}");
+    }
+}
+
+#[test]
+fn test_push_encoding() {
+    assert_eq!(
+        Instruction::push(&[1, 2, 3]),
+        vec![Instruction::PushB(vec![1, 2, 3])]
+    );
+    assert_eq!(
+        Instruction::push(&[1, -2]),
+        vec![Instruction::PushW(vec![1, -2])]
+    );
+    let many = (0..300).collect::<Vec<_>>();
+    let insts = Instruction::push(&many);
+    assert_eq!(insts.len(), 2);
+    assert!(matches!(&insts[0], Instruction::NpushB(v) if v.len() == 255));
+    assert!(matches!(&insts[1], Instruction::NpushW(v) if v.len() == 45));
+    assert_eq!(disassemble(&assemble(&insts)).unwrap(), insts);
+}
@@ -1,9 +1,21 @@
-use super::ITable;
+use super::{ITable, head::IndexToLocFormat};
 
-/// `loca` table, long format.
+/// `loca` table.
 pub struct Table {
     /// The starting offset within the `glyf` table of each glyph
     pub offsets: Vec<u32>,
+    /// The format to write the offsets in. Must match the `head` table.
+    pub format: IndexToLocFormat,
+}
+
+impl Table {
+    /// Whether the offsets can be written in the short format, i.e. all of
+    /// them are even and below 128 KiB.
+    pub fn fits_short(&self) -> bool {
+        self.offsets
+            .iter()
+            .all(|&x| x % 2 == 0 && x / 2 <= u16::MAX as u32)
+    }
 }
 
 impl ITable for Table {
@@ -12,8 +24,35 @@ impl ITable for Table {
     }
 
     fn write(&self, writer: &mut impl bytes::BufMut) {
-        for &x in &self.offsets {
-            writer.put_u32(x);
+        match self.format {
+            IndexToLocFormat::Short => {
+                debug_assert!(self.fits_short(), "offsets do not fit in short loca");
+                for &x in &self.offsets {
+                    writer.put_u16((x / 2) as u16);
+                }
+            }
+            IndexToLocFormat::Long => {
+                for &x in &self.offsets {
+                    writer.put_u32(x);
+                }
+            }
         }
     }
 }
+
+#[test]
+fn test_short_loca() {
+    let mut table = Table {
+        offsets: vec![0, 12, 0x1fffe],
+        format: IndexToLocFormat::Short,
+    };
+    assert!(table.fits_short());
+    let mut buf = vec![];
+    table.write(&mut buf);
+    assert_eq!(buf, [0, 0, 0, 6, 0xff, 0xff]);
+
+    table.offsets.push(0x20000);
+    assert!(!table.fits_short());
+    table.offsets = vec![0, 13];
+    assert!(!table.fits_short());
+}
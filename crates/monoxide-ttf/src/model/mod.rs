@@ -4,21 +4,29 @@
 //! the tables in an OpenType font file. For high-level structures that can be
 //! used to generate these tables, see the [`crate::hl`] module.
 
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use indexmap::IndexMap;
 pub mod cff2;
 pub mod cmap;
+pub mod collection;
+pub mod cvt;
 pub mod dsig;
 pub mod encoding;
+pub mod fpgm;
+pub mod gasp;
 pub mod glyf;
+pub mod hdmx;
 pub mod head;
 pub mod hhea;
 pub mod hmtx;
+pub mod inst;
 pub mod loca;
 pub mod maxp;
 pub mod name;
 pub mod os2;
 pub mod post;
+pub mod prep;
+pub mod vdmx;
 
 #[allow(non_camel_case_types)]
 /// A signed 16-bit number describing number of font design units.
@@ -64,7 +72,38 @@ impl<T: ITable> DynITable for T {
     }
 }
 
-fn ttf_checksum(data: &[u8]) -> u32 {
+/// A table written as is, such as a table not modeled by this crate or one
+/// produced by an external tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawTable {
+    pub tag: [u8; 4],
+    pub data: Bytes,
+}
+
+impl RawTable {
+    pub fn new(tag: [u8; 4], data: impl Into<Bytes>) -> Self {
+        Self {
+            tag,
+            data: data.into(),
+        }
+    }
+
+    /// Serialize a table to be attached as a raw table.
+    pub fn from_table(table: &impl ITable) -> Self {
+        let mut buf = BytesMut::new();
+        table.write(&mut buf);
+        Self::new(*table.name(), buf.freeze())
+    }
+}
+
+/// Whether the tag is valid, i.e. printable ASCII characters with spaces
+/// only allowed at the end.
+fn is_valid_tag(tag: &[u8; 4]) -> bool {
+    let len = tag.iter().rposition(|&b| b != b' ').map_or(0, |ix| ix + 1);
+    len > 0 && tag[..len].iter().all(|&b| b.is_ascii_graphic())
+}
+
+pub(crate) fn ttf_checksum(data: &[u8]) -> u32 {
     let mut sum = 0u32;
     let mut chunks = data.chunks_exact(4);
     for chunk in &mut chunks {
@@ -79,11 +118,26 @@ fn ttf_checksum(data: &[u8]) -> u32 {
     sum
 }
 
+/// The checksum in the table record of a table. The checksum of `head` is
+/// calculated with its checksum adjustment taken as zero.
+pub(crate) fn table_checksum(tag: &[u8; 4], data: &[u8]) -> u32 {
+    let sum = ttf_checksum(data);
+    match data.get(8..12) {
+        Some(adjustment) if tag == b"head" => {
+            sum.wrapping_sub(u32::from_be_bytes(adjustment.try_into().unwrap()))
+        }
+        _ => sum,
+    }
+}
+
 /// Tables for TrueType outlines.
 pub struct TrueTypeTables {
     pub glyf: glyf::Table,
     pub loca: loca::Table,
     pub maxp: maxp::TableV1,
+    pub fpgm: Option<fpgm::Table>,
+    pub prep: Option<prep::Table>,
+    pub cvt: Option<cvt::Table>,
 }
 
 /// Tables for CFF2 outlines.
@@ -105,11 +159,18 @@ pub struct FontFile {
     pub cmap: cmap::Table,
     pub name: name::Table,
     pub os2: os2::Table,
-    pub post: post::TableV3,
+    pub post: post::Table,
     pub outline: Outline,
+    pub gasp: Option<gasp::Table>,
+    pub hdmx: Option<hdmx::Table>,
+    pub vdmx: Option<vdmx::Table>,
     pub dsig: Option<dsig::Table>,
+    /// Tables written as is, alongside the tables above. Their tags must be
+    /// distinct from each other and from the tables above.
+    pub extra_tables: Vec<RawTable>,
 }
 
+#[derive(Clone)]
 struct TableRecord {
     tag: [u8; 4],
     checksum: u32,
@@ -117,35 +178,182 @@ struct TableRecord {
     length: u32,
 }
 
+/// The physical order of table data in the font file. The table directory is
+/// always sorted by tag, as required by the specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableOrder {
+    /// Sort table data by tag, the same as the table directory.
+    Alphabetical,
+    /// The order recommended by the OpenType specification, which lets
+    /// rasterizers load the frequently used tables with less seeking.
+    Recommended,
+}
+
+impl TableOrder {
+    /// Recommended order for fonts with TrueType outlines.
+    ///
+    /// <https://learn.microsoft.com/en-us/typography/opentype/spec/recom#optimized-table-ordering>
+    const RECOMMENDED_TRUETYPE: &[&[u8; 4]] = &[
+        b"head", b"hhea", b"maxp", b"OS/2", b"hmtx", b"LTSH", b"VDMX", b"hdmx", b"cmap", b"fpgm",
+        b"prep", b"cvt ", b"loca", b"glyf", b"kern", b"name", b"post", b"gasp", b"PCLT", b"DSIG",
+    ];
+
+    /// The sort key of a table in this order.
+    fn key(&self, tag: &[u8; 4]) -> (usize, [u8; 4]) {
+        match self {
+            TableOrder::Alphabetical => (0, *tag),
+            TableOrder::Recommended => {
+                // Tables not listed come last, in alphabetical order
+                let ix = Self::RECOMMENDED_TRUETYPE
+                    .iter()
+                    .position(|it| *it == tag)
+                    .unwrap_or(Self::RECOMMENDED_TRUETYPE.len());
+                (ix, *tag)
+            }
+        }
+    }
+}
+
+/// Options for writing a [`FontFile`].
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Write `____{tag}` before each table to help locating tables in a hex
+    /// dump. The markers are not referenced by any table record.
+    pub debug_markers: bool,
+    /// The physical order of table data.
+    pub table_order: TableOrder,
+    /// Use the short `loca` format if all glyph offsets fit in it. This
+    /// overrides the format set in the `loca` table.
+    pub short_loca: bool,
+    /// Write the glyph names of the `post` table, if present, as a version
+    /// 2.0 table. Otherwise, a version 3.0 table without names is written,
+    /// which is smaller but leaves tools like PDF readers without names.
+    pub glyph_names: bool,
+}
+
+impl WriteOptions {
+    /// Options suitable for debugging the output: debug markers are written
+    /// and tables are sorted alphabetically.
+    pub fn debug() -> Self {
+        Self {
+            debug_markers: true,
+            table_order: TableOrder::Alphabetical,
+            short_loca: false,
+            glyph_names: true,
+        }
+    }
+}
+
+/// The default options are suitable for release builds.
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            debug_markers: false,
+            table_order: TableOrder::Recommended,
+            short_loca: true,
+            glyph_names: true,
+        }
+    }
+}
+
 impl FontFile {
-    pub fn write(&self, w: impl std::io::Write) -> std::io::Result<()> {
-        write_font_file(self, w)
+    pub fn write(&self, options: &WriteOptions, w: impl std::io::Write) -> std::io::Result<()> {
+        write_font_file(self, options, w)
     }
 }
 
-fn write_font_file(font: &FontFile, mut w: impl std::io::Write) -> std::io::Result<()> {
+fn write_font_file(
+    font: &FontFile,
+    options: &WriteOptions,
+    mut w: impl std::io::Write,
+) -> std::io::Result<()> {
+    let font = serialize_font(font, options)?;
+    let header_size = offset_table_size(font.tables.len());
+    let (table_records, _) = allocate_tables(font.tables.iter(), header_size, options);
+    let offset_table = offset_table(font.version, &table_records);
+    debug_assert_eq!(
+        offset_table.len(),
+        header_size,
+        "header (including table records) size mismatch"
+    );
+
+    w.write_all(&offset_table)?;
+    write_table_data(
+        &mut w,
+        font.tables.values().zip(&table_records),
+        header_size,
+        options,
+    )
+}
+
+/// The serialized tables of a font.
+struct SerializedFont {
+    /// The sfnt version in the offset table.
+    version: [u8; 4],
+    /// The table data keyed by tag, in the order they are written. The
+    /// checksum adjustment in `head` is filled in for a standalone font file.
+    tables: IndexMap<[u8; 4], Bytes>,
+}
+
+/// Serialize the tables of a font, failing if an extra table has an invalid
+/// or duplicate tag.
+fn serialize_font(font: &FontFile, options: &WriteOptions) -> std::io::Result<SerializedFont> {
     let version = match font.outline {
         Outline::TrueType(_) => [0x00, 0x01, 0x00, 0x00],
         Outline::CFF2(_) => b"OTTO".to_owned(),
     };
+    // The `loca` format is decided here, and `head` must agree with it.
+    let loca = match &font.outline {
+        Outline::TrueType(tt_tables) => {
+            let format = if options.short_loca && tt_tables.loca.fits_short() {
+                head::IndexToLocFormat::Short
+            } else {
+                tt_tables.loca.format
+            };
+            Some(loca::Table {
+                offsets: tt_tables.loca.offsets.clone(),
+                format,
+            })
+        }
+        Outline::CFF2(_) => None,
+    };
+    let head = head::Table {
+        index_to_loc_format: loca
+            .as_ref()
+            .map_or(font.head.index_to_loc_format, |it| it.format),
+        ..font.head.clone()
+    };
+    let post = post::Table {
+        glyph_names: (font.post.glyph_names.clone()).filter(|_| options.glyph_names),
+        ..font.post
+    };
     // The `head` requires special treatment because it needs to be
     // rewritten after the checksum is calculated. Anyway, we can first
     // serialize them as opaque blobs and pay special attention to the
     // `head` table when calculating offsets and checksums.
     let mut tables = Vec::<&dyn DynITable>::new();
     {
-        tables.push(&font.head);
+        tables.push(&head);
         tables.push(&font.hhea);
         tables.push(&font.hmtx);
         tables.push(&font.cmap);
         tables.push(&font.name);
         tables.push(&font.os2);
-        tables.push(&font.post);
+        tables.push(&post);
         match &font.outline {
             Outline::TrueType(tt_tables) => {
                 tables.push(&tt_tables.glyf);
-                tables.push(&tt_tables.loca);
+                tables.push(loca.as_ref().expect("Should be set for TrueType outlines"));
                 tables.push(&tt_tables.maxp);
+                if let Some(fpgm) = &tt_tables.fpgm {
+                    tables.push(fpgm);
+                }
+                if let Some(prep) = &tt_tables.prep {
+                    tables.push(prep);
+                }
+                if let Some(cvt) = &tt_tables.cvt {
+                    tables.push(cvt);
+                }
             }
             Outline::CFF2(_) => {
                 todo!("CFF2 tables are not implemented yet");
@@ -153,49 +361,20 @@ fn write_font_file(font: &FontFile, mut w: impl std::io::Write) -> std::io::Resu
                 // tables_except_header.push(Box::new(tables.maxp));
             }
         }
+        if let Some(gasp) = &font.gasp {
+            tables.push(gasp);
+        }
+        if let Some(hdmx) = &font.hdmx {
+            tables.push(hdmx);
+        }
+        if let Some(vdmx) = &font.vdmx {
+            tables.push(vdmx);
+        }
         if let Some(dsig) = &font.dsig {
             tables.push(dsig);
         }
     }
-    let n_table_records = tables.len();
-    /*
-    header layout:
-        version: u32
-        n_tables: u16
-        search_range: u16 = ((2**floor(log2(numTables))) * 16 (Maximum power of 2 less than or equal to numTables * 16)
-        entry_selector: u16 = log2(searchRange / 16)
-        range_shift: u16 = numTables * 16 - searchRange
-        ... table records ...
-     */
-    let header_size = 12 + n_table_records * 16;
-
-    let search_range = (1 << (n_table_records as f32).log2().floor() as u32) * 16;
-    let entry_selector = (n_table_records as f32).log2().floor() as u16;
-    let range_shift = (n_table_records as u16 * 16) - search_range as u16;
-
-    // Write the header
-    let mut header_buffer = Vec::new();
-    header_buffer.extend_from_slice(&version);
-    header_buffer.extend_from_slice(&(n_table_records as u16).to_be_bytes());
-    header_buffer.extend_from_slice(&(search_range as u16).to_be_bytes());
-    header_buffer.extend_from_slice(&entry_selector.to_be_bytes());
-    header_buffer.extend_from_slice(&range_shift.to_be_bytes());
-
-    // Calculate the checksum of the whole font file.
-    //
-    // Since the checksum is calculated using wrapping add, it can be calculated
-    // in a different order from the actual writing order. Additionally, we are
-    // padding all tables to 4 bytes, so we can directly add the checksum
-    // calculated from `ttf_checksum`.
-    let mut font_cksum = 0u32;
-    font_cksum = font_cksum.wrapping_add(ttf_checksum(&header_buffer));
-
     // Serialize all tables
-    let head_ser = {
-        let mut head_buf = BytesMut::new();
-        font.head.write(&mut head_buf);
-        head_buf.freeze()
-    };
     let mut tables_ser = tables
         .iter()
         .map(|table| {
@@ -204,51 +383,55 @@ fn write_font_file(font: &FontFile, mut w: impl std::io::Write) -> std::io::Resu
             (*table.name_dyn(), buf.freeze())
         })
         .collect::<IndexMap<_, _>>();
-    // The tables must be sorted by tag
-    tables_ser.sort_by(|a, _, b, _| a.cmp(b));
-
-    // We first do a virtual allocation of all tables to calculate the offsets,
-    // before we write the actual data.
-    let mut table_records = Vec::with_capacity(n_table_records);
-    let mut offset = header_size; // current write offset
-
-    // To assist debugging, we write "____{tag}" before the beginning of each table
-    let debug_data_len = 8;
-
-    for (tag, ser) in tables_ser.iter() {
-        offset += debug_data_len;
-
-        let cksum = ttf_checksum(ser);
-        table_records.push(TableRecord {
-            tag: *tag,
-            checksum: cksum,
-            offset: offset as u32,
-            length: ser.len() as u32,
-        });
-
-        offset += ser.len();
-        offset = offset.next_multiple_of(4); // pad to 4 bytes
-
-        font_cksum = font_cksum.wrapping_add(cksum);
+    for table in &font.extra_tables {
+        let tag = String::from_utf8_lossy(&table.tag);
+        if !is_valid_tag(&table.tag) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid table tag {tag:?}"),
+            ));
+        }
+        if tables_ser.insert(table.tag, table.data.clone()).is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("duplicate table tag {tag:?}"),
+            ));
+        }
     }
-
-    let mut table_records_ser = Vec::new();
-    for record in &table_records {
-        table_records_ser.extend_from_slice(&record.tag);
-        table_records_ser.extend_from_slice(&record.checksum.to_be_bytes());
-        table_records_ser.extend_from_slice(&record.offset.to_be_bytes());
-        table_records_ser.extend_from_slice(&record.length.to_be_bytes());
+    // `numTables * 16` is stored in the u16 fields of the offset table.
+    if tables_ser.len() > MAX_TABLES {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "too many tables ({}), at most {MAX_TABLES} are supported",
+                tables_ser.len()
+            ),
+        ));
     }
-    font_cksum = font_cksum.wrapping_add(ttf_checksum(&table_records_ser));
+    // The table data are written in the requested order
+    tables_ser.sort_by(|a, _, b, _| options.table_order.key(a).cmp(&options.table_order.key(b)));
+
+    // Calculate the checksum of the whole font file, with a virtual allocation
+    // of the tables as they would be laid out in a standalone file.
+    //
+    // Since the checksum is calculated using wrapping add, it can be calculated
+    // in a different order from the actual writing order. Additionally, we are
+    // padding all tables to 4 bytes, so we can directly add the checksum
+    // calculated from `ttf_checksum`.
+    let header_size = offset_table_size(tables_ser.len());
+    let (table_records, _) = allocate_tables(tables_ser.iter(), header_size, options);
+    let font_cksum = (table_records.iter()).fold(
+        ttf_checksum(&offset_table(version, &table_records)),
+        |sum, record| sum.wrapping_add(record.checksum),
+    );
 
     // As we said, the head table needs to be rewritten with the checksum added.
     let cksum_adjustment = 0xB1B0AFBAu32.wrapping_sub(font_cksum);
     let new_head = head::Table {
         checksum_adjustment: cksum_adjustment,
-        ..font.head.clone()
+        ..head.clone()
     };
     // And re-serialize it
-    drop(head_ser); // We don't need the old head anymore
     let head_ser = {
         let mut head_buf = BytesMut::new();
         new_head.write(&mut head_buf);
@@ -256,45 +439,115 @@ fn write_font_file(font: &FontFile, mut w: impl std::io::Write) -> std::io::Resu
     };
     tables_ser.insert(*new_head.name(), head_ser);
 
-    fn write_ser(mut w: impl std::io::Write, name: [u8; 4], ser: &[u8]) -> std::io::Result<()> {
-        write!(w, "____{}", std::str::from_utf8(&name).unwrap())?;
-
-        w.write_all(ser)?;
-        pad_to_4_bytes(ser.len(), &mut w)?;
+    Ok(SerializedFont {
+        version,
+        tables: tables_ser,
+    })
+}
 
-        Ok(())
-    }
+/// The maximum number of tables in a font, so that `rangeShift` fits in u16.
+const MAX_TABLES: usize = 4095;
 
-    // Noice, we can finally write the font file
-    // Do offset checks for tables and table records
-    let mut actual_offset = 0;
+/// The size of the offset table, i.e. the header and the table records.
+fn offset_table_size(n_tables: usize) -> usize {
+    12 + n_tables * 16
+}
 
-    w.write_all(&header_buffer)?;
-    actual_offset += header_buffer.len();
-    debug_assert_eq!(header_buffer.len(), 12, "header size mismatch");
+/// Do a virtual allocation of the tables written in order from `offset`,
+/// before the actual data are written. Returns the table records in the same
+/// order, and the end offset.
+fn allocate_tables<'a>(
+    tables: impl IntoIterator<Item = (&'a [u8; 4], &'a Bytes)>,
+    mut offset: usize,
+    options: &WriteOptions,
+) -> (Vec<TableRecord>, usize) {
+    let records = tables
+        .into_iter()
+        .map(|(tag, ser)| {
+            offset += debug_marker_len(options);
+            let record = TableRecord {
+                tag: *tag,
+                checksum: table_checksum(tag, ser),
+                offset: offset as u32,
+                length: ser.len() as u32,
+            };
+            offset += ser.len();
+            offset = offset.next_multiple_of(4); // pad to 4 bytes
+            record
+        })
+        .collect();
+    (records, offset)
+}
 
-    w.write_all(&table_records_ser)?;
-    actual_offset += table_records_ser.len();
-    debug_assert_eq!(
-        table_records_ser.len(),
-        n_table_records * 16,
-        "table records size mismatch"
-    );
-    debug_assert_eq!(
-        actual_offset, header_size,
-        "header (including table records) size mismatch"
-    );
+/// To assist debugging, we may write "____{tag}" before the beginning of each
+/// table.
+fn debug_marker_len(options: &WriteOptions) -> usize {
+    if options.debug_markers { 8 } else { 0 }
+}
 
-    pad_to_4_bytes(actual_offset, &mut w)?;
+/// Serialize the offset table of a font, i.e. the header followed by the table
+/// records.
+fn offset_table(version: [u8; 4], table_records: &[TableRecord]) -> Vec<u8> {
+    let n_table_records = table_records.len();
+    let n_tables = u16::try_from(n_table_records)
+        .ok()
+        .filter(|&n| n as usize <= MAX_TABLES)
+        .expect("The number of tables should be checked by `serialize_font`");
+    /*
+    header layout:
+        version: u32
+        n_tables: u16
+        search_range: u16 = ((2**floor(log2(numTables))) * 16 (Maximum power of 2 less than or equal to numTables * 16)
+        entry_selector: u16 = log2(searchRange / 16)
+        range_shift: u16 = numTables * 16 - searchRange
+        ... table records ...
+     */
+    let entry_selector = n_tables.checked_ilog2().unwrap_or(0) as u16;
+    let search_range = n_tables.checked_ilog2().map_or(0, |e| 16 << e);
+    let range_shift = n_tables * 16 - search_range;
+
+    let mut buffer = Vec::with_capacity(offset_table_size(n_table_records));
+    buffer.extend_from_slice(&version);
+    buffer.extend_from_slice(&n_tables.to_be_bytes());
+    buffer.extend_from_slice(&search_range.to_be_bytes());
+    buffer.extend_from_slice(&entry_selector.to_be_bytes());
+    buffer.extend_from_slice(&range_shift.to_be_bytes());
+
+    // The table records must be sorted by tag, regardless of the data order
+    let mut sorted_records = table_records.iter().collect::<Vec<_>>();
+    sorted_records.sort_by_key(|record| record.tag);
+    for record in sorted_records {
+        buffer.extend_from_slice(&record.tag);
+        buffer.extend_from_slice(&record.checksum.to_be_bytes());
+        buffer.extend_from_slice(&record.offset.to_be_bytes());
+        buffer.extend_from_slice(&record.length.to_be_bytes());
+    }
+    buffer
+}
 
-    for ((_, ser), tbl) in tables_ser.iter().zip(table_records.iter()) {
-        actual_offset += 8; // debug info
+/// Write the table data at the offsets allocated in their records, starting
+/// from `offset`.
+fn write_table_data<'a>(
+    w: &mut impl std::io::Write,
+    tables: impl IntoIterator<Item = (&'a Bytes, &'a TableRecord)>,
+    mut offset: usize,
+    options: &WriteOptions,
+) -> std::io::Result<()> {
+    pad_to_4_bytes(offset, w)?;
+    offset = offset.next_multiple_of(4);
+
+    for (ser, tbl) in tables {
         let table_tag_string = std::str::from_utf8(&tbl.tag).unwrap();
+        if options.debug_markers {
+            write!(w, "____{table_tag_string}")?;
+            offset += debug_marker_len(options);
+        }
 
-        assert_table_invariants(actual_offset, tbl, table_tag_string);
+        assert_table_invariants(offset, tbl, table_tag_string);
 
-        write_ser(&mut w, tbl.tag, ser)?;
-        actual_offset += ser.len().next_multiple_of(4);
+        w.write_all(ser)?;
+        pad_to_4_bytes(ser.len(), w)?;
+        offset += ser.len().next_multiple_of(4);
     }
 
     Ok(())
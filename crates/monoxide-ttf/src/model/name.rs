@@ -1,14 +1,16 @@
+mod lang_id_mac;
 mod lang_id_ms;
 
 use std::collections::HashMap;
 
 use bytes::{BufMut, BytesMut};
+pub use lang_id_mac::MacLangID;
 pub use lang_id_ms::MSLangID;
 use widestring::U16String;
 
 use super::{
     ITable,
-    encoding::{PlatformId, UnicodePlatformEncoding},
+    encoding::{MacintoshPlatformEncoding, PlatformId, UnicodePlatformEncoding, encode_mac_roman},
 };
 
 /// The version of the name table. Only version 1 is supported by this library.
@@ -163,6 +165,12 @@ pub enum Lang {
     ///
     /// Please refer to the [`MSLangID`] enum for the list of language IDs.
     Microsoft(MSLangID),
+
+    /// Used by Macintosh platform, with strings encoded in Mac Roman.
+    /// Characters outside of Mac Roman are written as `?`.
+    ///
+    /// Please refer to the [`MacLangID`] enum for the list of language IDs.
+    Macintosh(MacLangID),
 }
 
 pub struct Table {
@@ -188,23 +196,43 @@ impl ITable for Table {
         let mut pool = BytesMut::new();
         let mut name_records = Vec::new();
 
-        for (lang, recs) in &self.records {
-            let (platform_id, encoding_id, language_id) = match lang {
-                Lang::Unicode => (
-                    PlatformId::Unicode as u16,
-                    UnicodePlatformEncoding::V2Full as u16,
-                    0,
-                ),
-                Lang::Microsoft(mslang_id) => (PlatformId::Microsoft as u16, 1, *mslang_id as u16),
-            };
+        // Lay out the string pool in record order, regardless of the
+        // iteration order of the map, so the output is deterministic.
+        let mut langs = self
+            .records
+            .iter()
+            .map(|(lang, recs)| {
+                let ids = match lang {
+                    Lang::Unicode => (
+                        PlatformId::Unicode as u16,
+                        UnicodePlatformEncoding::V2Full as u16,
+                        0,
+                    ),
+                    Lang::Microsoft(mslang_id) => {
+                        (PlatformId::Microsoft as u16, 1, *mslang_id as u16)
+                    }
+                    Lang::Macintosh(maclang_id) => (
+                        PlatformId::Macintosh as u16,
+                        MacintoshPlatformEncoding::Roman as u16,
+                        *maclang_id as u16,
+                    ),
+                };
+                (ids, recs)
+            })
+            .collect::<Vec<_>>();
+        langs.sort_by_key(|(ids, _)| *ids);
 
+        for ((platform_id, encoding_id, language_id), recs) in langs {
             for rec in recs.to_records() {
-                let rec_u16 = U16String::from_str(&rec.value);
                 let rec_start = pool.len();
-                let rec_len = rec_u16.as_vec().len() * 2;
-                for ch in rec_u16.as_vec() {
-                    pool.put_u16(*ch);
+                if platform_id == PlatformId::Macintosh as u16 {
+                    pool.put_slice(&encode_mac_roman(&rec.value));
+                } else {
+                    for ch in U16String::from_str(&rec.value).as_vec() {
+                        pool.put_u16(*ch);
+                    }
                 }
+                let rec_len = pool.len() - rec_start;
 
                 name_records.push(EncodedNameRecord {
                     platform_id,
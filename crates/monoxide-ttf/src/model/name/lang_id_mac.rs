@@ -0,0 +1,246 @@
+//! Language IDs for the Macintosh platform.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/name#macintosh-language-ids>
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Language IDs for the Macintosh platform. The variants are named after
+/// the closest BCP 47 tags.
+pub enum MacLangID {
+    /// English
+    r#en = 0,
+    /// French
+    r#fr = 1,
+    /// German
+    r#de = 2,
+    /// Italian
+    r#it = 3,
+    /// Dutch
+    r#nl = 4,
+    /// Swedish
+    r#sv = 5,
+    /// Spanish
+    r#es = 6,
+    /// Danish
+    r#da = 7,
+    /// Portuguese
+    r#pt = 8,
+    /// Norwegian
+    r#no = 9,
+    /// Hebrew
+    r#he = 10,
+    /// Japanese
+    r#ja = 11,
+    /// Arabic
+    r#ar = 12,
+    /// Finnish
+    r#fi = 13,
+    /// Greek
+    r#el = 14,
+    /// Icelandic
+    r#is = 15,
+    /// Maltese
+    r#mt = 16,
+    /// Turkish
+    r#tr = 17,
+    /// Croatian
+    r#hr = 18,
+    /// Chinese (Traditional)
+    r#zh_hant = 19,
+    /// Urdu
+    r#ur = 20,
+    /// Hindi
+    r#hi = 21,
+    /// Thai
+    r#th = 22,
+    /// Korean
+    r#ko = 23,
+    /// Lithuanian
+    r#lt = 24,
+    /// Polish
+    r#pl = 25,
+    /// Hungarian
+    r#hu = 26,
+    /// Estonian
+    r#et = 27,
+    /// Latvian
+    r#lv = 28,
+    /// Sami
+    r#se = 29,
+    /// Faroese
+    r#fo = 30,
+    /// Farsi/Persian
+    r#fa = 31,
+    /// Russian
+    r#ru = 32,
+    /// Chinese (Simplified)
+    r#zh_hans = 33,
+    /// Flemish
+    r#nl_be = 34,
+    /// Irish Gaelic
+    r#ga = 35,
+    /// Albanian
+    r#sq = 36,
+    /// Romanian
+    r#ro = 37,
+    /// Czech
+    r#cs = 38,
+    /// Slovak
+    r#sk = 39,
+    /// Slovenian
+    r#sl = 40,
+    /// Yiddish
+    r#yi = 41,
+    /// Serbian
+    r#sr = 42,
+    /// Macedonian
+    r#mk = 43,
+    /// Bulgarian
+    r#bg = 44,
+    /// Ukrainian
+    r#uk = 45,
+    /// Byelorussian
+    r#be = 46,
+    /// Uzbek
+    r#uz = 47,
+    /// Kazakh
+    r#kk = 48,
+    /// Azerbaijani (Cyrillic script)
+    r#az_cyrl = 49,
+    /// Azerbaijani (Arabic script)
+    r#az_arab = 50,
+    /// Armenian
+    r#hy = 51,
+    /// Georgian
+    r#ka = 52,
+    /// Moldavian
+    r#ro_md = 53,
+    /// Kirghiz
+    r#ky = 54,
+    /// Tajiki
+    r#tg = 55,
+    /// Turkmen
+    r#tk = 56,
+    /// Mongolian (Mongolian script)
+    r#mn_mong = 57,
+    /// Mongolian (Cyrillic script)
+    r#mn_cyrl = 58,
+    /// Pashto
+    r#ps = 59,
+    /// Kurdish
+    r#ku = 60,
+    /// Kashmiri
+    r#ks = 61,
+    /// Sindhi
+    r#sd = 62,
+    /// Tibetan
+    r#bo = 63,
+    /// Nepali
+    r#ne = 64,
+    /// Sanskrit
+    r#sa = 65,
+    /// Marathi
+    r#mr = 66,
+    /// Bengali
+    r#bn = 67,
+    /// Assamese
+    r#as = 68,
+    /// Gujarati
+    r#gu = 69,
+    /// Punjabi
+    r#pa = 70,
+    /// Oriya
+    r#or = 71,
+    /// Malayalam
+    r#ml = 72,
+    /// Kannada
+    r#kn = 73,
+    /// Tamil
+    r#ta = 74,
+    /// Telugu
+    r#te = 75,
+    /// Sinhalese
+    r#si = 76,
+    /// Burmese
+    r#my = 77,
+    /// Khmer
+    r#km = 78,
+    /// Lao
+    r#lo = 79,
+    /// Vietnamese
+    r#vi = 80,
+    /// Indonesian
+    r#id = 81,
+    /// Tagalog
+    r#tl = 82,
+    /// Malay (Roman script)
+    r#ms_latn = 83,
+    /// Malay (Arabic script)
+    r#ms_arab = 84,
+    /// Amharic
+    r#am = 85,
+    /// Tigrinya
+    r#ti = 86,
+    /// Galla
+    r#om = 87,
+    /// Somali
+    r#so = 88,
+    /// Swahili
+    r#sw = 89,
+    /// Kinyarwanda/Ruanda
+    r#rw = 90,
+    /// Rundi
+    r#rn = 91,
+    /// Nyanja/Chewa
+    r#ny = 92,
+    /// Malagasy
+    r#mg = 93,
+    /// Esperanto
+    r#eo = 94,
+    /// Welsh
+    r#cy = 128,
+    /// Basque
+    r#eu = 129,
+    /// Catalan
+    r#ca = 130,
+    /// Latin
+    r#la = 131,
+    /// Quechua
+    r#qu = 132,
+    /// Guarani
+    r#gn = 133,
+    /// Aymara
+    r#ay = 134,
+    /// Tatar
+    r#tt = 135,
+    /// Uighur
+    r#ug = 136,
+    /// Dzongkha
+    r#dz = 137,
+    /// Javanese (Roman script)
+    r#jv_latn = 138,
+    /// Sundanese (Roman script)
+    r#su_latn = 139,
+    /// Galician
+    r#gl = 140,
+    /// Afrikaans
+    r#af = 141,
+    /// Breton
+    r#br = 142,
+    /// Inuktitut
+    r#iu = 143,
+    /// Scottish Gaelic
+    r#gd = 144,
+    /// Manx Gaelic
+    r#gv = 145,
+    /// Irish Gaelic (with dot above)
+    r#ga_latg = 146,
+    /// Tongan
+    r#to = 147,
+    /// Greek (polytonic)
+    r#el_polyton = 148,
+    /// Greenlandic
+    r#kl = 149,
+    /// Azerbaijani (Roman script)
+    r#az_latn = 150,
+}
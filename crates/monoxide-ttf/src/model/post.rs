@@ -1,9 +1,11 @@
 //! The `post` table.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/post>
 
 use super::{Fixed, ITable, fword};
 
-pub struct TableV3 {
-    // version: u32 = 0x00030000,
+pub struct Table {
+    // version: u32 = 0x00020000 or 0x00030000,
     pub italic_angle: Fixed,
     pub underline_position: fword,
     pub underline_thickness: fword,
@@ -16,19 +18,35 @@ pub struct TableV3 {
     pub max_mem_type42: u32,
     pub min_mem_type1: u32,
     pub max_mem_type1: u32,
+
+    /// Names of all glyphs in glyph ID order. If present, a version 2.0
+    /// table is written. Otherwise, a version 3.0 table without glyph names
+    /// is written. Fonts can be written without the names regardless, see
+    /// [`super::WriteOptions::glyph_names`].
+    ///
+    /// Names should be ASCII-only and no longer than 63 characters.
+    pub glyph_names: Option<Vec<String>>,
 }
 
-impl TableV3 {
-    pub const VERSION: u32 = 0x00030000;
+impl Table {
+    pub const VERSION_2: u32 = 0x00020000;
+    pub const VERSION_3: u32 = 0x00030000;
+
+    pub fn version(&self) -> u32 {
+        match self.glyph_names {
+            Some(_) => Self::VERSION_2,
+            None => Self::VERSION_3,
+        }
+    }
 }
 
-impl ITable for TableV3 {
+impl ITable for Table {
     fn name(&self) -> &'static [u8; 4] {
         b"post"
     }
 
     fn write(&self, writer: &mut impl bytes::BufMut) {
-        writer.put_u32(Self::VERSION);
+        writer.put_u32(self.version());
         writer.put_i32(self.italic_angle.to_bits());
         writer.put_i16(self.underline_position);
         writer.put_i16(self.underline_thickness);
@@ -37,5 +55,335 @@ impl ITable for TableV3 {
         writer.put_u32(self.max_mem_type42);
         writer.put_u32(self.min_mem_type1);
         writer.put_u32(self.max_mem_type1);
+
+        if let Some(names) = &self.glyph_names {
+            write_glyph_names(names, writer);
+        }
     }
 }
+
+/// Write the version 2.0 glyph name index and the custom name pool. Names
+/// in [`STANDARD_MAC_NAMES`] refer to the standard Macintosh ordering and
+/// others are written once to the pool.
+fn write_glyph_names(names: &[String], writer: &mut impl bytes::BufMut) {
+    let mut pool: Vec<&str> = vec![];
+    let mut pool_index = std::collections::HashMap::new();
+
+    writer.put_u16(names.len() as u16);
+    for name in names {
+        let index = match STANDARD_MAC_NAMES.iter().position(|it| it == name) {
+            Some(ix) => ix,
+            None => *pool_index.entry(name.as_str()).or_insert_with(|| {
+                pool.push(name);
+                STANDARD_MAC_NAMES.len() + pool.len() - 1
+            }),
+        };
+        writer.put_u16(index as u16);
+    }
+
+    for name in pool {
+        debug_assert!(
+            name.is_ascii() && name.len() < 64,
+            "glyph name {name:?} is not a valid PostScript name"
+        );
+        writer.put_u8(name.len() as u8);
+        writer.put_slice(name.as_bytes());
+    }
+}
+
+/// The standard Macintosh glyph ordering, whose names are not stored in
+/// the name pool of version 2.0 tables.
+pub const STANDARD_MAC_NAMES: [&str; 258] = [
+    ".notdef",
+    ".null",
+    "nonmarkingreturn",
+    "space",
+    "exclam",
+    "quotedbl",
+    "numbersign",
+    "dollar",
+    "percent",
+    "ampersand",
+    "quotesingle",
+    "parenleft",
+    "parenright",
+    "asterisk",
+    "plus",
+    "comma",
+    "hyphen",
+    "period",
+    "slash",
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "colon",
+    "semicolon",
+    "less",
+    "equal",
+    "greater",
+    "question",
+    "at",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "bracketleft",
+    "backslash",
+    "bracketright",
+    "asciicircum",
+    "underscore",
+    "grave",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "braceleft",
+    "bar",
+    "braceright",
+    "asciitilde",
+    "Adieresis",
+    "Aring",
+    "Ccedilla",
+    "Eacute",
+    "Ntilde",
+    "Odieresis",
+    "Udieresis",
+    "aacute",
+    "agrave",
+    "acircumflex",
+    "adieresis",
+    "atilde",
+    "aring",
+    "ccedilla",
+    "eacute",
+    "egrave",
+    "ecircumflex",
+    "edieresis",
+    "iacute",
+    "igrave",
+    "icircumflex",
+    "idieresis",
+    "ntilde",
+    "oacute",
+    "ograve",
+    "ocircumflex",
+    "odieresis",
+    "otilde",
+    "uacute",
+    "ugrave",
+    "ucircumflex",
+    "udieresis",
+    "dagger",
+    "degree",
+    "cent",
+    "sterling",
+    "section",
+    "bullet",
+    "paragraph",
+    "germandbls",
+    "registered",
+    "copyright",
+    "trademark",
+    "acute",
+    "dieresis",
+    "notequal",
+    "AE",
+    "Oslash",
+    "infinity",
+    "plusminus",
+    "lessequal",
+    "greaterequal",
+    "yen",
+    "mu",
+    "partialdiff",
+    "summation",
+    "product",
+    "pi",
+    "integral",
+    "ordfeminine",
+    "ordmasculine",
+    "Omega",
+    "ae",
+    "oslash",
+    "questiondown",
+    "exclamdown",
+    "logicalnot",
+    "radical",
+    "florin",
+    "approxequal",
+    "Delta",
+    "guillemotleft",
+    "guillemotright",
+    "ellipsis",
+    "nonbreakingspace",
+    "Agrave",
+    "Atilde",
+    "Otilde",
+    "OE",
+    "oe",
+    "endash",
+    "emdash",
+    "quotedblleft",
+    "quotedblright",
+    "quoteleft",
+    "quoteright",
+    "divide",
+    "lozenge",
+    "ydieresis",
+    "Ydieresis",
+    "fraction",
+    "currency",
+    "guilsinglleft",
+    "guilsinglright",
+    "fi",
+    "fl",
+    "daggerdbl",
+    "periodcentered",
+    "quotesinglbase",
+    "quotedblbase",
+    "perthousand",
+    "Acircumflex",
+    "Ecircumflex",
+    "Aacute",
+    "Edieresis",
+    "Egrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Igrave",
+    "Oacute",
+    "Ocircumflex",
+    "apple",
+    "Ograve",
+    "Uacute",
+    "Ucircumflex",
+    "Ugrave",
+    "dotlessi",
+    "circumflex",
+    "tilde",
+    "macron",
+    "breve",
+    "dotaccent",
+    "ring",
+    "cedilla",
+    "hungarumlaut",
+    "ogonek",
+    "caron",
+    "Lslash",
+    "lslash",
+    "Scaron",
+    "scaron",
+    "Zcaron",
+    "zcaron",
+    "brokenbar",
+    "Eth",
+    "eth",
+    "Yacute",
+    "yacute",
+    "Thorn",
+    "thorn",
+    "minus",
+    "multiply",
+    "onesuperior",
+    "twosuperior",
+    "threesuperior",
+    "onehalf",
+    "onequarter",
+    "threequarters",
+    "franc",
+    "Gbreve",
+    "gbreve",
+    "Idotaccent",
+    "Scedilla",
+    "scedilla",
+    "Cacute",
+    "cacute",
+    "Ccaron",
+    "ccaron",
+    "dcroat",
+];
+
+#[test]
+fn test_glyph_names() {
+    let table = Table {
+        italic_angle: Fixed::from_num(0),
+        underline_position: 0,
+        underline_thickness: 0,
+        is_fixed_pitch: true,
+        min_mem_type42: 0,
+        max_mem_type42: 0,
+        min_mem_type1: 0,
+        max_mem_type1: 0,
+        glyph_names: Some(
+            [".notdef", "A", "uni0410", "f_f", "uni0410"]
+                .map(String::from)
+                .to_vec(),
+        ),
+    };
+    let mut buf = vec![];
+    table.write(&mut buf);
+
+    assert_eq!(&buf[..4], &[0, 2, 0, 0]);
+    let rest = &buf[32..];
+    assert_eq!(
+        rest,
+        [
+            &[0, 5][..],
+            &[0, 0, 0, 36, 1, 2, 1, 3, 1, 2],
+            b"\x07uni0410",
+            b"\x03f_f",
+        ]
+        .concat()
+    );
+}
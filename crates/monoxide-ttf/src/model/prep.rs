@@ -0,0 +1,21 @@
+//! The `prep` table.
+
+use super::ITable;
+
+/// `prep` table. The control value program, run whenever the point size or
+/// transformation changes, before any glyph is hinted.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    /// The bytecode of the program, see [`super::inst`].
+    pub instructions: Vec<u8>,
+}
+
+impl ITable for Table {
+    fn name(&self) -> &'static [u8; 4] {
+        b"prep"
+    }
+
+    fn write(&self, writer: &mut impl bytes::BufMut) {
+        writer.put_slice(&self.instructions);
+    }
+}
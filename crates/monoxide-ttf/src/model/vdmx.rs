@@ -0,0 +1,163 @@
+//! The `VDMX` table.
+//!
+//! <https://learn.microsoft.com/en-us/typography/opentype/spec/vdmx>
+
+use super::ITable;
+
+pub const VERSION: u16 = 1;
+
+/// The character set the `yMax` and `yMin` values are computed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CharSet {
+    /// All glyphs in the font
+    AllGlyphs = 0,
+    /// The Windows ANSI subset
+    WindowsAnsi = 1,
+}
+
+/// The aspect ratios a group of records applies to.
+///
+/// A ratio of `0:0-0` matches all aspect ratios, and should be the last one
+/// in the table if present.
+#[derive(Debug, Clone, Copy)]
+pub struct RatioRange {
+    pub char_set: CharSet,
+    pub x_ratio: u8,
+    pub y_start_ratio: u8,
+    pub y_end_ratio: u8,
+}
+
+impl RatioRange {
+    /// The ratio range that matches all aspect ratios.
+    pub const ALL: RatioRange = RatioRange {
+        char_set: CharSet::AllGlyphs,
+        x_ratio: 0,
+        y_start_ratio: 0,
+        y_end_ratio: 0,
+    };
+}
+
+/// The extents of the font at a specific pixel height.
+#[derive(Debug, Clone, Copy)]
+pub struct VTableRecord {
+    pub y_pel_height: u16,
+    /// Maximum value (in pixels) for this height, rounded up.
+    pub y_max: i16,
+    /// Minimum value (in pixels) for this height, rounded down.
+    pub y_min: i16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Group {
+    /// Records sorted by ascending pixel height.
+    pub records: Vec<VTableRecord>,
+}
+
+impl Group {
+    fn byte_length(&self) -> usize {
+        4 + 6 * self.records.len()
+    }
+}
+
+/// `VDMX` table version 1.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub groups: Vec<Group>,
+    /// The ratio ranges, and the index into [`Table::groups`] of the group
+    /// each applies to.
+    pub ratios: Vec<(RatioRange, usize)>,
+}
+
+impl ITable for Table {
+    fn name(&self) -> &'static [u8; 4] {
+        b"VDMX"
+    }
+
+    fn write(&self, writer: &mut impl bytes::BufMut) {
+        writer.put_u16(VERSION);
+        writer.put_u16(self.groups.len() as u16);
+        writer.put_u16(self.ratios.len() as u16);
+        for (ratio, _) in &self.ratios {
+            writer.put_u8(ratio.char_set as u8);
+            writer.put_u8(ratio.x_ratio);
+            writer.put_u8(ratio.y_start_ratio);
+            writer.put_u8(ratio.y_end_ratio);
+        }
+
+        let header_size = 6 + 6 * self.ratios.len();
+        let mut group_offsets = Vec::with_capacity(self.groups.len());
+        let mut offset = header_size;
+        for group in &self.groups {
+            group_offsets.push(offset as u16);
+            offset += group.byte_length();
+        }
+        for &(_, group) in &self.ratios {
+            writer.put_u16(group_offsets[group]);
+        }
+
+        for group in &self.groups {
+            writer.put_u16(group.records.len() as u16);
+            writer.put_u8(group.records.first().map_or(0, |r| r.y_pel_height as u8));
+            writer.put_u8(group.records.last().map_or(0, |r| r.y_pel_height as u8));
+            for record in &group.records {
+                writer.put_u16(record.y_pel_height);
+                writer.put_i16(record.y_max);
+                writer.put_i16(record.y_min);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_group_layout() {
+    let record = |y_pel_height, y_max, y_min| VTableRecord {
+        y_pel_height,
+        y_max,
+        y_min,
+    };
+    let table = Table {
+        groups: vec![
+            Group {
+                records: vec![record(8, 7, -2), record(9, 8, -2)],
+            },
+            Group {
+                records: vec![record(8, 8, -3)],
+            },
+        ],
+        ratios: vec![
+            (
+                RatioRange {
+                    char_set: CharSet::WindowsAnsi,
+                    x_ratio: 1,
+                    y_start_ratio: 1,
+                    y_end_ratio: 1,
+                },
+                1,
+            ),
+            (RatioRange::ALL, 0),
+        ],
+    };
+    let mut buf = vec![];
+    table.write(&mut buf);
+    #[rustfmt::skip]
+    assert_eq!(
+        buf,
+        [
+            0, 1, 0, 2, 0, 2,
+            // Ratio ranges
+            1, 1, 1, 1,
+            0, 0, 0, 0,
+            // Group offsets of the ratios: the header is 18 bytes long, and
+            // the first group 16 bytes
+            0, 34, 0, 18,
+            // Group 0
+            0, 2, 8, 9,
+            0, 8, 0, 7, 0xff, 0xfe,
+            0, 9, 0, 8, 0xff, 0xfe,
+            // Group 1
+            0, 1, 8, 8,
+            0, 8, 0, 8, 0xff, 0xfd,
+        ]
+    );
+}
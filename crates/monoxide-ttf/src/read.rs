@@ -0,0 +1,49 @@
+//! Decoding of the binary tables, shared by [`crate::validate`] and
+//! [`crate::dump`].
+//!
+//! The readers here are lenient: they decode what the tables say and leave it
+//! to the caller to decide what is wrong with it.
+
+pub(crate) mod cmap;
+pub(crate) mod font;
+pub(crate) mod glyf;
+
+pub(crate) fn u8_at(data: &[u8], at: usize) -> Option<u8> {
+    data.get(at).copied()
+}
+
+pub(crate) fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+pub(crate) fn i16_at(data: &[u8], at: usize) -> Option<i16> {
+    u16_at(data, at).map(|x| x as i16)
+}
+
+pub(crate) fn u24_at(data: &[u8], at: usize) -> Option<u32> {
+    let [a, b, c] = data.get(at..at + 3)?.try_into().ok()?;
+    Some(u32::from_be_bytes([0, a, b, c]))
+}
+
+pub(crate) fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The offset of the table with the given tag, for tests that patch the
+/// tables of a written font.
+#[cfg(test)]
+pub(crate) fn table_offset(data: &[u8], tag: &[u8; 4]) -> usize {
+    let font = font::Font::parse(data).expect("the table directory should be valid");
+    let record = (font.records.iter())
+        .find(|record| &record.tag == tag)
+        .expect("the table should be present");
+    record.offset as usize
+}
+
+pub(crate) fn i32_at(data: &[u8], at: usize) -> Option<i32> {
+    u32_at(data, at).map(|x| x as i32)
+}
+
+pub(crate) fn u64_at(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
@@ -0,0 +1,153 @@
+//! Decoding of the `cmap` subtables.
+
+use std::collections::BTreeMap;
+
+use super::{u8_at, u16_at, u24_at, u32_at};
+
+/// The mappings decoded from a subtable, including characters explicitly
+/// mapped to `.notdef`.
+pub(crate) type Mappings = BTreeMap<u32, u32>;
+
+/// Why a subtable could not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Malformed {
+    Truncated,
+    OutOfBounds,
+    /// The segments or groups are not sorted, overlap or are inverted.
+    UnsortedRanges,
+}
+
+/// The glyphs of the variation sequences with the same selector.
+pub(crate) struct VariationSelector {
+    pub selector: u32,
+    /// The characters whose sequence maps to their default glyph.
+    pub default: Vec<u32>,
+    /// The characters whose sequence maps to another glyph.
+    pub non_default: Vec<(u32, u16)>,
+}
+
+/// Decode a format 4 subtable.
+pub(crate) fn read_format4(subtable: &[u8]) -> Result<Mappings, Malformed> {
+    let (Some(length), Some(seg_count_x2)) = (u16_at(subtable, 2), u16_at(subtable, 6)) else {
+        return Err(Malformed::Truncated);
+    };
+    let subtable = (subtable.get(..length as usize)).ok_or(Malformed::OutOfBounds)?;
+    let seg_count = seg_count_x2 as usize / 2;
+    let end_codes = 14;
+    let start_codes = end_codes + seg_count_x2 as usize + 2;
+    let id_deltas = start_codes + seg_count_x2 as usize;
+    let id_range_offsets = id_deltas + seg_count_x2 as usize;
+    if subtable.len() < id_range_offsets + seg_count_x2 as usize {
+        return Err(Malformed::Truncated);
+    }
+    let at = |base: usize, ix: usize| u16_at(subtable, base + 2 * ix).unwrap();
+
+    let mut mappings = Mappings::new();
+    let mut prev_end = None;
+    for ix in 0..seg_count {
+        let (start, end) = (at(start_codes, ix), at(end_codes, ix));
+        if start > end || prev_end.is_some_and(|prev| prev >= start) {
+            return Err(Malformed::UnsortedRanges);
+        }
+        prev_end = Some(end);
+        let (id_delta, id_range_offset) = (at(id_deltas, ix), at(id_range_offsets, ix));
+        for code in start..=end {
+            if code == 0xFFFF {
+                break;
+            }
+            let glyph = if id_range_offset == 0 {
+                code.wrapping_add(id_delta)
+            } else {
+                let glyph_at = id_range_offsets
+                    + 2 * ix
+                    + id_range_offset as usize
+                    + 2 * (code - start) as usize;
+                match u16_at(subtable, glyph_at).ok_or(Malformed::OutOfBounds)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(id_delta),
+                }
+            };
+            mappings.insert(code as u32, glyph as u32);
+        }
+    }
+    Ok(mappings)
+}
+
+/// Decode a format 12 subtable.
+pub(crate) fn read_format12(subtable: &[u8]) -> Result<Mappings, Malformed> {
+    let (Some(length), Some(n_groups)) = (u32_at(subtable, 4), u32_at(subtable, 12)) else {
+        return Err(Malformed::Truncated);
+    };
+    let subtable = (subtable.get(..length as usize)).ok_or(Malformed::OutOfBounds)?;
+    if subtable.len() < 16 + 12 * n_groups as usize {
+        return Err(Malformed::Truncated);
+    }
+
+    let mut mappings = Mappings::new();
+    let mut prev_end = None;
+    for ix in 0..n_groups as usize {
+        let [start, end, glyph] = [0, 4, 8].map(|at| u32_at(subtable, 16 + 12 * ix + at).unwrap());
+        if start > end || end > 0x10FFFF || prev_end.is_some_and(|prev| prev >= start) {
+            return Err(Malformed::UnsortedRanges);
+        }
+        prev_end = Some(end);
+        mappings.extend((start..=end).map(|code| (code, glyph.saturating_add(code - start))));
+    }
+    Ok(mappings)
+}
+
+/// Decode a format 14 subtable.
+pub(crate) fn read_format14(subtable: &[u8]) -> Result<Vec<VariationSelector>, Malformed> {
+    let (Some(length), Some(n_records)) = (u32_at(subtable, 2), u32_at(subtable, 6)) else {
+        return Err(Malformed::Truncated);
+    };
+    let subtable = (subtable.get(..length as usize)).ok_or(Malformed::OutOfBounds)?;
+    let mut selectors = vec![];
+    for ix in 0..n_records as usize {
+        let record = 10 + 11 * ix;
+        let (Some(selector), Some(default_offset), Some(non_default_offset)) = (
+            u24_at(subtable, record),
+            u32_at(subtable, record + 3),
+            u32_at(subtable, record + 7),
+        ) else {
+            return Err(Malformed::Truncated);
+        };
+
+        let mut default = vec![];
+        if default_offset != 0 {
+            let at = default_offset as usize;
+            let n_ranges = u32_at(subtable, at).ok_or(Malformed::OutOfBounds)?;
+            for ix in 0..n_ranges as usize {
+                let range = at + 4 + 4 * ix;
+                let (Some(start), Some(additional)) =
+                    (u24_at(subtable, range), u8_at(subtable, range + 3))
+                else {
+                    return Err(Malformed::OutOfBounds);
+                };
+                default.extend(start..=start + additional as u32);
+            }
+        }
+
+        let mut non_default = vec![];
+        if non_default_offset != 0 {
+            let at = non_default_offset as usize;
+            let n_mappings = u32_at(subtable, at).ok_or(Malformed::OutOfBounds)?;
+            for ix in 0..n_mappings as usize {
+                let mapping = at + 4 + 5 * ix;
+                let (Some(code), Some(glyph)) =
+                    (u24_at(subtable, mapping), u16_at(subtable, mapping + 3))
+                else {
+                    return Err(Malformed::OutOfBounds);
+                };
+                non_default.push((code, glyph));
+            }
+        }
+
+        selectors.push(VariationSelector {
+            selector,
+            default,
+            non_default,
+        });
+    }
+    Ok(selectors)
+}
@@ -0,0 +1,71 @@
+//! Decoding of the table directory.
+
+use super::{u16_at, u32_at};
+
+/// Why the table directory could not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Malformed {
+    Truncated,
+    /// The data is a font collection rather than a single font.
+    Collection,
+}
+
+/// A record of the table directory.
+pub(crate) struct TableRecord {
+    pub tag: [u8; 4],
+    pub checksum: u32,
+    pub offset: u32,
+    pub length: u32,
+}
+
+/// A font file with its tables located.
+pub(crate) struct Font<'a> {
+    pub data: &'a [u8],
+    pub version: u32,
+    /// The table records in directory order.
+    pub records: Vec<TableRecord>,
+}
+
+impl<'a> Font<'a> {
+    /// Decode the table directory. The tables themselves are not checked to be
+    /// in bounds, see [`Font::table_data`].
+    pub fn parse(data: &'a [u8]) -> Result<Self, Malformed> {
+        let (Some(version), Some(n_tables)) = (u32_at(data, 0), u16_at(data, 4)) else {
+            return Err(Malformed::Truncated);
+        };
+        if version == u32::from_be_bytes(*b"ttcf") {
+            return Err(Malformed::Collection);
+        }
+        let records = (0..n_tables as usize)
+            .map(|ix| {
+                let record = data.get(12 + 16 * ix..12 + 16 * (ix + 1))?;
+                let [checksum, offset, length] = [4, 8, 12].map(|at| u32_at(record, at).unwrap());
+                Some(TableRecord {
+                    tag: record[..4].try_into().unwrap(),
+                    checksum,
+                    offset,
+                    length,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Malformed::Truncated)?;
+        Ok(Self {
+            data,
+            version,
+            records,
+        })
+    }
+
+    /// The data of a table, or `None` if it is out of bounds.
+    pub fn table_data(&self, record: &TableRecord) -> Option<&'a [u8]> {
+        let start = record.offset as usize;
+        self.data.get(start..start + record.length as usize)
+    }
+
+    /// The data of the table with the given tag, if it is present and in
+    /// bounds.
+    pub fn table(&self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        let record = self.records.iter().find(|record| &record.tag == tag)?;
+        self.table_data(record)
+    }
+}
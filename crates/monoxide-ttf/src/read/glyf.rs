@@ -0,0 +1,257 @@
+//! Decoding of the glyph outlines in `glyf` and their locations in `loca`.
+
+use super::{i16_at, u16_at, u32_at};
+
+/// Component flags, see [`crate::model::glyf::compound::ComponentFlags`].
+pub(crate) const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+pub(crate) const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+pub(crate) const WE_HAVE_A_SCALE: u16 = 0x0008;
+pub(crate) const MORE_COMPONENTS: u16 = 0x0020;
+pub(crate) const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+pub(crate) const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+pub(crate) const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+pub(crate) const SCALED_COMPONENT_OFFSET: u16 = 0x0800;
+
+/// Outline flags, see [`crate::model::glyf::simple::OutlineFlag`].
+pub(crate) const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const REPEAT: u8 = 0x08;
+const X_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_SAME_OR_POSITIVE: u8 = 0x20;
+pub(crate) const OVERLAP_SIMPLE: u8 = 0x40;
+
+/// A glyph as recorded in `glyf`.
+pub(crate) struct Glyph<'a> {
+    pub bbox: [i16; 4],
+    pub outline: Outline,
+    pub instructions: &'a [u8],
+}
+
+pub(crate) enum Outline {
+    Simple {
+        points: Vec<Point>,
+        /// The index of the last point of each contour.
+        end_points: Vec<u16>,
+    },
+    Compound(Vec<Component>),
+}
+
+/// A point of a simple glyph, in absolute coordinates.
+#[derive(Clone, Copy)]
+pub(crate) struct Point {
+    pub x: i32,
+    pub y: i32,
+    /// The outline flags, less those only concerning the encoding.
+    pub flags: u8,
+}
+
+impl Point {
+    pub fn on_curve(&self) -> bool {
+        self.flags & ON_CURVE_POINT != 0
+    }
+}
+
+/// A component of a compound glyph.
+pub(crate) struct Component {
+    pub flags: u16,
+    pub glyph: u16,
+    /// The offset, or the indices of the points to align in the parent and
+    /// the child glyph.
+    pub placement: Placement,
+    /// The transformation in the order of `xx, xy, yx, yy`, mapping `(x, y)`
+    /// to `(xx * x + yx * y, xy * x + yy * y)`.
+    pub matrix: [f64; 4],
+}
+
+impl Component {
+    pub fn scaled_offset(&self) -> bool {
+        self.flags & SCALED_COMPONENT_OFFSET != 0
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Placement {
+    Offset(i32, i32),
+    Align(u16, u16),
+}
+
+/// Read the glyph offsets in `loca` of the given format, or `None` if the
+/// format is unknown.
+pub(crate) fn loca_offsets(loca: &[u8], format: i16) -> Option<Vec<u32>> {
+    match format {
+        0 => Some(
+            loca.chunks_exact(2)
+                .map(|x| u16_at(x, 0).unwrap() as u32 * 2)
+                .collect(),
+        ),
+        1 => Some(
+            loca.chunks_exact(4)
+                .map(|x| u32_at(x, 0).unwrap())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Parse a glyph, or return what is malformed.
+pub(crate) fn parse_glyph(data: &[u8]) -> Result<Glyph<'_>, &'static str> {
+    if data.is_empty() {
+        let outline = Outline::Simple {
+            points: vec![],
+            end_points: vec![],
+        };
+        return Ok(Glyph {
+            bbox: [0; 4],
+            outline,
+            instructions: &[],
+        });
+    }
+    const TRUNCATED: &str = "the glyph data ends too early";
+    let n_contours = i16_at(data, 0).ok_or(TRUNCATED)?;
+    let bbox = [2, 4, 6, 8].map(|at| i16_at(data, at));
+    let [Some(x_min), Some(y_min), Some(x_max), Some(y_max)] = bbox else {
+        return Err(TRUNCATED);
+    };
+    let bbox = [x_min, y_min, x_max, y_max];
+    if n_contours >= 0 {
+        parse_simple(data, n_contours as usize, bbox).ok_or(TRUNCATED)?
+    } else {
+        parse_compound(data, bbox).ok_or(TRUNCATED)?
+    }
+}
+
+fn parse_simple(
+    data: &[u8],
+    n_contours: usize,
+    bbox: [i16; 4],
+) -> Option<Result<Glyph<'_>, &'static str>> {
+    let mut at = 10;
+    let mut end_points = Vec::with_capacity(n_contours);
+    for _ in 0..n_contours {
+        let end = u16_at(data, at)?;
+        if end_points.last().is_some_and(|&prev| end <= prev) {
+            return Some(Err("the contour end points are not increasing"));
+        }
+        end_points.push(end);
+        at += 2;
+    }
+    let n_points = end_points.last().map_or(0, |&end| end as usize + 1);
+    let n_instructions = u16_at(data, at)? as usize;
+    let instructions = data.get(at + 2..at + 2 + n_instructions)?;
+    at += 2 + n_instructions;
+
+    let mut flags = Vec::with_capacity(n_points);
+    while flags.len() < n_points {
+        let flag = *data.get(at)?;
+        at += 1;
+        let times = if flag & REPEAT != 0 {
+            at += 1;
+            *data.get(at - 1)? as usize + 1
+        } else {
+            1
+        };
+        if flags.len() + times > n_points {
+            return Some(Err("the flags are repeated beyond the last point"));
+        }
+        flags.extend(std::iter::repeat_n(flag, times));
+    }
+
+    let mut read_coords = |short: u8, same_or_positive: u8| {
+        let mut coords = Vec::with_capacity(n_points);
+        let mut value = 0i32;
+        for &flag in &flags {
+            if flag & short != 0 {
+                let delta = *data.get(at)? as i32;
+                at += 1;
+                value += if flag & same_or_positive != 0 {
+                    delta
+                } else {
+                    -delta
+                };
+            } else if flag & same_or_positive == 0 {
+                value += i16_at(data, at)? as i32;
+                at += 2;
+            }
+            coords.push(value);
+        }
+        Some(coords)
+    };
+    let xs = read_coords(X_SHORT_VECTOR, X_SAME_OR_POSITIVE)?;
+    let ys = read_coords(Y_SHORT_VECTOR, Y_SAME_OR_POSITIVE)?;
+    let points = (xs.into_iter().zip(ys).zip(&flags))
+        .map(|((x, y), &flag)| Point {
+            x,
+            y,
+            flags: flag & (ON_CURVE_POINT | OVERLAP_SIMPLE),
+        })
+        .collect();
+    let outline = Outline::Simple { points, end_points };
+    Some(Ok(Glyph {
+        bbox,
+        outline,
+        instructions,
+    }))
+}
+
+fn parse_compound(data: &[u8], bbox: [i16; 4]) -> Option<Result<Glyph<'_>, &'static str>> {
+    let f2dot14 = |at| i16_at(data, at).map(|x| x as f64 / 16384.);
+    let mut at = 10;
+    let mut components = vec![];
+    let mut flags = MORE_COMPONENTS;
+    while flags & MORE_COMPONENTS != 0 {
+        flags = u16_at(data, at)?;
+        let glyph = u16_at(data, at + 2)?;
+        at += 4;
+        let (arg1, arg2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            at += 4;
+            (u16_at(data, at - 4)?, u16_at(data, at - 2)?)
+        } else {
+            at += 2;
+            (*data.get(at - 2)? as u16, *data.get(at - 1)? as u16)
+        };
+        let placement = match (
+            flags & ARGS_ARE_XY_VALUES != 0,
+            flags & ARG_1_AND_2_ARE_WORDS != 0,
+        ) {
+            (true, true) => Placement::Offset(arg1 as i16 as i32, arg2 as i16 as i32),
+            (true, false) => Placement::Offset(arg1 as u8 as i8 as i32, arg2 as u8 as i8 as i32),
+            (false, _) => Placement::Align(arg1, arg2),
+        };
+        let matrix = if flags & WE_HAVE_A_SCALE != 0 {
+            at += 2;
+            let scale = f2dot14(at - 2)?;
+            [scale, 0., 0., scale]
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            at += 4;
+            [f2dot14(at - 4)?, 0., 0., f2dot14(at - 2)?]
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            at += 8;
+            [0, 1, 2, 3]
+                .map(|ix| f2dot14(at - 8 + ix * 2))
+                .map(Option::unwrap_or_default)
+        } else {
+            [1., 0., 0., 1.]
+        };
+        if at > data.len() {
+            return None;
+        }
+        components.push(Component {
+            flags,
+            glyph,
+            placement,
+            matrix,
+        });
+    }
+    let instructions = if flags & WE_HAVE_INSTRUCTIONS != 0 {
+        let n = u16_at(data, at)? as usize;
+        data.get(at + 2..at + 2 + n)?
+    } else {
+        &[]
+    };
+    Some(Ok(Glyph {
+        bbox,
+        outline: Outline::Compound(components),
+        instructions,
+    }))
+}
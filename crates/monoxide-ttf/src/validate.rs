@@ -0,0 +1,510 @@
+//! Structural validation of font files.
+//!
+//! The checks here cover the common failures reported by external validators
+//! such as FontValidator: the table directory and checksums, the consistency
+//! of `loca`, `glyf` and `maxp`, glyph bounding boxes, the `cmap` subtables,
+//! the `name` records, and the metrics shared between `head`, `hhea`,
+//! `hmtx`, `OS/2` and `post`.
+//!
+//! The font is validated in its binary form, so the checks apply to what is
+//! actually written, and to fonts not produced by this crate as well.
+//! Instruction-related `maxp` fields are not checked.
+
+mod cmap;
+mod glyf;
+mod metrics;
+mod name;
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::{
+    model::{FontFile, WriteOptions, table_checksum, ttf_checksum},
+    read::{
+        font::{Font, Malformed},
+        u16_at, u32_at,
+    },
+};
+
+/// The tables required in all fonts.
+const REQUIRED_TABLES: &[&[u8; 4]] = &[
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post",
+];
+
+/// The tables required in fonts with TrueType outlines.
+const REQUIRED_TRUETYPE_TABLES: &[&[u8; 4]] = &[b"glyf", b"loca"];
+
+/// The sfnt versions of fonts with TrueType outlines.
+const TRUETYPE_VERSIONS: &[u32] = &[0x0001_0000, u32::from_be_bytes(*b"true")];
+
+/// The sfnt version of fonts with CFF outlines.
+const CFF_VERSION: u32 = u32::from_be_bytes(*b"OTTO");
+
+/// The value the whole font sums to with the checksum adjustment in `head`.
+const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The font works, but may look or behave differently than intended in
+    /// some applications.
+    Warning,
+    /// The font violates the specification.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// The kinds of problems found by the validator.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DiagnosticKind {
+    #[error("the data ends before the structure is complete")]
+    Truncated,
+    #[error("an offset points beyond the end of the data")]
+    OutOfBounds,
+    #[error("sfnt version {0:#010x} is not recognized")]
+    UnknownVersion(u32),
+    #[error("the table is required but missing")]
+    MissingTable,
+    #[error("the table directory is not sorted by tag")]
+    UnsortedDirectory,
+    #[error("the table starts at offset {0}, which is not aligned to 4 bytes")]
+    Misaligned(u32),
+    #[error("the checksum is {recorded:#010x}, but the data sums to {actual:#010x}")]
+    Checksum { recorded: u32, actual: u32 },
+    #[error("`{field}` is {recorded}, expected {expected}")]
+    FieldMismatch {
+        field: &'static str,
+        recorded: i64,
+        expected: i64,
+    },
+    #[error("`{field}` is {recorded}, more than the {actual} needed")]
+    FieldExcessive {
+        field: &'static str,
+        recorded: i64,
+        actual: i64,
+    },
+    #[error("`{field}` is {value}, outside {min}..={max}")]
+    OutOfRange {
+        field: &'static str,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+    #[error("the table is {actual} bytes long, expected {expected}")]
+    Length { expected: usize, actual: usize },
+
+    #[error("the glyph offset is smaller than that of the previous glyph")]
+    LocaNotMonotonic,
+    #[error("the glyph data is malformed: {0}")]
+    MalformedGlyph(&'static str),
+    #[error("a component refers to glyph {0}, which does not exist")]
+    MissingComponent(u16),
+    #[error("the glyph contains itself through its components")]
+    RecursiveComponent,
+    #[error("the bounding box is {recorded:?}, but the points span {actual:?}")]
+    BoundingBox {
+        recorded: [i16; 4],
+        actual: [i16; 4],
+    },
+    #[error("the glyph is {advance} units wide in a fixed pitch font of width {expected}")]
+    NotFixedPitch { advance: u16, expected: u16 },
+    #[error("`{field}` is {win}, so the font is clipped at {extent}")]
+    ClippedByWinMetrics {
+        field: &'static str,
+        win: u16,
+        extent: i16,
+    },
+
+    #[error("there is no Unicode subtable")]
+    NoUnicodeSubtable,
+    #[error("the encoding records are not sorted by platform and encoding")]
+    UnsortedEncodingRecords,
+    #[error("subtable format {0} is not supported by the validator")]
+    UnsupportedCmapFormat(u16),
+    #[error("the ranges of the format {0} subtable are unsorted or overlapping")]
+    UnsortedRanges(u16),
+    #[error("the format 4 subtable does not end with a segment for U+FFFF")]
+    MissingFinalSegment,
+    #[error("U+{code:04X} maps to glyph {glyph}, which does not exist")]
+    MissingGlyph { code: u32, glyph: u32 },
+    #[error("U+{0:04X} is mapped differently by the format 4 and 12 subtables")]
+    InconsistentMapping(u32),
+    #[error("{0:?} is not mapped to any glyph")]
+    Unmapped(char),
+
+    #[error("the name records are not sorted")]
+    UnsortedNameRecords,
+    #[error("name {name_id} of platform {platform_id} is recorded more than once")]
+    DuplicateName { platform_id: u16, name_id: u16 },
+    #[error("name {name_id} of platform {platform_id} is not valid UTF-16")]
+    InvalidUtf16 { platform_id: u16, name_id: u16 },
+    #[error("name {name_id} of platform {platform_id} is required but missing")]
+    MissingName { platform_id: u16, name_id: u16 },
+    #[error("the PostScript name {0:?} contains forbidden characters or is too long")]
+    InvalidPostscriptName(String),
+}
+
+impl DiagnosticKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::FieldExcessive { .. }
+            | DiagnosticKind::ClippedByWinMetrics { .. }
+            | DiagnosticKind::UnsupportedCmapFormat(_)
+            | DiagnosticKind::Unmapped(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A problem found in a font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The table the problem is found in, or `None` for the offset table.
+    pub table: Option<[u8; 4]>,
+    /// The glyph the problem is specific to, if any.
+    pub glyph: Option<u16>,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity())?;
+        match &self.table {
+            Some(tag) => write!(f, " in `{}`", String::from_utf8_lossy(tag))?,
+            None => write!(f, " in the offset table")?,
+        }
+        if let Some(glyph) = self.glyph {
+            write!(f, ", glyph {glyph}")?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+/// Validate a font as it would be written with the default options.
+pub fn font_file(font: &FontFile) -> std::io::Result<Vec<Diagnostic>> {
+    let mut buf = vec![];
+    font.write(&WriteOptions::default(), &mut buf)?;
+    Ok(font_data(&buf))
+}
+
+/// Validate the binary data of a font file.
+pub fn font_data(data: &[u8]) -> Vec<Diagnostic> {
+    let mut out = Diagnostics::default();
+    let Some(font) = Font::parse_checked(data, &mut out) else {
+        return out.0;
+    };
+    font.check_head(&mut out);
+    let glyphs = glyf::check(&font, &mut out);
+    if let Some(glyphs) = &glyphs {
+        metrics::check(&font, glyphs, &mut out);
+    }
+    cmap::check(&font, &mut out);
+    name::check(&font, &mut out);
+    out.0
+}
+
+/// The collected diagnostics.
+#[derive(Default)]
+struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    fn push(&mut self, table: &[u8; 4], glyph: Option<u16>, kind: DiagnosticKind) {
+        self.0.push(Diagnostic {
+            table: Some(*table),
+            glyph,
+            kind,
+        });
+    }
+
+    fn push_header(&mut self, kind: DiagnosticKind) {
+        self.0.push(Diagnostic {
+            table: None,
+            glyph: None,
+            kind,
+        });
+    }
+
+    /// Compare a field with its expected value.
+    fn expect(&mut self, table: &[u8; 4], field: &'static str, recorded: i64, expected: i64) {
+        if recorded != expected {
+            let kind = DiagnosticKind::FieldMismatch {
+                field,
+                recorded,
+                expected,
+            };
+            self.push(table, None, kind);
+        }
+    }
+
+    /// Check that a maximum recorded in a field is at least the actual value.
+    fn expect_max(&mut self, table: &[u8; 4], field: &'static str, recorded: i64, actual: i64) {
+        if recorded < actual {
+            self.expect(table, field, recorded, actual);
+        } else if recorded > actual {
+            let kind = DiagnosticKind::FieldExcessive {
+                field,
+                recorded,
+                actual,
+            };
+            self.push(table, None, kind);
+        }
+    }
+}
+
+impl Font<'_> {
+    /// Check the offset table and the checksums, and locate the tables.
+    fn parse_checked<'a>(data: &'a [u8], out: &mut Diagnostics) -> Option<Font<'a>> {
+        let font = match Font::parse(data) {
+            Ok(font) => font,
+            Err(Malformed::Truncated) => {
+                out.push_header(DiagnosticKind::Truncated);
+                return None;
+            }
+            Err(Malformed::Collection) => {
+                let version = u32_at(data, 0).expect("header is in bounds");
+                out.push_header(DiagnosticKind::UnknownVersion(version));
+                return None;
+            }
+        };
+        let truetype = font.is_truetype();
+        if !truetype && font.version != CFF_VERSION {
+            out.push_header(DiagnosticKind::UnknownVersion(font.version));
+            return None;
+        }
+
+        let n_tables = font.records.len() as u16;
+        let entry_selector = if n_tables == 0 { 0 } else { n_tables.ilog2() };
+        let search_range = (1u16 << entry_selector).wrapping_mul(16);
+        let search_params = [
+            ("searchRange", 6, search_range),
+            ("entrySelector", 8, entry_selector as u16),
+            (
+                "rangeShift",
+                10,
+                n_tables.wrapping_mul(16).wrapping_sub(search_range),
+            ),
+        ];
+        for (field, at, expected) in search_params {
+            let recorded = u16_at(data, at).expect("header is in bounds");
+            if recorded != expected {
+                out.push_header(DiagnosticKind::FieldMismatch {
+                    field,
+                    recorded: recorded.into(),
+                    expected: expected.into(),
+                });
+            }
+        }
+
+        let mut prev_tag = None;
+        for record in &font.records {
+            let tag = record.tag;
+            if prev_tag.is_some_and(|prev| prev >= tag) {
+                out.push_header(DiagnosticKind::UnsortedDirectory);
+            }
+            prev_tag = Some(tag);
+
+            if !record.offset.is_multiple_of(4) {
+                out.push(&tag, None, DiagnosticKind::Misaligned(record.offset));
+            }
+            let Some(table) = font.table_data(record) else {
+                out.push(&tag, None, DiagnosticKind::OutOfBounds);
+                continue;
+            };
+            // The padding is included in the checksum, as long as it is there
+            let offset = record.offset as usize;
+            let padded = data
+                .get(offset..offset + (record.length as usize).next_multiple_of(4))
+                .unwrap_or(table);
+            let actual = table_checksum(&tag, padded);
+            if record.checksum != actual {
+                let kind = DiagnosticKind::Checksum {
+                    recorded: record.checksum,
+                    actual,
+                };
+                out.push(&tag, None, kind);
+            }
+        }
+
+        let outlines = if truetype {
+            REQUIRED_TRUETYPE_TABLES
+        } else {
+            &[]
+        };
+        for &tag in REQUIRED_TABLES.iter().chain(outlines) {
+            if font.table(tag).is_none() {
+                out.push(tag, None, DiagnosticKind::MissingTable);
+            }
+        }
+
+        if let Some(recorded) = font.table(b"head").and_then(|head| u32_at(head, 8)) {
+            let sum = ttf_checksum(data).wrapping_sub(recorded);
+            let expected = CHECKSUM_MAGIC.wrapping_sub(sum);
+            if recorded != expected {
+                out.push(
+                    b"head",
+                    None,
+                    DiagnosticKind::FieldMismatch {
+                        field: "checksumAdjustment",
+                        recorded: recorded.into(),
+                        expected: expected.into(),
+                    },
+                );
+            }
+        }
+
+        Some(font)
+    }
+
+    fn is_truetype(&self) -> bool {
+        TRUETYPE_VERSIONS.contains(&self.version)
+    }
+
+    /// Check the fields of `head` that don't depend on other tables.
+    fn check_head(&self, out: &mut Diagnostics) {
+        let Some(head) = self.table(b"head") else {
+            return;
+        };
+        let (Some(magic), Some(units_per_em)) = (u32_at(head, 12), u16_at(head, 18)) else {
+            out.push(b"head", None, DiagnosticKind::Truncated);
+            return;
+        };
+        out.expect(b"head", "magicNumber", magic.into(), 0x5F0F3CF5);
+        if !(16..=16384).contains(&units_per_em) {
+            let kind = DiagnosticKind::OutOfRange {
+                field: "unitsPerEm",
+                value: units_per_em.into(),
+                min: 16,
+                max: 16384,
+            };
+            out.push(b"head", None, kind);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_font() -> FontFile {
+    use crate::{
+        hl::builder::{FontBuilder, test_metrics, test_rect},
+        model::glyf::{
+            Glyph,
+            compound::{Args, Component, ComponentFlags, CompoundGlyph, Scale},
+        },
+    };
+
+    let mut compound = CompoundGlyph {
+        common: test_rect(50, 0, 450, 700).common().clone(),
+        components: vec![Component {
+            flags: ComponentFlags::empty(),
+            glyph_index: 1,
+            args: Args::Offset { x: 0, y: 100 },
+            scale: Scale::One,
+        }],
+        instructions: vec![],
+    };
+    compound.common.y_min += 100;
+    compound.common.y_max += 100;
+    FontBuilder::new(test_metrics())
+        .glyph(test_rect(50, 0, 450, 700), 500)
+        .glyph(test_rect(50, 0, 450, 700), 500)
+        .glyph(Glyph::Compound(compound), 500)
+        .glyph(Glyph::Simple(Default::default()), 500)
+        .cmap([('A', 1), ('B', 2), (' ', 3)])
+        .timestamp(std::time::UNIX_EPOCH)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_valid_font() {
+    let diagnostics = font_file(&test_font()).unwrap();
+    assert_eq!(diagnostics, []);
+}
+
+#[test]
+fn test_invalid_font() {
+    use crate::read::table_offset;
+
+    let mut buf = vec![];
+    test_font().write(&WriteOptions::debug(), &mut buf).unwrap();
+
+    // Move the compound glyph away from where its bounding box says
+    let [glyf, loca, maxp] = [b"glyf", b"loca", b"maxp"].map(|tag| table_offset(&buf, tag));
+    let compound = glyf + u32_at(&buf, loca + 8).unwrap() as usize;
+    let y_offset = compound + 10 + 4 + 1;
+    buf[y_offset] = 0;
+    // Claim more components than any glyph has
+    buf[maxp + 28..maxp + 30].copy_from_slice(&5u16.to_be_bytes());
+
+    let diagnostics = font_data(&buf);
+    let kinds = diagnostics
+        .iter()
+        .map(|d| (d.table.as_ref(), d.glyph, &d.kind))
+        .collect::<Vec<_>>();
+    assert!(kinds.contains(&(
+        Some(b"glyf"),
+        Some(2),
+        &DiagnosticKind::BoundingBox {
+            recorded: [50, 100, 450, 800],
+            actual: [50, 0, 450, 700],
+        }
+    )));
+    assert!(kinds.contains(&(
+        Some(b"maxp"),
+        None,
+        &DiagnosticKind::FieldExcessive {
+            field: "maxComponentElements",
+            recorded: 5,
+            actual: 1,
+        }
+    )));
+    let checksum_errors = (diagnostics.iter())
+        .filter(|d| matches!(d.kind, DiagnosticKind::Checksum { .. }))
+        .count();
+    assert_eq!(checksum_errors, 2);
+    assert!(diagnostics.iter().any(|d| matches!(
+        d.kind,
+        DiagnosticKind::FieldMismatch {
+            field: "checksumAdjustment",
+            ..
+        }
+    )));
+
+    // Collections are not read as a single font
+    buf[..4].copy_from_slice(b"ttcf");
+    let ttcf = u32::from_be_bytes(*b"ttcf");
+    assert_eq!(
+        font_data(&buf).iter().map(|d| &d.kind).collect::<Vec<_>>(),
+        [&DiagnosticKind::UnknownVersion(ttcf)]
+    );
+}
+
+#[test]
+fn test_many_tables() {
+    // The search parameters of 4096 tables or more overflow 16 bits.
+    let n_tables = 4096u16;
+    let mut buf = vec![0; 12 + 16 * n_tables as usize];
+    buf[..4].copy_from_slice(&[0, 1, 0, 0]);
+    buf[4..6].copy_from_slice(&n_tables.to_be_bytes());
+    let diagnostics = font_data(&buf);
+    assert!(diagnostics.iter().any(|d| d.kind
+        == DiagnosticKind::FieldMismatch {
+            field: "entrySelector",
+            recorded: 0,
+            expected: 12,
+        }));
+}
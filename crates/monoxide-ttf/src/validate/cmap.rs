@@ -0,0 +1,136 @@
+//! Checks of the `cmap` subtables, their coverage, and the character range in
+//! `OS/2`.
+
+use std::collections::BTreeMap;
+
+use super::{DiagnosticKind, Diagnostics, Font};
+use crate::read::{
+    cmap::{Malformed, Mappings, read_format4, read_format12},
+    u16_at, u32_at,
+};
+
+pub(super) fn check(font: &Font<'_>, out: &mut Diagnostics) {
+    let Some(cmap) = font.table(b"cmap") else {
+        return;
+    };
+    let n_glyphs = font.table(b"maxp").and_then(|maxp| u16_at(maxp, 4));
+    let Some(n_records) = u16_at(cmap, 2) else {
+        out.push(b"cmap", None, DiagnosticKind::Truncated);
+        return;
+    };
+
+    let mut prev = None;
+    let mut has_unicode = false;
+    // Subtables shared by several encodings are only decoded once
+    let mut decoded = BTreeMap::<u32, (u16, Option<Mappings>)>::new();
+    for ix in 0..n_records as usize {
+        let record = 4 + 8 * ix;
+        let (Some(platform_id), Some(encoding_id), Some(offset)) = (
+            u16_at(cmap, record),
+            u16_at(cmap, record + 2),
+            u32_at(cmap, record + 4),
+        ) else {
+            out.push(b"cmap", None, DiagnosticKind::Truncated);
+            return;
+        };
+        if prev.is_some_and(|prev| prev >= (platform_id, encoding_id)) {
+            out.push(b"cmap", None, DiagnosticKind::UnsortedEncodingRecords);
+        }
+        prev = Some((platform_id, encoding_id));
+        has_unicode |= platform_id == 0 || (platform_id == 3 && matches!(encoding_id, 1 | 10));
+        if decoded.contains_key(&offset) {
+            continue;
+        }
+
+        let Some(subtable) = cmap.get(offset as usize..) else {
+            out.push(b"cmap", None, DiagnosticKind::OutOfBounds);
+            continue;
+        };
+        let Some(format) = u16_at(subtable, 0) else {
+            out.push(b"cmap", None, DiagnosticKind::Truncated);
+            continue;
+        };
+        let mappings = match format {
+            4 => Some(read_format4(subtable)),
+            12 => Some(read_format12(subtable)),
+            // Variation sequences don't map characters by themselves
+            14 => None,
+            _ => {
+                out.push(b"cmap", None, DiagnosticKind::UnsupportedCmapFormat(format));
+                None
+            }
+        };
+        let mappings = match mappings.transpose() {
+            Ok(mappings) => mappings,
+            Err(malformed) => {
+                report(format, malformed, out);
+                None
+            }
+        };
+        if format == 4 && mappings.is_some() && !has_final_segment(subtable) {
+            out.push(b"cmap", None, DiagnosticKind::MissingFinalSegment);
+        }
+        if let (Some(mappings), Some(n_glyphs)) = (&mappings, n_glyphs)
+            && let Some((&code, &glyph)) = mappings.iter().find(|&(_, &g)| g >= n_glyphs as u32)
+        {
+            out.push(b"cmap", None, DiagnosticKind::MissingGlyph { code, glyph });
+        }
+        decoded.insert(offset, (format, mappings));
+    }
+    if !has_unicode {
+        out.push(b"cmap", None, DiagnosticKind::NoUnicodeSubtable);
+    }
+
+    let first_of = |format| {
+        (decoded.values()).find_map(|(f, mappings)| (*f == format).then_some(mappings.as_ref())?)
+    };
+    let (fmt4, fmt12) = (first_of(4), first_of(12));
+    if let (Some(fmt4), Some(fmt12)) = (fmt4, fmt12) {
+        let mut codes = fmt4.keys().chain(fmt12.range(..=0xFFFF).map(|(c, _)| c));
+        if let Some(&code) = codes.find(|c| fmt4.get(c) != fmt12.get(c)) {
+            out.push(b"cmap", None, DiagnosticKind::InconsistentMapping(code));
+        }
+    }
+
+    let Some(mappings) = fmt12.or(fmt4) else {
+        return;
+    };
+    if !mappings.contains_key(&(' ' as u32)) {
+        out.push(b"cmap", None, DiagnosticKind::Unmapped(' '));
+    }
+    if let Some(os2) = font.table(b"OS/2")
+        && let (Some(first), Some(last)) = (u16_at(os2, 64), u16_at(os2, 66))
+    {
+        let codes = mappings.keys().map(|&c| c.min(0xFFFF) as i64);
+        let (expected_first, expected_last) = (codes.clone().min(), codes.max());
+        out.expect(
+            b"OS/2",
+            "usFirstCharIndex",
+            first.into(),
+            expected_first.unwrap_or(0),
+        );
+        out.expect(
+            b"OS/2",
+            "usLastCharIndex",
+            last.into(),
+            expected_last.unwrap_or(0),
+        );
+    }
+}
+
+/// Whether the last segment of a format 4 subtable ends at `0xFFFF`, as
+/// required.
+fn has_final_segment(subtable: &[u8]) -> bool {
+    let seg_count_x2 = u16_at(subtable, 6).unwrap_or_default() as usize;
+    seg_count_x2 > 0 && u16_at(subtable, 12 + seg_count_x2) == Some(0xFFFF)
+}
+
+/// Report why a subtable of the given format could not be decoded.
+fn report(format: u16, malformed: Malformed, out: &mut Diagnostics) {
+    let kind = match malformed {
+        Malformed::Truncated => DiagnosticKind::Truncated,
+        Malformed::OutOfBounds => DiagnosticKind::OutOfBounds,
+        Malformed::UnsortedRanges => DiagnosticKind::UnsortedRanges(format),
+    };
+    out.push(b"cmap", None, kind);
+}
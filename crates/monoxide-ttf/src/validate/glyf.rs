@@ -0,0 +1,291 @@
+//! Checks of the glyph outlines in `glyf`, their locations in `loca` and the
+//! glyph statistics in `maxp`.
+
+use std::collections::BTreeSet;
+
+use super::{DiagnosticKind, Diagnostics, Font};
+use crate::read::{
+    glyf::{Glyph, Outline, Placement, loca_offsets, parse_glyph},
+    i16_at, u16_at, u32_at,
+};
+
+/// What the metrics checks need to know about a glyph.
+pub(super) struct GlyphInfo {
+    /// The recorded bounding box, if the glyph has any points.
+    pub bbox: Option<[i16; 4]>,
+}
+
+/// Check the glyphs, returning their information if they can be located.
+pub(super) fn check(font: &Font<'_>, out: &mut Diagnostics) -> Option<Vec<GlyphInfo>> {
+    if !font.is_truetype() {
+        return None;
+    }
+    let (maxp, head, loca, glyf) = (
+        font.table(b"maxp")?,
+        font.table(b"head")?,
+        font.table(b"loca")?,
+        font.table(b"glyf")?,
+    );
+    let (Some(maxp_version), Some(n_glyphs)) = (u32_at(maxp, 0), u16_at(maxp, 4)) else {
+        out.push(b"maxp", None, DiagnosticKind::Truncated);
+        return None;
+    };
+    out.expect(b"maxp", "version", maxp_version.into(), 0x0001_0000);
+    let Some(loca_format) = i16_at(head, 50) else {
+        out.push(b"head", None, DiagnosticKind::Truncated);
+        return None;
+    };
+
+    let offsets = read_loca(loca, loca_format, n_glyphs, out)?;
+    let mut glyphs = vec![];
+    for (gid, range) in offsets.windows(2).enumerate() {
+        let gid = gid as u16;
+        if range[1] < range[0] {
+            out.push(b"loca", Some(gid), DiagnosticKind::LocaNotMonotonic);
+            glyphs.push(None);
+            continue;
+        }
+        let Some(data) = glyf.get(range[0] as usize..range[1] as usize) else {
+            out.push(b"glyf", Some(gid), DiagnosticKind::OutOfBounds);
+            glyphs.push(None);
+            continue;
+        };
+        match parse_glyph(data) {
+            Ok(glyph) => glyphs.push(Some(glyph)),
+            Err(reason) => {
+                out.push(b"glyf", Some(gid), DiagnosticKind::MalformedGlyph(reason));
+                glyphs.push(None);
+            }
+        }
+    }
+
+    let mut resolver = Resolver {
+        glyphs: &glyphs,
+        resolved: vec![None; glyphs.len()],
+        visiting: vec![],
+        recursive: BTreeSet::new(),
+        missing: BTreeSet::new(),
+    };
+    let resolved = (0..glyphs.len())
+        .map(|gid| resolver.resolve(gid as u16))
+        .collect::<Vec<_>>();
+    for &gid in &resolver.recursive {
+        out.push(b"glyf", Some(gid), DiagnosticKind::RecursiveComponent);
+    }
+    for &(gid, component) in &resolver.missing {
+        let kind = DiagnosticKind::MissingComponent(component);
+        out.push(b"glyf", Some(gid), kind);
+    }
+
+    let mut stats = MaxpStats::default();
+    let mut infos = vec![];
+    for (gid, (glyph, resolved)) in glyphs.iter().zip(&resolved).enumerate() {
+        let (Some(glyph), Some(resolved)) = (glyph, resolved) else {
+            infos.push(GlyphInfo { bbox: None });
+            continue;
+        };
+        stats.add(glyph, resolved);
+        let bbox = (!resolved.points.is_empty()).then_some(glyph.bbox);
+        if let Some(recorded) = bbox {
+            let actual = resolved.bbox();
+            let tolerance = if resolved.exact { 0 } else { 1 };
+            let differs = (recorded.iter().zip(&actual)).any(|(r, a)| r.abs_diff(*a) > tolerance);
+            if differs {
+                let kind = DiagnosticKind::BoundingBox { recorded, actual };
+                out.push(b"glyf", Some(gid as u16), kind);
+            }
+        }
+        infos.push(GlyphInfo { bbox });
+    }
+    stats.check(maxp, out);
+    Some(infos)
+}
+
+/// Read the glyph offsets, checking the length of `loca` against `maxp` and
+/// `head`.
+fn read_loca(loca: &[u8], format: i16, n_glyphs: u16, out: &mut Diagnostics) -> Option<Vec<u32>> {
+    let n_offsets = n_glyphs as usize + 1;
+    let Some(offsets) = loca_offsets(loca, format) else {
+        let kind = DiagnosticKind::OutOfRange {
+            field: "indexToLocFormat",
+            value: format.into(),
+            min: 0,
+            max: 1,
+        };
+        out.push(b"head", None, kind);
+        return None;
+    };
+    let entry_size = if format == 0 { 2 } else { 4 };
+    if loca.len() != n_offsets * entry_size {
+        let kind = DiagnosticKind::Length {
+            expected: n_offsets * entry_size,
+            actual: loca.len(),
+        };
+        out.push(b"loca", None, kind);
+        return None;
+    }
+    Some(offsets)
+}
+
+/// A glyph with its components resolved into points.
+#[derive(Clone)]
+struct Resolved {
+    points: Vec<(f64, f64)>,
+    n_contours: usize,
+    /// Levels of compound glyphs, 0 for simple glyphs.
+    depth: usize,
+    /// Whether the points are exact, i.e. no scaling is involved.
+    exact: bool,
+}
+
+impl Resolved {
+    fn bbox(&self) -> [i16; 4] {
+        let (mut x_min, mut y_min) = (f64::INFINITY, f64::INFINITY);
+        let (mut x_max, mut y_max) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for &(x, y) in &self.points {
+            (x_min, y_min) = (x_min.min(x), y_min.min(y));
+            (x_max, y_max) = (x_max.max(x), y_max.max(y));
+        }
+        [x_min, y_min, x_max, y_max].map(|v| v.round() as i16)
+    }
+}
+
+/// Resolves compound glyphs into points, detecting recursive and missing
+/// components.
+struct Resolver<'a> {
+    glyphs: &'a [Option<Glyph<'a>>],
+    resolved: Vec<Option<Option<Resolved>>>,
+    visiting: Vec<u16>,
+    recursive: BTreeSet<u16>,
+    missing: BTreeSet<(u16, u16)>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, gid: u16) -> Option<Resolved> {
+        if let Some(resolved) = &self.resolved[gid as usize] {
+            return resolved.clone();
+        }
+        if self.visiting.contains(&gid) {
+            self.recursive.insert(gid);
+            return None;
+        }
+        self.visiting.push(gid);
+        let resolved = self.resolve_uncached(gid);
+        self.visiting.pop();
+        self.resolved[gid as usize] = Some(resolved.clone());
+        resolved
+    }
+
+    fn resolve_uncached(&mut self, gid: u16) -> Option<Resolved> {
+        let glyph = self.glyphs[gid as usize].as_ref()?;
+        let components = match &glyph.outline {
+            Outline::Simple { points, end_points } => {
+                return Some(Resolved {
+                    points: (points.iter()).map(|p| (p.x as f64, p.y as f64)).collect(),
+                    n_contours: end_points.len(),
+                    depth: 0,
+                    exact: true,
+                });
+            }
+            Outline::Compound(components) => components,
+        };
+
+        let mut result = Resolved {
+            points: vec![],
+            n_contours: 0,
+            depth: 0,
+            exact: true,
+        };
+        let mut complete = true;
+        for component in components {
+            if component.glyph as usize >= self.glyphs.len() {
+                self.missing.insert((gid, component.glyph));
+                complete = false;
+                continue;
+            }
+            let Some(child) = self.resolve(component.glyph) else {
+                complete = false;
+                continue;
+            };
+            let [xx, xy, yx, yy] = component.matrix;
+            let transform = |(x, y): (f64, f64)| (xx * x + yx * y, xy * x + yy * y);
+            let (dx, dy) = match component.placement {
+                Placement::Offset(dx, dy) if component.scaled_offset() => {
+                    transform((dx as f64, dy as f64))
+                }
+                Placement::Offset(dx, dy) => (dx as f64, dy as f64),
+                Placement::Align(parent, child_point) => {
+                    let parent = result.points.get(parent as usize);
+                    let child_point = child.points.get(child_point as usize).copied();
+                    let (Some(&(px, py)), Some(child_point)) = (parent, child_point) else {
+                        complete = false;
+                        continue;
+                    };
+                    let (cx, cy) = transform(child_point);
+                    (px - cx, py - cy)
+                }
+            };
+            let identity = component.matrix == [1., 0., 0., 1.];
+            result.exact &= child.exact && identity;
+            result.n_contours += child.n_contours;
+            result.depth = result.depth.max(child.depth + 1);
+            let points = child.points.into_iter().map(transform);
+            result.points.extend(points.map(|(x, y)| (x + dx, y + dy)));
+        }
+        complete.then_some(result)
+    }
+}
+
+/// The glyph statistics recorded in `maxp`.
+#[derive(Default)]
+struct MaxpStats {
+    max_points: usize,
+    max_contours: usize,
+    max_composite_points: usize,
+    max_composite_contours: usize,
+    max_size_of_instructions: u16,
+    max_component_elements: usize,
+    max_component_depth: usize,
+}
+
+impl MaxpStats {
+    fn add(&mut self, glyph: &Glyph<'_>, resolved: &Resolved) {
+        let n_instructions = glyph.instructions.len() as u16;
+        self.max_size_of_instructions = self.max_size_of_instructions.max(n_instructions);
+        match &glyph.outline {
+            Outline::Simple { .. } => {
+                self.max_points = self.max_points.max(resolved.points.len());
+                self.max_contours = self.max_contours.max(resolved.n_contours);
+            }
+            Outline::Compound(components) => {
+                self.max_composite_points = self.max_composite_points.max(resolved.points.len());
+                self.max_composite_contours = self.max_composite_contours.max(resolved.n_contours);
+                self.max_component_elements = self.max_component_elements.max(components.len());
+                self.max_component_depth = self.max_component_depth.max(resolved.depth);
+            }
+        }
+    }
+
+    fn check(&self, maxp: &[u8], out: &mut Diagnostics) {
+        let fields = [
+            ("maxPoints", 6, self.max_points),
+            ("maxContours", 8, self.max_contours),
+            ("maxCompositePoints", 10, self.max_composite_points),
+            ("maxCompositeContours", 12, self.max_composite_contours),
+            (
+                "maxSizeOfInstructions",
+                26,
+                self.max_size_of_instructions as usize,
+            ),
+            ("maxComponentElements", 28, self.max_component_elements),
+            ("maxComponentDepth", 30, self.max_component_depth),
+        ];
+        for (field, at, actual) in fields {
+            let Some(recorded) = u16_at(maxp, at) else {
+                out.push(b"maxp", None, DiagnosticKind::Truncated);
+                return;
+            };
+            out.expect_max(b"maxp", field, recorded.into(), actual as i64);
+        }
+    }
+}
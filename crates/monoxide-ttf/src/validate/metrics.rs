@@ -0,0 +1,261 @@
+//! Checks of the metrics in `head`, `hhea`, `hmtx`, `OS/2` and `post`
+//! against each other and the glyph outlines.
+
+use super::{DiagnosticKind, Diagnostics, Font, glyf::GlyphInfo};
+use crate::read::{i16_at, u16_at, u32_at};
+
+/// `head` flag for left side bearings equal to `xMin`.
+const LEFT_SIDEBEARING_X0: u16 = 0x0002;
+
+/// `head` macStyle bits.
+const MAC_BOLD: u16 = 0x0001;
+const MAC_ITALIC: u16 = 0x0002;
+
+/// `OS/2` fsSelection bits.
+const FS_ITALIC: u16 = 0x0001;
+const FS_BOLD: u16 = 0x0020;
+
+/// The length of the `OS/2` table by version.
+const OS2_LENGTHS: &[usize] = &[78, 86, 96, 96, 96, 100];
+
+pub(super) fn check(font: &Font<'_>, glyphs: &[GlyphInfo], out: &mut Diagnostics) {
+    let Some(head) = font.table(b"head") else {
+        return;
+    };
+    let Some(advances) = check_hmtx(font, glyphs, head, out) else {
+        return;
+    };
+    check_head_bbox(head, glyphs, out);
+    check_hhea(font, glyphs, &advances, out);
+    check_os2(font, head, &advances, out);
+    check_post(font, &advances, out);
+}
+
+/// The horizontal metrics of a glyph.
+struct Metric {
+    advance: u16,
+    lsb: i16,
+}
+
+/// Check `hmtx` against `hhea` and the glyphs, returning the metrics if the
+/// table can be read.
+fn check_hmtx(
+    font: &Font<'_>,
+    glyphs: &[GlyphInfo],
+    head: &[u8],
+    out: &mut Diagnostics,
+) -> Option<Vec<Metric>> {
+    let (hhea, hmtx) = (font.table(b"hhea")?, font.table(b"hmtx")?);
+    let Some(n_metrics) = u16_at(hhea, 34) else {
+        out.push(b"hhea", None, DiagnosticKind::Truncated);
+        return None;
+    };
+    let n_metrics = n_metrics as usize;
+    if n_metrics == 0 || n_metrics > glyphs.len() {
+        let kind = DiagnosticKind::OutOfRange {
+            field: "numberOfHMetrics",
+            value: n_metrics as i64,
+            min: 1,
+            max: glyphs.len() as i64,
+        };
+        out.push(b"hhea", None, kind);
+        return None;
+    }
+    let expected = 4 * n_metrics + 2 * (glyphs.len() - n_metrics);
+    if hmtx.len() != expected {
+        let kind = DiagnosticKind::Length {
+            expected,
+            actual: hmtx.len(),
+        };
+        out.push(b"hmtx", None, kind);
+        return None;
+    }
+
+    let metrics = (0..glyphs.len())
+        .map(|ix| {
+            if ix < n_metrics {
+                Metric {
+                    advance: u16_at(hmtx, 4 * ix).unwrap(),
+                    lsb: i16_at(hmtx, 4 * ix + 2).unwrap(),
+                }
+            } else {
+                Metric {
+                    advance: u16_at(hmtx, 4 * (n_metrics - 1)).unwrap(),
+                    lsb: i16_at(hmtx, 4 * n_metrics + 2 * (ix - n_metrics)).unwrap(),
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let flags = u16_at(head, 16).unwrap_or_default();
+    if flags & LEFT_SIDEBEARING_X0 != 0 {
+        for (gid, (glyph, metric)) in glyphs.iter().zip(&metrics).enumerate() {
+            if let Some([x_min, ..]) = glyph.bbox
+                && metric.lsb != x_min
+            {
+                let kind = DiagnosticKind::FieldMismatch {
+                    field: "leftSideBearing",
+                    recorded: metric.lsb.into(),
+                    expected: x_min.into(),
+                };
+                out.push(b"hmtx", Some(gid as u16), kind);
+            }
+        }
+    }
+    Some(metrics)
+}
+
+/// Check the font bounding box in `head` against the glyphs.
+fn check_head_bbox(head: &[u8], glyphs: &[GlyphInfo], out: &mut Diagnostics) {
+    let Some(bbox) = (glyphs.iter().filter_map(|g| g.bbox)).reduce(|a, b| {
+        [
+            a[0].min(b[0]),
+            a[1].min(b[1]),
+            a[2].max(b[2]),
+            a[3].max(b[3]),
+        ]
+    }) else {
+        return;
+    };
+    let fields = [("xMin", 36), ("yMin", 38), ("xMax", 40), ("yMax", 42)];
+    for ((field, at), expected) in fields.into_iter().zip(bbox) {
+        let recorded = i16_at(head, at).unwrap_or_default();
+        out.expect(b"head", field, recorded.into(), expected.into());
+    }
+}
+
+/// Check the horizontal extents in `hhea` against the glyphs and their
+/// metrics.
+fn check_hhea(font: &Font<'_>, glyphs: &[GlyphInfo], metrics: &[Metric], out: &mut Diagnostics) {
+    let Some(hhea) = font.table(b"hhea") else {
+        return;
+    };
+    let advance_width_max = metrics.iter().map(|m| m.advance).max().unwrap_or(0);
+    let recorded = u16_at(hhea, 10).unwrap_or_default();
+    out.expect(
+        b"hhea",
+        "advanceWidthMax",
+        recorded.into(),
+        advance_width_max.into(),
+    );
+
+    // Glyphs without outlines don't count towards the extents
+    let extents = glyphs.iter().zip(metrics).filter_map(|(glyph, metric)| {
+        let [x_min, _, x_max, _] = glyph.bbox?;
+        let lsb = metric.lsb as i64;
+        let extent = lsb + x_max as i64 - x_min as i64;
+        Some((lsb, metric.advance as i64 - extent, extent))
+    });
+    let Some((min_lsb, min_rsb, x_max_extent)) =
+        extents.reduce(|(a0, a1, a2), (b0, b1, b2)| (a0.min(b0), a1.min(b1), a2.max(b2)))
+    else {
+        return;
+    };
+    let fields = [
+        ("minLeftSideBearing", 12, min_lsb),
+        ("minRightSideBearing", 14, min_rsb),
+        ("xMaxExtent", 16, x_max_extent),
+    ];
+    for (field, at, expected) in fields {
+        let recorded = i16_at(hhea, at).unwrap_or_default();
+        out.expect(b"hhea", field, recorded.into(), expected);
+    }
+}
+
+/// Check `OS/2` against `head` and the advances.
+fn check_os2(font: &Font<'_>, head: &[u8], metrics: &[Metric], out: &mut Diagnostics) {
+    let Some(os2) = font.table(b"OS/2") else {
+        return;
+    };
+    let Some(version) = u16_at(os2, 0) else {
+        out.push(b"OS/2", None, DiagnosticKind::Truncated);
+        return;
+    };
+    let Some(&expected) = OS2_LENGTHS.get(version as usize) else {
+        let kind = DiagnosticKind::OutOfRange {
+            field: "version",
+            value: version.into(),
+            min: 0,
+            max: OS2_LENGTHS.len() as i64 - 1,
+        };
+        out.push(b"OS/2", None, kind);
+        return;
+    };
+    if os2.len() != expected {
+        let kind = DiagnosticKind::Length {
+            expected,
+            actual: os2.len(),
+        };
+        out.push(b"OS/2", None, kind);
+        return;
+    }
+
+    // Since version 3, the average is taken over all non-zero advances
+    if version >= 3 {
+        let (sum, count) = (metrics.iter().filter(|m| m.advance != 0))
+            .fold((0u64, 0u64), |(sum, count), m| {
+                (sum + m.advance as u64, count + 1)
+            });
+        let expected = (sum + count / 2).checked_div(count).unwrap_or(0);
+        let recorded = i16_at(os2, 2).unwrap();
+        out.expect(b"OS/2", "xAvgCharWidth", recorded.into(), expected as i64);
+    }
+
+    let fs_selection = u16_at(os2, 62).unwrap();
+    let mac_style = u16_at(head, 44).unwrap_or_default();
+    let mut expected = mac_style & !(MAC_BOLD | MAC_ITALIC);
+    if fs_selection & FS_BOLD != 0 {
+        expected |= MAC_BOLD;
+    }
+    if fs_selection & FS_ITALIC != 0 {
+        expected |= MAC_ITALIC;
+    }
+    out.expect(b"head", "macStyle", mac_style.into(), expected.into());
+
+    let (y_min, y_max) = (i16_at(head, 38), i16_at(head, 42));
+    let (win_ascent, win_descent) = (u16_at(os2, 74).unwrap(), u16_at(os2, 76).unwrap());
+    if let Some(y_max) = y_max
+        && (win_ascent as i32) < y_max as i32
+    {
+        let kind = DiagnosticKind::ClippedByWinMetrics {
+            field: "usWinAscent",
+            win: win_ascent,
+            extent: y_max,
+        };
+        out.push(b"OS/2", None, kind);
+    }
+    if let Some(y_min) = y_min
+        && (win_descent as i32) < -(y_min as i32)
+    {
+        let kind = DiagnosticKind::ClippedByWinMetrics {
+            field: "usWinDescent",
+            win: win_descent,
+            extent: y_min,
+        };
+        out.push(b"OS/2", None, kind);
+    }
+}
+
+/// Check that fonts marked as fixed pitch in `post` are.
+fn check_post(font: &Font<'_>, metrics: &[Metric], out: &mut Diagnostics) {
+    let Some(post) = font.table(b"post") else {
+        return;
+    };
+    let Some(is_fixed_pitch) = u32_at(post, 12) else {
+        out.push(b"post", None, DiagnosticKind::Truncated);
+        return;
+    };
+    if is_fixed_pitch == 0 {
+        return;
+    }
+    let mut advances = metrics.iter().map(|m| m.advance).enumerate();
+    let Some((_, expected)) = advances.find(|&(_, a)| a != 0) else {
+        return;
+    };
+    for (gid, advance) in advances {
+        if advance != 0 && advance != expected {
+            let kind = DiagnosticKind::NotFixedPitch { advance, expected };
+            out.push(b"hmtx", Some(gid as u16), kind);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Checks of the `name` records.
+
+use super::{DiagnosticKind, Diagnostics, Font};
+use crate::read::u16_at;
+
+/// The names required for the Windows platform: family, subfamily, unique
+/// identifier, full name, version and PostScript name.
+const REQUIRED_WINDOWS_NAMES: &[u16] = &[1, 2, 3, 4, 5, 6];
+
+/// The name ID of the PostScript name.
+const POSTSCRIPT_NAME_ID: u16 = 6;
+
+/// Characters not allowed in PostScript names, besides whitespace and
+/// non-printable ASCII.
+const POSTSCRIPT_FORBIDDEN: &[u8] = b"[](){}<>/%";
+
+/// Maximum length of PostScript names.
+const POSTSCRIPT_MAX_LEN: usize = 63;
+
+const PLATFORM_UNICODE: u16 = 0;
+const PLATFORM_WINDOWS: u16 = 3;
+
+pub(super) fn check(font: &Font<'_>, out: &mut Diagnostics) {
+    let Some(name) = font.table(b"name") else {
+        return;
+    };
+    let (Some(count), Some(storage_offset)) = (u16_at(name, 2), u16_at(name, 4)) else {
+        out.push(b"name", None, DiagnosticKind::Truncated);
+        return;
+    };
+    if name.len() < 6 + 12 * count as usize {
+        out.push(b"name", None, DiagnosticKind::Truncated);
+        return;
+    }
+
+    let mut prev = None;
+    let mut windows_names = vec![];
+    for ix in 0..count as usize {
+        let [
+            platform_id,
+            encoding_id,
+            language_id,
+            name_id,
+            length,
+            offset,
+        ] = [0, 2, 4, 6, 8, 10].map(|at| u16_at(name, 6 + 12 * ix + at).unwrap());
+        let key = (platform_id, encoding_id, language_id, name_id);
+        match prev {
+            Some(prev) if prev == key => {
+                let kind = DiagnosticKind::DuplicateName {
+                    platform_id,
+                    name_id,
+                };
+                out.push(b"name", None, kind);
+            }
+            Some(prev) if prev > key => {
+                out.push(b"name", None, DiagnosticKind::UnsortedNameRecords)
+            }
+            _ => {}
+        }
+        prev = Some(key);
+        if platform_id == PLATFORM_WINDOWS {
+            windows_names.push(name_id);
+        }
+
+        let start = storage_offset as usize + offset as usize;
+        let Some(data) = name.get(start..start + length as usize) else {
+            out.push(b"name", None, DiagnosticKind::OutOfBounds);
+            continue;
+        };
+        let value = if matches!(platform_id, PLATFORM_UNICODE | PLATFORM_WINDOWS) {
+            let units = (data.chunks_exact(2)).map(|ch| u16::from_be_bytes([ch[0], ch[1]]));
+            match String::from_utf16(&units.collect::<Vec<_>>()) {
+                Ok(value) if data.len().is_multiple_of(2) => value,
+                _ => {
+                    let kind = DiagnosticKind::InvalidUtf16 {
+                        platform_id,
+                        name_id,
+                    };
+                    out.push(b"name", None, kind);
+                    continue;
+                }
+            }
+        } else {
+            // Only the ASCII subset of other encodings is checked
+            String::from_utf8_lossy(data).into_owned()
+        };
+        if name_id == POSTSCRIPT_NAME_ID && !is_valid_postscript_name(&value) {
+            out.push(b"name", None, DiagnosticKind::InvalidPostscriptName(value));
+        }
+    }
+
+    for &name_id in REQUIRED_WINDOWS_NAMES {
+        if !windows_names.contains(&name_id) {
+            let kind = DiagnosticKind::MissingName {
+                platform_id: PLATFORM_WINDOWS,
+                name_id,
+            };
+            out.push(b"name", None, kind);
+        }
+    }
+}
+
+fn is_valid_postscript_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= POSTSCRIPT_MAX_LEN
+        && (name.bytes()).all(|b| b.is_ascii_graphic() && !POSTSCRIPT_FORBIDDEN.contains(&b))
+}
@@ -1,26 +1,142 @@
-use std::fs::File;
+use std::{fs::File, path::PathBuf};
 
-use monoxide_font::make_font;
+use anyhow::Context;
+use clap::Parser;
+use monoxide_font::make_family;
 use monoxide_script::eval;
+use monoxide_ttf::{
+    dump,
+    model::{FontFile, WriteOptions, collection},
+    validate::{self, Severity},
+};
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 
+/// Generate the Monoxide font family.
+#[derive(Parser)]
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Generate the TTF files of all styles and the collection bundling them.
+    /// This is the default.
+    Build,
+
+    /// Dump font files as TTX-like XML, so that two builds can be compared
+    /// with ordinary text tools.
+    ///
+    /// Each dump is written next to its font with the extension `.ttx`. If no
+    /// font is given, the styles of the current design are dumped to
+    /// `Monoxide-<Style>.ttx` instead, without writing any font.
+    Dump {
+        /// The font files to dump.
+        fonts: Vec<PathBuf>,
+    },
+}
+
 fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive(LevelFilter::INFO.into()))
         .init();
+
+    match Args::parse().command.unwrap_or(Command::Build) {
+        Command::Build => build(),
+        Command::Dump { fonts } => dump(&fonts),
+    }
+}
+
+/// A style of the family, evaluated.
+struct Style {
+    name: &'static str,
+    /// The file name of the style, without extension.
+    file_stem: String,
+    font: FontFile,
+}
+
+fn eval_family() -> anyhow::Result<Vec<Style>> {
+    let mut styles = vec![];
+    for (style, font) in make_family() {
+        let aux = eval::AuxiliarySettings {
+            style_name: style.into(),
+            ..Default::default()
+        };
+        let (res, report) = eval::eval_with_report(&font, &aux)?;
+        for g in &report.exceeding_typo_box {
+            let name =
+                (res.post.glyph_names.as_ref()).and_then(|names| names.get(g.glyph_id as usize));
+            tracing::warn!(
+                "{style}: glyph {} ({}) spans {}..{}, beyond the typographic box",
+                g.glyph_id,
+                name.map_or("unnamed", |n| n.as_str()),
+                g.y_min,
+                g.y_max,
+            );
+        }
+        styles.push(Style {
+            name: style,
+            file_stem: format!("{}-{}", aux.family_name, style.replace(' ', "")),
+            font: res,
+        });
+    }
+    Ok(styles)
+}
+
+fn build() -> anyhow::Result<()> {
     eprintln!("Hello from Monoxide!");
 
-    let res = eval::eval(
-        &make_font(),
-        &eval::AuxiliarySettings {
-            point_per_em: 2048,
-            font_name: "Monoxide".into(),
-        },
-    )?;
+    let styles = eval_family()?;
+    for Style {
+        name: style,
+        file_stem,
+        font,
+    } in &styles
+    {
+        let fout = format!("{file_stem}.ttf");
+        let mut buf = vec![];
+        font.write(&WriteOptions::default(), &mut buf)?;
+        std::fs::write(&fout, &buf)?;
+        eprintln!("Successfully generated '{fout}'");
+
+        let diagnostics = validate::font_data(&buf);
+        for d in &diagnostics {
+            match d.severity() {
+                Severity::Warning => tracing::warn!("{style}: {d}"),
+                Severity::Error => tracing::error!("{style}: {d}"),
+            }
+        }
+        if diagnostics.iter().any(|d| d.severity() == Severity::Error) {
+            anyhow::bail!("'{fout}' failed validation");
+        }
+    }
 
-    let fout = "out.ttf";
-    res.write(File::create(fout)?)?;
+    let fonts = styles.into_iter().map(|s| s.font).collect::<Vec<_>>();
+    let fout = "Monoxide.ttc";
+    collection::write_collection(&fonts, &WriteOptions::default(), File::create(fout)?)?;
     eprintln!("Successfully generated '{fout}'");
 
     Ok(())
 }
+
+fn dump(fonts: &[PathBuf]) -> anyhow::Result<()> {
+    if fonts.is_empty() {
+        for style in eval_family()? {
+            let fout = format!("{}.ttx", style.file_stem);
+            std::fs::write(&fout, dump::font_file(&style.font)?)?;
+            eprintln!("Successfully dumped '{fout}'");
+        }
+        return Ok(());
+    }
+
+    for font in fonts {
+        let data =
+            std::fs::read(font).with_context(|| format!("failed to read '{}'", font.display()))?;
+        let ttx = dump::font_data(&data)
+            .with_context(|| format!("failed to dump '{}'", font.display()))?;
+        let fout = font.with_extension("ttx");
+        std::fs::write(&fout, ttx)?;
+        eprintln!("Successfully dumped '{}'", fout.display());
+    }
+    Ok(())
+}